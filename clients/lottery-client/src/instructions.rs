@@ -0,0 +1,900 @@
+//! Builders for every instruction of the program. PDAs are derived from the IDs given, and
+//! token accounts are the owners' associated token accounts; to use other token accounts, build
+//! the instruction from `lottery::accounts` directly.
+//!
+//! Ticket IDs are read from the lottery first: a purchase takes `last_ticket_id + 1`, a claim the
+//! tier's `winner_ticket_id`.
+
+use anchor_lang::{
+    prelude::*,
+    solana_program::{bpf_loader_upgradeable, instruction::Instruction, sysvar},
+    system_program, InstructionData,
+};
+use anchor_spl::associated_token;
+use lottery::{accounts, instruction as ix, CreateLotteryParams, SeriesParams};
+
+use crate::pda;
+
+/// Mint of a token lottery or a raffle prize, with the token program that owns it
+#[derive(Debug, Clone, Copy)]
+pub struct TokenMint {
+    pub mint: Pubkey,
+    pub token_program: Pubkey,
+}
+
+impl TokenMint {
+    fn vault(&self, owner: &Pubkey) -> Pubkey {
+        pda::token_account(owner, &self.mint, &self.token_program)
+    }
+}
+
+/// Where a winning ticket is recorded
+#[derive(Debug, Clone, Copy)]
+pub enum WinningTicket {
+    /// The ticket has its own `TicketPDA`
+    Pda(u32),
+    /// The ticket was bought in bulk and is recorded in the lottery's ticket ledger
+    Ledger,
+}
+
+fn build(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: lottery::ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+pub fn init_master(payer: &Pubkey) -> Instruction {
+    build(
+        accounts::InitMaster {
+            master_pda: pda::master(),
+            treasury_pda: pda::treasury(),
+            payer: *payer,
+            system_program: system_program::ID,
+        },
+        ix::InitMaster {},
+    )
+}
+
+/// `admin` must be the program's upgrade authority
+pub fn migrate_master(admin: &Pubkey) -> Instruction {
+    build(
+        accounts::MigrateMaster {
+            master_pda: pda::master(),
+            treasury_pda: pda::treasury(),
+            admin: *admin,
+            program: lottery::ID,
+            program_data: Pubkey::find_program_address(
+                &[lottery::ID.as_ref()],
+                &bpf_loader_upgradeable::ID,
+            )
+            .0,
+            system_program: system_program::ID,
+        },
+        ix::MigrateMaster {},
+    )
+}
+
+pub fn migrate_account(account: &Pubkey, payer: &Pubkey) -> Instruction {
+    build(
+        accounts::MigrateAccount {
+            account: *account,
+            payer: *payer,
+            system_program: system_program::ID,
+        },
+        ix::MigrateAccount {},
+    )
+}
+
+pub fn set_paused(admin: &Pubkey, paused: bool) -> Instruction {
+    build(
+        accounts::SetPaused {
+            master_pda: pda::master(),
+            admin: *admin,
+        },
+        ix::SetPaused { paused },
+    )
+}
+
+pub fn set_fee(admin: &Pubkey, fee_bps: u16) -> Instruction {
+    build(
+        accounts::SetFee {
+            master_pda: pda::master(),
+            admin: *admin,
+        },
+        ix::SetFee { fee_bps },
+    )
+}
+
+pub fn withdraw_fees(admin: &Pubkey, amount: u64) -> Instruction {
+    build(
+        accounts::WithdrawFees {
+            master_pda: pda::master(),
+            treasury_pda: pda::treasury(),
+            admin: *admin,
+        },
+        ix::WithdrawFees { amount },
+    )
+}
+
+pub fn withdraw_token_fees(admin: &Pubkey, fee_mint: TokenMint, amount: u64) -> Instruction {
+    build(
+        accounts::WithdrawTokenFees {
+            master_pda: pda::master(),
+            treasury_pda: pda::treasury(),
+            fee_mint: fee_mint.mint,
+            treasury_token_account: fee_mint.vault(&pda::treasury()),
+            admin_token_account: fee_mint.vault(admin),
+            admin: *admin,
+            token_program: fee_mint.token_program,
+        },
+        ix::WithdrawTokenFees { amount },
+    )
+}
+
+/// `lottery_id` is the master PDA's `last_lottery_id + 1`
+pub fn create_lottery(
+    lottery_id: u32,
+    authority: &Pubkey,
+    params: CreateLotteryParams,
+) -> Instruction {
+    build(
+        accounts::CreateLottery {
+            lottery_pda: pda::lottery(lottery_id),
+            master_pda: pda::master(),
+            authority: *authority,
+            system_program: system_program::ID,
+        },
+        ix::CreateLottery { params },
+    )
+}
+
+pub fn set_ticket_mint(lottery_id: u32, authority: &Pubkey, ticket_mint: TokenMint) -> Instruction {
+    let lottery = pda::lottery(lottery_id);
+    build(
+        accounts::SetTicketMint {
+            lottery_pda: lottery,
+            ticket_mint: ticket_mint.mint,
+            prize_vault: ticket_mint.vault(&lottery),
+            authority: *authority,
+            token_program: ticket_mint.token_program,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+        },
+        ix::SetTicketMint {},
+    )
+}
+
+pub fn deposit_prize(lottery_id: u32, authority: &Pubkey, prize_mint: TokenMint) -> Instruction {
+    let lottery = pda::lottery(lottery_id);
+    build(
+        accounts::DepositPrize {
+            lottery_pda: lottery,
+            prize_mint: prize_mint.mint,
+            prize_vault: prize_mint.vault(&lottery),
+            authority_prize_account: prize_mint.vault(authority),
+            authority: *authority,
+            token_program: prize_mint.token_program,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+        },
+        ix::DepositPrize {},
+    )
+}
+
+pub fn commit_randomness(lottery_id: u32, authority: &Pubkey, commitment: [u8; 32]) -> Instruction {
+    build(
+        accounts::CommitRandomness {
+            lottery_pda: pda::lottery(lottery_id),
+            authority: *authority,
+        },
+        ix::CommitRandomness { commitment },
+    )
+}
+
+pub fn transfer_lottery_authority(
+    lottery_id: u32,
+    authority: &Pubkey,
+    new_authority: Pubkey,
+) -> Instruction {
+    build(
+        accounts::TransferLotteryAuthority {
+            lottery_pda: pda::lottery(lottery_id),
+            authority: *authority,
+        },
+        ix::TransferLotteryAuthority { new_authority },
+    )
+}
+
+pub fn accept_lottery_authority(lottery_id: u32, new_authority: &Pubkey) -> Instruction {
+    build(
+        accounts::AcceptLotteryAuthority {
+            lottery_pda: pda::lottery(lottery_id),
+            new_authority: *new_authority,
+        },
+        ix::AcceptLotteryAuthority {},
+    )
+}
+
+pub fn buy_ticket(
+    lottery_id: u32,
+    ticket_id: u32,
+    buyer: &Pubkey,
+    referrer: Option<&Pubkey>,
+    entropy: Option<[u8; 32]>,
+    numbers: Vec<u8>,
+) -> Instruction {
+    let lottery = pda::lottery(lottery_id);
+    build(
+        accounts::BuyTicket {
+            ticket_pda: pda::ticket(&lottery, ticket_id),
+            lottery_pda: lottery,
+            wallet_tickets: pda::wallet_tickets(&lottery, buyer),
+            referral_pda: referrer.map(|referrer| pda::referral(&lottery, referrer)),
+            player_stats: pda::player_stats(buyer),
+            master_pda: pda::master(),
+            buyer: *buyer,
+            system_program: system_program::ID,
+        },
+        ix::BuyTicket { entropy, numbers },
+    )
+}
+
+pub fn buy_ticket_with_token(
+    lottery_id: u32,
+    ticket_id: u32,
+    buyer: &Pubkey,
+    ticket_mint: TokenMint,
+    entropy: Option<[u8; 32]>,
+) -> Instruction {
+    let lottery = pda::lottery(lottery_id);
+    build(
+        accounts::BuyTicketWithToken {
+            ticket_pda: pda::ticket(&lottery, ticket_id),
+            lottery_pda: lottery,
+            wallet_tickets: pda::wallet_tickets(&lottery, buyer),
+            ticket_mint: ticket_mint.mint,
+            prize_vault: ticket_mint.vault(&lottery),
+            buyer_token_account: ticket_mint.vault(buyer),
+            player_stats: pda::player_stats(buyer),
+            master_pda: pda::master(),
+            buyer: *buyer,
+            token_program: ticket_mint.token_program,
+            system_program: system_program::ID,
+        },
+        ix::BuyTicketWithToken { entropy },
+    )
+}
+
+pub fn buy_nft_ticket(
+    lottery_id: u32,
+    ticket_id: u32,
+    buyer: &Pubkey,
+    referrer: Option<&Pubkey>,
+    token_program: &Pubkey,
+    entropy: Option<[u8; 32]>,
+) -> Instruction {
+    let lottery = pda::lottery(lottery_id);
+    let ticket_nft_mint = pda::ticket_nft_mint(&lottery, ticket_id);
+    build(
+        accounts::BuyNftTicket {
+            ticket_pda: pda::ticket(&lottery, ticket_id),
+            lottery_pda: lottery,
+            wallet_tickets: pda::wallet_tickets(&lottery, buyer),
+            ticket_nft_mint,
+            buyer_nft_account: pda::token_account(buyer, &ticket_nft_mint, token_program),
+            referral_pda: referrer.map(|referrer| pda::referral(&lottery, referrer)),
+            player_stats: pda::player_stats(buyer),
+            master_pda: pda::master(),
+            buyer: *buyer,
+            token_program: *token_program,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+        },
+        ix::BuyNftTicket { entropy },
+    )
+}
+
+pub fn init_ticket_ledger(lottery_id: u32, authority: &Pubkey) -> Instruction {
+    let lottery = pda::lottery(lottery_id);
+    build(
+        accounts::InitTicketLedger {
+            ticket_ledger: pda::ticket_ledger(&lottery),
+            lottery_pda: lottery,
+            authority: *authority,
+            system_program: system_program::ID,
+        },
+        ix::InitTicketLedger {},
+    )
+}
+
+pub fn buy_tickets(
+    lottery_id: u32,
+    buyer: &Pubkey,
+    referrer: Option<&Pubkey>,
+    count: u32,
+    entropy: Option<[u8; 32]>,
+) -> Instruction {
+    let lottery = pda::lottery(lottery_id);
+    build(
+        accounts::BuyTickets {
+            ticket_ledger: pda::ticket_ledger(&lottery),
+            lottery_pda: lottery,
+            wallet_tickets: pda::wallet_tickets(&lottery, buyer),
+            referral_pda: referrer.map(|referrer| pda::referral(&lottery, referrer)),
+            player_stats: pda::player_stats(buyer),
+            master_pda: pda::master(),
+            buyer: *buyer,
+            system_program: system_program::ID,
+        },
+        ix::BuyTickets { count, entropy },
+    )
+}
+
+pub fn buy_ticket_for(
+    lottery_id: u32,
+    ticket_id: u32,
+    payer: &Pubkey,
+    recipient: Pubkey,
+    entropy: Option<[u8; 32]>,
+    numbers: Vec<u8>,
+) -> Instruction {
+    let lottery = pda::lottery(lottery_id);
+    build(
+        accounts::BuyTicketFor {
+            ticket_pda: pda::ticket(&lottery, ticket_id),
+            lottery_pda: lottery,
+            wallet_tickets: pda::wallet_tickets(&lottery, &recipient),
+            player_stats: pda::player_stats(&recipient),
+            master_pda: pda::master(),
+            payer: *payer,
+            system_program: system_program::ID,
+        },
+        ix::BuyTicketFor {
+            recipient,
+            entropy,
+            numbers,
+        },
+    )
+}
+
+/// Passes each recipient's `WalletTicketsPDA` and `PlayerStats` as the remaining accounts, in order
+pub fn buy_tickets_for(
+    lottery_id: u32,
+    payer: &Pubkey,
+    recipients: Vec<Pubkey>,
+    entropy: Option<[u8; 32]>,
+) -> Instruction {
+    let lottery = pda::lottery(lottery_id);
+    let mut instruction = build(
+        accounts::BuyTicketsFor {
+            ticket_ledger: pda::ticket_ledger(&lottery),
+            lottery_pda: lottery,
+            master_pda: pda::master(),
+            payer: *payer,
+            system_program: system_program::ID,
+        },
+        ix::BuyTicketsFor {
+            recipients: recipients.clone(),
+            entropy,
+        },
+    );
+    instruction
+        .accounts
+        .extend(recipients.iter().flat_map(|recipient| {
+            [
+                AccountMeta::new(pda::wallet_tickets(&lottery, recipient), false),
+                AccountMeta::new(pda::player_stats(recipient), false),
+            ]
+        }));
+    instruction
+}
+
+pub fn close_player_stats(player: &Pubkey) -> Instruction {
+    build(
+        accounts::ClosePlayerStats {
+            player_stats: pda::player_stats(player),
+            player: *player,
+        },
+        ix::ClosePlayerStats {},
+    )
+}
+
+pub fn transfer_ticket(
+    lottery_id: u32,
+    ticket_id: u32,
+    owner: &Pubkey,
+    new_owner: Pubkey,
+) -> Instruction {
+    let lottery = pda::lottery(lottery_id);
+    build(
+        accounts::TransferTicket {
+            lottery_pda: lottery,
+            ticket_pda: pda::ticket(&lottery, ticket_id),
+            owner: *owner,
+        },
+        ix::TransferTicket { new_owner },
+    )
+}
+
+pub fn list_ticket(
+    lottery_id: u32,
+    ticket_id: u32,
+    owner: &Pubkey,
+    price_lamports: u64,
+) -> Instruction {
+    let lottery = pda::lottery(lottery_id);
+    let ticket = pda::ticket(&lottery, ticket_id);
+    build(
+        accounts::ListTicket {
+            lottery_pda: lottery,
+            ticket_pda: ticket,
+            listing_pda: pda::listing(&ticket),
+            owner: *owner,
+            system_program: system_program::ID,
+        },
+        ix::ListTicket { price_lamports },
+    )
+}
+
+/// `price_lamports` is the listed price, so a changed listing fails instead of charging more
+pub fn buy_listed_ticket(
+    lottery_id: u32,
+    ticket_id: u32,
+    seller: &Pubkey,
+    buyer: &Pubkey,
+    price_lamports: u64,
+) -> Instruction {
+    let lottery = pda::lottery(lottery_id);
+    let ticket = pda::ticket(&lottery, ticket_id);
+    build(
+        accounts::BuyListedTicket {
+            lottery_pda: lottery,
+            ticket_pda: ticket,
+            listing_pda: pda::listing(&ticket),
+            seller: *seller,
+            buyer: *buyer,
+            system_program: system_program::ID,
+        },
+        ix::BuyListedTicket { price_lamports },
+    )
+}
+
+pub fn cancel_listing(
+    lottery_id: u32,
+    ticket_id: u32,
+    seller: &Pubkey,
+    caller: &Pubkey,
+) -> Instruction {
+    let lottery = pda::lottery(lottery_id);
+    let ticket = pda::ticket(&lottery, ticket_id);
+    build(
+        accounts::CancelListing {
+            lottery_pda: lottery,
+            ticket_pda: ticket,
+            listing_pda: pda::listing(&ticket),
+            seller: *seller,
+            caller: *caller,
+        },
+        ix::CancelListing {},
+    )
+}
+
+pub fn close_sales(lottery_id: u32) -> Instruction {
+    build(
+        accounts::CloseSales {
+            lottery_pda: pda::lottery(lottery_id),
+        },
+        ix::CloseSales {},
+    )
+}
+
+pub fn reveal_and_pick_winner(
+    lottery_id: u32,
+    authority: &Pubkey,
+    secret: [u8; 32],
+) -> Instruction {
+    build(
+        accounts::RevealAndPickWinner {
+            lottery_pda: pda::lottery(lottery_id),
+            authority: *authority,
+            slot_hashes: sysvar::slot_hashes::ID,
+        },
+        ix::RevealAndPickWinner { secret },
+    )
+}
+
+pub fn crank_draw(lottery_id: u32, caller: &Pubkey) -> Instruction {
+    build(
+        accounts::CrankDraw {
+            lottery_pda: pda::lottery(lottery_id),
+            master_pda: pda::master(),
+            caller: *caller,
+            slot_hashes: sysvar::slot_hashes::ID,
+        },
+        ix::CrankDraw {},
+    )
+}
+
+/// `prize_mint` is set for a raffle whose prize was deposited, so it goes back to `authority`
+pub fn cancel_lottery(
+    lottery_id: u32,
+    caller: &Pubkey,
+    authority: &Pubkey,
+    prize_mint: Option<TokenMint>,
+) -> Instruction {
+    let lottery = pda::lottery(lottery_id);
+    build(
+        accounts::CancelLottery {
+            lottery_pda: lottery,
+            caller: *caller,
+            prize_mint: prize_mint.map(|prize_mint| prize_mint.mint),
+            prize_vault: prize_mint.map(|prize_mint| prize_mint.vault(&lottery)),
+            authority_prize_account: prize_mint.map(|prize_mint| prize_mint.vault(authority)),
+            token_program: prize_mint.map(|prize_mint| prize_mint.token_program),
+        },
+        ix::CancelLottery {},
+    )
+}
+
+pub fn refund_ticket(lottery_id: u32, ticket_id: u32, owner: &Pubkey) -> Instruction {
+    let lottery = pda::lottery(lottery_id);
+    build(
+        accounts::RefundTicket {
+            lottery_pda: lottery,
+            ticket_pda: pda::ticket(&lottery, ticket_id),
+            owner: *owner,
+        },
+        ix::RefundTicket {},
+    )
+}
+
+pub fn refund_ticket_with_token(
+    lottery_id: u32,
+    ticket_id: u32,
+    owner: &Pubkey,
+    ticket_mint: TokenMint,
+) -> Instruction {
+    let lottery = pda::lottery(lottery_id);
+    build(
+        accounts::RefundTicketWithToken {
+            lottery_pda: lottery,
+            ticket_pda: pda::ticket(&lottery, ticket_id),
+            owner: *owner,
+            ticket_mint: ticket_mint.mint,
+            prize_vault: ticket_mint.vault(&lottery),
+            owner_token_account: ticket_mint.vault(owner),
+            token_program: ticket_mint.token_program,
+        },
+        ix::RefundTicketWithToken {},
+    )
+}
+
+/// `holder` burns the ticket NFT, the refund goes to the ticket's `owner`
+pub fn refund_nft_ticket(
+    lottery_id: u32,
+    ticket_id: u32,
+    owner: &Pubkey,
+    holder: &Pubkey,
+    token_program: &Pubkey,
+) -> Instruction {
+    let lottery = pda::lottery(lottery_id);
+    let ticket_nft_mint = pda::ticket_nft_mint(&lottery, ticket_id);
+    build(
+        accounts::RefundNftTicket {
+            lottery_pda: lottery,
+            ticket_pda: pda::ticket(&lottery, ticket_id),
+            owner: *owner,
+            ticket_nft_mint,
+            holder_nft_account: pda::token_account(holder, &ticket_nft_mint, token_program),
+            holder: *holder,
+            token_program: *token_program,
+        },
+        ix::RefundNftTicket {},
+    )
+}
+
+pub fn refund_ticket_range(lottery_id: u32, owner: &Pubkey, range_index: u32) -> Instruction {
+    let lottery = pda::lottery(lottery_id);
+    build(
+        accounts::RefundTicketRange {
+            lottery_pda: lottery,
+            ticket_ledger: pda::ticket_ledger(&lottery),
+            owner: *owner,
+        },
+        ix::RefundTicketRange { range_index },
+    )
+}
+
+pub fn close_ticket(lottery_id: u32, ticket_id: u32, owner: &Pubkey) -> Instruction {
+    let lottery = pda::lottery(lottery_id);
+    build(
+        accounts::CloseTicket {
+            ticket_pda: pda::ticket(&lottery, ticket_id),
+            lottery_pda: lottery,
+            owner: *owner,
+        },
+        ix::CloseTicket {},
+    )
+}
+
+/// `vault_mint` is the ticket mint of a token lottery or the prize mint of a raffle
+pub fn close_lottery(
+    lottery_id: u32,
+    authority: &Pubkey,
+    vault_mint: Option<TokenMint>,
+) -> Instruction {
+    let lottery = pda::lottery(lottery_id);
+    build(
+        accounts::CloseLottery {
+            lottery_pda: lottery,
+            ticket_ledger: pda::ticket_ledger(&lottery),
+            ticket_mint: vault_mint.map(|vault_mint| vault_mint.mint),
+            prize_vault: vault_mint.map(|vault_mint| vault_mint.vault(&lottery)),
+            authority_token_account: vault_mint.map(|vault_mint| vault_mint.vault(authority)),
+            jackpot_pda: pda::jackpot(),
+            authority: *authority,
+            token_program: vault_mint.map(|vault_mint| vault_mint.token_program),
+            system_program: system_program::ID,
+        },
+        ix::CloseLottery {},
+    )
+}
+
+pub fn claim_prize(
+    lottery_id: u32,
+    tier: u8,
+    winning_ticket: WinningTicket,
+    winner: &Pubkey,
+) -> Instruction {
+    let lottery = pda::lottery(lottery_id);
+    let (winner_ticket, ticket_ledger) = match winning_ticket {
+        WinningTicket::Pda(ticket_id) => (Some(pda::ticket(&lottery, ticket_id)), None),
+        WinningTicket::Ledger => (None, Some(pda::ticket_ledger(&lottery))),
+    };
+    build(
+        accounts::ClaimPrize {
+            lottery_pda: lottery,
+            winner_ticket,
+            ticket_ledger,
+            treasury_pda: pda::treasury(),
+            player_stats: pda::player_stats(winner),
+            winner: *winner,
+            system_program: system_program::ID,
+        },
+        ix::ClaimPrize { tier },
+    )
+}
+
+pub fn claim_prize_with_token(
+    lottery_id: u32,
+    tier: u8,
+    winner_ticket_id: u32,
+    winner: &Pubkey,
+    ticket_mint: TokenMint,
+) -> Instruction {
+    let lottery = pda::lottery(lottery_id);
+    build(
+        accounts::ClaimPrizeWithToken {
+            lottery_pda: lottery,
+            winner_ticket: pda::ticket(&lottery, winner_ticket_id),
+            ticket_mint: ticket_mint.mint,
+            prize_vault: ticket_mint.vault(&lottery),
+            winner_token_account: ticket_mint.vault(winner),
+            treasury_pda: pda::treasury(),
+            treasury_token_account: ticket_mint.vault(&pda::treasury()),
+            player_stats: pda::player_stats(winner),
+            winner: *winner,
+            token_program: ticket_mint.token_program,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+        },
+        ix::ClaimPrizeWithToken { tier },
+    )
+}
+
+/// `winner` is whoever holds the winning ticket's NFT
+pub fn claim_prize_nft(
+    lottery_id: u32,
+    tier: u8,
+    winner_ticket_id: u32,
+    winner: &Pubkey,
+    token_program: &Pubkey,
+) -> Instruction {
+    let lottery = pda::lottery(lottery_id);
+    let ticket_nft_mint = pda::ticket_nft_mint(&lottery, winner_ticket_id);
+    build(
+        accounts::ClaimPrizeNft {
+            lottery_pda: lottery,
+            ticket_nft_mint,
+            winner_nft_account: pda::token_account(winner, &ticket_nft_mint, token_program),
+            treasury_pda: pda::treasury(),
+            player_stats: pda::player_stats(winner),
+            winner: *winner,
+            token_program: *token_program,
+            system_program: system_program::ID,
+        },
+        ix::ClaimPrizeNft { tier },
+    )
+}
+
+pub fn init_referral(lottery_id: u32, referrer: &Pubkey) -> Instruction {
+    let lottery = pda::lottery(lottery_id);
+    build(
+        accounts::InitReferral {
+            lottery_pda: lottery,
+            referral_pda: pda::referral(&lottery, referrer),
+            referrer: *referrer,
+            system_program: system_program::ID,
+        },
+        ix::InitReferral {},
+    )
+}
+
+pub fn claim_referral_rewards(lottery_id: u32, referrer: &Pubkey) -> Instruction {
+    let lottery = pda::lottery(lottery_id);
+    build(
+        accounts::ClaimReferralRewards {
+            lottery_pda: lottery,
+            referral_pda: pda::referral(&lottery, referrer),
+            referrer: *referrer,
+        },
+        ix::ClaimReferralRewards {},
+    )
+}
+
+pub fn claim_raffle_prize(
+    lottery_id: u32,
+    winning_ticket: WinningTicket,
+    winner: &Pubkey,
+    prize_mint: TokenMint,
+) -> Instruction {
+    let lottery = pda::lottery(lottery_id);
+    let (winner_ticket, ticket_ledger) = match winning_ticket {
+        WinningTicket::Pda(ticket_id) => (Some(pda::ticket(&lottery, ticket_id)), None),
+        WinningTicket::Ledger => (None, Some(pda::ticket_ledger(&lottery))),
+    };
+    build(
+        accounts::ClaimRafflePrize {
+            lottery_pda: lottery,
+            winner_ticket,
+            ticket_ledger,
+            prize_mint: prize_mint.mint,
+            prize_vault: prize_mint.vault(&lottery),
+            winner_prize_account: prize_mint.vault(winner),
+            player_stats: pda::player_stats(winner),
+            winner: *winner,
+            token_program: prize_mint.token_program,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+        },
+        ix::ClaimRafflePrize {},
+    )
+}
+
+pub fn withdraw_proceeds(lottery_id: u32, authority: &Pubkey) -> Instruction {
+    build(
+        accounts::WithdrawProceeds {
+            lottery_pda: pda::lottery(lottery_id),
+            treasury_pda: pda::treasury(),
+            authority: *authority,
+        },
+        ix::WithdrawProceeds {},
+    )
+}
+
+pub fn register_match(lottery_id: u32, ticket_id: u32) -> Instruction {
+    let lottery = pda::lottery(lottery_id);
+    build(
+        accounts::RegisterMatch {
+            lottery_pda: lottery,
+            ticket_pda: pda::ticket(&lottery, ticket_id),
+        },
+        ix::RegisterMatch {},
+    )
+}
+
+pub fn claim_match_prize(lottery_id: u32, ticket_id: u32, owner: &Pubkey) -> Instruction {
+    let lottery = pda::lottery(lottery_id);
+    build(
+        accounts::ClaimMatchPrize {
+            lottery_pda: lottery,
+            ticket_pda: pda::ticket(&lottery, ticket_id),
+            treasury_pda: pda::treasury(),
+            player_stats: pda::player_stats(owner),
+            owner: *owner,
+            system_program: system_program::ID,
+        },
+        ix::ClaimMatchPrize {},
+    )
+}
+
+/// Without `next_lottery_id` the unclaimed prizes go to the jackpot
+pub fn rollover_unclaimed(
+    lottery_id: u32,
+    next_lottery_id: Option<u32>,
+    caller: &Pubkey,
+    ticket_mint: Option<TokenMint>,
+) -> Instruction {
+    let lottery = pda::lottery(lottery_id);
+    let next_lottery = next_lottery_id.map(pda::lottery);
+    build(
+        accounts::RolloverUnclaimed {
+            lottery_pda: lottery,
+            next_lottery,
+            jackpot_pda: pda::jackpot(),
+            ticket_mint: ticket_mint.map(|ticket_mint| ticket_mint.mint),
+            prize_vault: ticket_mint.map(|ticket_mint| ticket_mint.vault(&lottery)),
+            next_prize_vault: ticket_mint
+                .zip(next_lottery)
+                .map(|(ticket_mint, next_lottery)| ticket_mint.vault(&next_lottery)),
+            caller: *caller,
+            token_program: ticket_mint.map(|ticket_mint| ticket_mint.token_program),
+            system_program: system_program::ID,
+        },
+        ix::RolloverUnclaimed {},
+    )
+}
+
+pub fn create_series(authority: &Pubkey, series_id: u32, params: SeriesParams) -> Instruction {
+    build(
+        accounts::CreateSeries {
+            series: pda::series(authority, series_id),
+            authority: *authority,
+            system_program: system_program::ID,
+        },
+        ix::CreateSeries { series_id, params },
+    )
+}
+
+/// `current_lottery_id` is the series' current round, unset before the first one.
+/// `next_lottery_id` is the master PDA's `last_lottery_id + 1`.
+pub fn advance_series(
+    authority: &Pubkey,
+    series_id: u32,
+    current_lottery_id: Option<u32>,
+    next_lottery_id: u32,
+    caller: &Pubkey,
+) -> Instruction {
+    build(
+        accounts::AdvanceSeries {
+            series: pda::series(authority, series_id),
+            current_lottery: current_lottery_id.map(pda::lottery),
+            next_lottery: pda::lottery(next_lottery_id),
+            master_pda: pda::master(),
+            caller: *caller,
+            system_program: system_program::ID,
+        },
+        ix::AdvanceSeries {},
+    )
+}
+
+pub fn close_series(
+    authority: &Pubkey,
+    series_id: u32,
+    current_lottery_id: Option<u32>,
+) -> Instruction {
+    build(
+        accounts::CloseSeries {
+            series: pda::series(authority, series_id),
+            current_lottery: current_lottery_id.map(pda::lottery),
+            jackpot_pda: pda::jackpot(),
+            authority: *authority,
+            system_program: system_program::ID,
+        },
+        ix::CloseSeries {},
+    )
+}
+
+pub fn seed_from_jackpot(admin: &Pubkey, lottery_id: u32, amount: u64) -> Instruction {
+    build(
+        accounts::SeedFromJackpot {
+            master_pda: pda::master(),
+            jackpot_pda: pda::jackpot(),
+            lottery_pda: pda::lottery(lottery_id),
+            admin: *admin,
+        },
+        ix::SeedFromJackpot { amount },
+    )
+}
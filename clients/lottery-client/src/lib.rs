@@ -0,0 +1,10 @@
+//! Off-chain client for the lottery program: PDA derivation, instruction builders and account
+//! decoding, plus node queries behind the default `rpc` feature.
+
+pub mod instructions;
+pub mod pda;
+#[cfg(feature = "rpc")]
+pub mod rpc;
+pub mod state;
+
+pub use lottery::{self, ID};
@@ -0,0 +1,66 @@
+//! Addresses of the program's accounts. Lotteries are found by ID, everything scoped to one
+//! lottery is seeded by the lottery PDA's key.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+use lottery::{
+    LotteryPDA, MasterPDA, TicketPDA, JACKPOT_SEED, LISTING_SEED, PLAYER_STATS_SEED, REFERRAL_SEED,
+    SERIES_SEED, TICKET_LEDGER_SEED, TICKET_NFT_SEED, TREASURY_SEED, WALLET_TICKETS_SEED,
+};
+
+fn find(seeds: &[&[u8]]) -> Pubkey {
+    Pubkey::find_program_address(seeds, &lottery::ID).0
+}
+
+pub fn master() -> Pubkey {
+    MasterPDA::pda().0
+}
+
+pub fn treasury() -> Pubkey {
+    find(&[TREASURY_SEED])
+}
+
+pub fn jackpot() -> Pubkey {
+    find(&[JACKPOT_SEED])
+}
+
+pub fn lottery(lottery_id: u32) -> Pubkey {
+    LotteryPDA::pda(lottery_id).0
+}
+
+pub fn ticket(lottery: &Pubkey, ticket_id: u32) -> Pubkey {
+    TicketPDA::pda(lottery, ticket_id).0
+}
+
+pub fn ticket_nft_mint(lottery: &Pubkey, ticket_id: u32) -> Pubkey {
+    find(&[TICKET_NFT_SEED, lottery.as_ref(), &ticket_id.to_le_bytes()])
+}
+
+pub fn wallet_tickets(lottery: &Pubkey, owner: &Pubkey) -> Pubkey {
+    find(&[WALLET_TICKETS_SEED, lottery.as_ref(), owner.as_ref()])
+}
+
+pub fn ticket_ledger(lottery: &Pubkey) -> Pubkey {
+    find(&[TICKET_LEDGER_SEED, lottery.as_ref()])
+}
+
+pub fn referral(lottery: &Pubkey, referrer: &Pubkey) -> Pubkey {
+    find(&[REFERRAL_SEED, lottery.as_ref(), referrer.as_ref()])
+}
+
+pub fn player_stats(player: &Pubkey) -> Pubkey {
+    find(&[PLAYER_STATS_SEED, player.as_ref()])
+}
+
+pub fn listing(ticket: &Pubkey) -> Pubkey {
+    find(&[LISTING_SEED, ticket.as_ref()])
+}
+
+pub fn series(authority: &Pubkey, series_id: u32) -> Pubkey {
+    find(&[SERIES_SEED, authority.as_ref(), &series_id.to_le_bytes()])
+}
+
+/// Associated token account of `owner`, also the vault of a lottery when `owner` is its PDA
+pub fn token_account(owner: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    get_associated_token_address_with_program_id(owner, mint, token_program)
+}
@@ -0,0 +1,165 @@
+//! Account queries against a node, with the blocking RPC client
+
+use std::fmt;
+
+use anchor_lang::{prelude::*, AccountDeserialize, Discriminator};
+use lottery::{LotteryPDA, MasterPDA, TicketLedger, TicketPDA};
+use solana_account_decoder_client_types::UiAccountEncoding;
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_rpc_client_api::{
+    client_error::Error as ClientError,
+    config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    filter::{Memcmp, RpcFilterType},
+};
+
+use crate::{
+    pda,
+    state::{
+        self, DecodedTicketLedger, OwnedTicket, TICKET_LOTTERY_ID_OFFSET, TICKET_OWNER_OFFSET,
+    },
+};
+
+#[derive(Debug)]
+pub enum Error {
+    Rpc(Box<ClientError>),
+    /// The account exists but isn't of the expected type
+    Decode(anchor_lang::error::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Rpc(error) => write!(f, "RPC request failed: {error}"),
+            Error::Decode(error) => write!(f, "Account did not decode: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<ClientError> for Error {
+    fn from(error: ClientError) -> Self {
+        Error::Rpc(Box::new(error))
+    }
+}
+
+impl From<anchor_lang::error::Error> for Error {
+    fn from(error: anchor_lang::error::Error) -> Self {
+        Error::Decode(error)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+pub fn fetch<T: AccountDeserialize>(rpc: &RpcClient, address: &Pubkey) -> Result<T> {
+    let data = rpc.get_account_data(address)?;
+    Ok(state::decode(&data)?)
+}
+
+pub fn fetch_master(rpc: &RpcClient) -> Result<MasterPDA> {
+    fetch(rpc, &pda::master())
+}
+
+/// ID the next `create_lottery` or `advance_series` creates
+pub fn next_lottery_id(rpc: &RpcClient) -> Result<u32> {
+    Ok(fetch_master(rpc)?.last_lottery_id + 1)
+}
+
+/// Reads lotteries that weren't migrated yet as the current layout
+pub fn fetch_lottery(rpc: &RpcClient, lottery_id: u32) -> Result<LotteryPDA> {
+    let data = rpc.get_account_data(&pda::lottery(lottery_id))?;
+    Ok(state::decode_lottery(&data)?)
+}
+
+/// Reads tickets that weren't migrated yet as the current layout
+pub fn fetch_ticket(rpc: &RpcClient, lottery_id: u32, ticket_id: u32) -> Result<TicketPDA> {
+    let data = rpc.get_account_data(&pda::ticket(&pda::lottery(lottery_id), ticket_id))?;
+    Ok(state::decode_ticket(&data)?)
+}
+
+pub fn fetch_ticket_ledger(rpc: &RpcClient, lottery_id: u32) -> Result<DecodedTicketLedger> {
+    let data = rpc.get_account_data(&pda::ticket_ledger(&pda::lottery(lottery_id)))?;
+    Ok(state::decode_ticket_ledger(&data)?)
+}
+
+/// Raw accounts of the program starting with `discriminator`, narrowed by `filters`
+fn program_accounts(
+    rpc: &RpcClient,
+    discriminator: &[u8],
+    filters: Vec<RpcFilterType>,
+) -> Result<Vec<(Pubkey, Vec<u8>)>> {
+    let mut all_filters = vec![RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+        0,
+        discriminator,
+    ))];
+    all_filters.extend(filters);
+    let config = RpcProgramAccountsConfig {
+        filters: Some(all_filters),
+        account_config: RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            ..RpcAccountInfoConfig::default()
+        },
+        ..RpcProgramAccountsConfig::default()
+    };
+    let accounts = rpc.get_program_accounts_with_config(&lottery::ID, config)?;
+    Ok(accounts
+        .into_iter()
+        .map(|(address, account)| (address, account.data))
+        .collect())
+}
+
+/// Every lottery, sorted by ID. Lotteries that weren't migrated yet are read as the current
+/// layout.
+pub fn list_lotteries(rpc: &RpcClient) -> Result<Vec<(Pubkey, LotteryPDA)>> {
+    let mut lotteries = program_accounts(rpc, LotteryPDA::DISCRIMINATOR, vec![])?
+        .into_iter()
+        .map(|(address, data)| Ok((address, state::decode_lottery(&data)?)))
+        .collect::<Result<Vec<_>>>()?;
+    lotteries.sort_by_key(|(_, lottery)| lottery.id);
+    Ok(lotteries)
+}
+
+/// Every ticket `owner` holds, both `TicketPDA`s and ticket ledger ranges, in the lottery
+/// `lottery_id` or across all lotteries. NFT tickets are listed for the wallet that bought
+/// them, the NFT may have moved since.
+pub fn list_tickets_for_owner(
+    rpc: &RpcClient,
+    owner: &Pubkey,
+    lottery_id: Option<u32>,
+) -> Result<Vec<OwnedTicket>> {
+    let mut ticket_filters = vec![RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+        TICKET_OWNER_OFFSET,
+        owner.as_ref(),
+    ))];
+    if let Some(lottery_id) = lottery_id {
+        ticket_filters.push(RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+            TICKET_LOTTERY_ID_OFFSET,
+            &lottery_id.to_le_bytes(),
+        )));
+    }
+    let mut tickets = Vec::new();
+    for (address, data) in program_accounts(rpc, TicketPDA::DISCRIMINATOR, ticket_filters)? {
+        let ticket = state::decode_ticket(&data)?;
+        tickets.push(OwnedTicket::from_ticket_pda(address, &ticket));
+    }
+    // Ledger ranges aren't at fixed offsets, so only the ledgers themselves can be narrowed
+    let ledgers = match lottery_id {
+        Some(lottery_id) => {
+            let address = pda::ticket_ledger(&pda::lottery(lottery_id));
+            let account = rpc
+                .get_account_with_commitment(&address, rpc.commitment())?
+                .value;
+            account.map(|account| account.data).into_iter().collect()
+        }
+        None => program_accounts(rpc, TicketLedger::DISCRIMINATOR, vec![])?
+            .into_iter()
+            .map(|(_, data)| data)
+            .collect::<Vec<_>>(),
+    };
+    for data in ledgers {
+        let ledger = state::decode_ticket_ledger(&data)?;
+        tickets.extend(OwnedTicket::from_ledger(&ledger, owner));
+    }
+    tickets.sort_by_key(|ticket| (ticket.lottery, ticket.ticket_id));
+    Ok(tickets)
+}
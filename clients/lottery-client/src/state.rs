@@ -0,0 +1,108 @@
+//! Decoding of account data as fetched from a node
+
+use anchor_lang::{error::ErrorCode, prelude::*, AccountDeserialize, Discriminator, Space};
+use lottery::{LegacyLotteryPDAv0, LotteryPDA, TicketLedger, TicketPDA, TicketRange, Versioned};
+
+/// Offset of `TicketPDA::lottery_id`, right after the discriminator
+pub const TICKET_LOTTERY_ID_OFFSET: usize = 8;
+/// Offset of `TicketPDA::owner`, after the discriminator, `lottery_id` and `ticket_id`
+pub const TICKET_OWNER_OFFSET: usize = 8 + 4 + 4;
+
+/// Decodes any regular account of the program, checking its discriminator
+pub fn decode<T: AccountDeserialize>(data: &[u8]) -> Result<T> {
+    T::try_deserialize(&mut &data[..])
+}
+
+/// Decodes a versioned account as `migrate_account` would upgrade it, so accounts nobody
+/// migrated yet read like the others: an older layout is extended with zeroes, then the
+/// fields added since its version are filled in
+pub fn decode_versioned<T: AccountDeserialize + Space + Versioned>(data: &[u8]) -> Result<T> {
+    let mut data = data.to_vec();
+    if data.len() < 8 + T::INIT_SPACE {
+        data.resize(8 + T::INIT_SPACE, 0);
+    }
+    let mut account = decode::<T>(&data)?;
+    let from_version = *account.version_mut();
+    if from_version < T::VERSION {
+        account.migrate(from_version)?;
+        *account.version_mut() = T::VERSION;
+    }
+    Ok(account)
+}
+
+/// Decodes a lottery in any layout it was ever written with, including `LegacyLotteryPDAv0`
+pub fn decode_lottery(data: &[u8]) -> Result<LotteryPDA> {
+    if data.len() == LegacyLotteryPDAv0::LEN && data.starts_with(LotteryPDA::DISCRIMINATOR) {
+        return LegacyLotteryPDAv0::upgrade(data);
+    }
+    decode_versioned(data)
+}
+
+/// Decodes a ticket in any layout it was ever written with, including the unversioned one
+pub fn decode_ticket(data: &[u8]) -> Result<TicketPDA> {
+    decode_versioned(data)
+}
+
+/// A ticket ledger's header and the ranges recorded behind it
+#[derive(Debug, Clone)]
+pub struct DecodedTicketLedger {
+    pub ledger: TicketLedger,
+    pub ranges: Vec<TicketRange>,
+}
+
+/// The ticket ledger is zero-copy, so it's read straight from its bytes
+pub fn decode_ticket_ledger(data: &[u8]) -> Result<DecodedTicketLedger> {
+    if !data.starts_with(TicketLedger::DISCRIMINATOR) {
+        return Err(ErrorCode::AccountDiscriminatorMismatch.into());
+    }
+    let body = &data[TicketLedger::DISCRIMINATOR.len()..];
+    let header_size = std::mem::size_of::<TicketLedger>();
+    if body.len() < header_size {
+        return Err(ErrorCode::AccountDidNotDeserialize.into());
+    }
+    // Fetched data carries no alignment guarantee, so copy it out
+    let ledger: TicketLedger = bytemuck::pod_read_unaligned(&body[..header_size]);
+    let ranges: Vec<TicketRange> = body[header_size..]
+        .chunks_exact(std::mem::size_of::<TicketRange>())
+        .take(ledger.range_count as usize)
+        .map(bytemuck::pod_read_unaligned)
+        .collect();
+    if ranges.len() != ledger.range_count as usize {
+        return Err(ErrorCode::AccountDidNotDeserialize.into());
+    }
+    Ok(DecodedTicketLedger { ledger, ranges })
+}
+
+/// A ticket as held by one owner, whether it has a `TicketPDA` or sits in the ticket ledger
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OwnedTicket {
+    pub lottery: Pubkey,
+    pub ticket_id: u32,
+    /// Not set for tickets bought in bulk
+    pub ticket_pda: Option<Pubkey>,
+}
+
+impl OwnedTicket {
+    pub fn from_ticket_pda(address: Pubkey, ticket: &TicketPDA) -> Self {
+        Self {
+            lottery: crate::pda::lottery(ticket.lottery_id),
+            ticket_id: ticket.ticket_id,
+            ticket_pda: Some(address),
+        }
+    }
+
+    /// Every ticket `owner` holds in a lottery's ticket ledger
+    pub fn from_ledger(ledger: &DecodedTicketLedger, owner: &Pubkey) -> Vec<Self> {
+        ledger
+            .ranges
+            .iter()
+            .filter(|range| range.owner == *owner)
+            .flat_map(|range| range.first_ticket_id..range.first_ticket_id + range.count)
+            .map(|ticket_id| Self {
+                lottery: ledger.ledger.lottery,
+                ticket_id,
+                ticket_pda: None,
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,95 @@
+use anchor_lang::{prelude::*, Discriminator};
+use lottery::{TicketLedger, TicketRange, TICKET_SEED};
+use lottery_client::{
+    instructions::{self, WinningTicket},
+    pda,
+    state::{self, OwnedTicket},
+};
+
+#[test]
+fn test_tickets_are_seeded_by_the_lottery_key() {
+    let lottery = pda::lottery(3);
+    let expected = Pubkey::find_program_address(
+        &[TICKET_SEED, lottery.as_ref(), &7u32.to_le_bytes()],
+        &lottery::ID,
+    )
+    .0;
+    assert_eq!(pda::ticket(&lottery, 7), expected);
+    assert_ne!(pda::ticket(&pda::lottery(4), 7), expected);
+}
+
+#[test]
+fn test_builders_fill_derived_and_optional_accounts() {
+    let buyer = Pubkey::new_unique();
+    let lottery = pda::lottery(1);
+    let instruction = instructions::buy_ticket(1, 5, &buyer, None, None, vec![]);
+    let keys: Vec<Pubkey> = instruction
+        .accounts
+        .iter()
+        .map(|meta| meta.pubkey)
+        .collect();
+    assert_eq!(instruction.program_id, lottery::ID);
+    assert_eq!(keys[0], pda::ticket(&lottery, 5));
+    assert_eq!(keys[1], lottery);
+    assert_eq!(keys[2], pda::wallet_tickets(&lottery, &buyer));
+    // Anchor passes the program ID for a missing optional account
+    assert_eq!(keys[3], lottery::ID);
+    assert_eq!(keys[4], pda::player_stats(&buyer));
+
+    let recipients = vec![Pubkey::new_unique(), Pubkey::new_unique()];
+    let instruction = instructions::buy_tickets_for(1, &buyer, recipients.clone(), None);
+    let gifted = &instruction.accounts[instruction.accounts.len() - 4..];
+    for (metas, recipient) in gifted.chunks(2).zip(&recipients) {
+        assert_eq!(metas[0].pubkey, pda::wallet_tickets(&lottery, recipient));
+        assert_eq!(metas[1].pubkey, pda::player_stats(recipient));
+        assert!(metas.iter().all(|meta| meta.is_writable));
+    }
+
+    let instruction = instructions::claim_prize(1, 0, WinningTicket::Ledger, &buyer);
+    assert_eq!(instruction.accounts[1].pubkey, lottery::ID);
+    assert_eq!(instruction.accounts[2].pubkey, pda::ticket_ledger(&lottery));
+}
+
+#[test]
+fn test_ticket_ledger_decodes_owned_ranges() {
+    let owner = Pubkey::new_unique();
+    let other = Pubkey::new_unique();
+    let ranges = [
+        TicketRange {
+            owner,
+            first_ticket_id: 1,
+            count: 2,
+        },
+        TicketRange {
+            owner: other,
+            first_ticket_id: 3,
+            count: 4,
+        },
+        TicketRange {
+            owner,
+            first_ticket_id: 7,
+            count: 1,
+        },
+    ];
+    let ledger = TicketLedger {
+        lottery: pda::lottery(2),
+        range_count: 3,
+        version: 2,
+        _reserved: [0; 3],
+    };
+    // Offset by a byte, as fetched data has no alignment guarantee
+    let mut data = vec![0u8];
+    data.extend_from_slice(TicketLedger::DISCRIMINATOR);
+    data.extend_from_slice(bytemuck::bytes_of(&ledger));
+    data.extend_from_slice(bytemuck::cast_slice(&ranges));
+    // Room for another range, which isn't recorded yet
+    data.extend_from_slice(&[0; 40]);
+
+    let decoded = state::decode_ticket_ledger(&data[1..]).unwrap();
+    let ticket_ids: Vec<u32> = OwnedTicket::from_ledger(&decoded, &owner)
+        .iter()
+        .map(|ticket| ticket.ticket_id)
+        .collect();
+    assert_eq!(ticket_ids, vec![1, 2, 7]);
+    assert!(state::decode_ticket_ledger(&data[2..]).is_err());
+}
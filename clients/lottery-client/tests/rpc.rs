@@ -0,0 +1,196 @@
+#![cfg(feature = "rpc")]
+
+use anchor_lang::{prelude::*, AccountSerialize, Discriminator};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use lottery::{LegacyLotteryPDAv0, LotteryPDA, TicketLedger, TicketPDA, TicketRange, Versioned};
+use lottery_client::{pda, rpc};
+use serde_json::{json, Value};
+use solana_rpc_client::{mock_sender::Mocks, rpc_client::RpcClient};
+use solana_rpc_client_api::request::RpcRequest;
+
+const TICKET_PRICE: u64 = 10_000_000;
+
+/// Account data as a node returns it
+fn ui_account(data: &[u8]) -> Value {
+    json!({
+        "lamports": 1_000_000,
+        "data": [STANDARD.encode(data), "base64"],
+        "owner": lottery::ID.to_string(),
+        "executable": false,
+        "rentEpoch": 0,
+        "space": data.len(),
+    })
+}
+
+fn program_accounts(accounts: &[(Pubkey, Vec<u8>)]) -> Value {
+    accounts
+        .iter()
+        .map(
+            |(address, data)| json!({ "pubkey": address.to_string(), "account": ui_account(data) }),
+        )
+        .collect()
+}
+
+/// A lottery created before versioning, with `last_ticket_id` tickets sold
+fn legacy_lottery(lottery_id: u32, last_ticket_id: u32) -> Vec<u8> {
+    let mut data = LotteryPDA::DISCRIMINATOR.to_vec();
+    data.extend_from_slice(&lottery_id.to_le_bytes());
+    data.extend_from_slice(Pubkey::new_unique().as_ref());
+    data.extend_from_slice(&TICKET_PRICE.to_le_bytes());
+    data.extend_from_slice(&last_ticket_id.to_le_bytes());
+    // No winner yet, not claimed
+    data.extend_from_slice(&[0, 0]);
+    data.resize(LegacyLotteryPDAv0::LEN, 0);
+    data
+}
+
+/// A lottery written with version `version`, which had the current layout but its last two
+/// fields
+fn versioned_lottery(lottery_id: u32, version: u8, last_ticket_id: u32) -> Vec<u8> {
+    let mut lottery = LegacyLotteryPDAv0::upgrade(&legacy_lottery(lottery_id, 0)).unwrap();
+    lottery.last_ticket_id = last_ticket_id;
+    lottery.version = version;
+    let mut data = Vec::new();
+    lottery.try_serialize(&mut data).unwrap();
+    if version < LotteryPDA::VERSION {
+        // `prize_deposited` and `proceeds_withdrawn`
+        data.truncate(data.len() - 2);
+    }
+    data
+}
+
+/// A ticket created before versioning, which held no more than its IDs and owner
+fn legacy_ticket(lottery_id: u32, ticket_id: u32, owner: &Pubkey) -> Vec<u8> {
+    let mut data = TicketPDA::DISCRIMINATOR.to_vec();
+    data.extend_from_slice(&lottery_id.to_le_bytes());
+    data.extend_from_slice(&ticket_id.to_le_bytes());
+    data.extend_from_slice(owner.as_ref());
+    data
+}
+
+fn ticket(lottery_id: u32, ticket_id: u32, owner: &Pubkey) -> Vec<u8> {
+    let ticket = TicketPDA {
+        lottery_id,
+        ticket_id,
+        owner: *owner,
+        version: TicketPDA::VERSION,
+        numbers: Default::default(),
+        match_registered: false,
+        match_claimed: false,
+    };
+    let mut data = Vec::new();
+    ticket.try_serialize(&mut data).unwrap();
+    data
+}
+
+fn ticket_ledger(lottery_id: u32, ranges: &[TicketRange]) -> Vec<u8> {
+    let ledger = TicketLedger {
+        lottery: pda::lottery(lottery_id),
+        range_count: ranges.len() as u32,
+        version: TicketLedger::VERSION,
+        _reserved: [0; 3],
+    };
+    let mut data = TicketLedger::DISCRIMINATOR.to_vec();
+    data.extend_from_slice(bytemuck::bytes_of(&ledger));
+    data.extend_from_slice(bytemuck::cast_slice(ranges));
+    data
+}
+
+fn mock_rpc(mocks: Vec<(RpcRequest, Value)>) -> RpcClient {
+    RpcClient::new_mock_with_mocks("succeeds", mocks.into_iter().collect::<Mocks>())
+}
+
+#[test]
+fn test_list_lotteries_reads_unmigrated_layouts() {
+    let rpc = mock_rpc(vec![(
+        RpcRequest::GetProgramAccounts,
+        program_accounts(&[
+            (
+                pda::lottery(3),
+                versioned_lottery(3, LotteryPDA::VERSION, 0),
+            ),
+            (pda::lottery(1), legacy_lottery(1, 4)),
+            (pda::lottery(2), versioned_lottery(2, 5, 2)),
+        ]),
+    )]);
+
+    let lotteries = rpc::list_lotteries(&rpc).unwrap();
+    let ids: Vec<u32> = lotteries.iter().map(|(_, lottery)| lottery.id).collect();
+    assert_eq!(ids, vec![1, 2, 3]);
+    for (address, lottery) in &lotteries {
+        assert_eq!(*address, pda::lottery(lottery.id));
+        assert_eq!(lottery.version, LotteryPDA::VERSION);
+    }
+    // Both old layouts get the sales their migration would record
+    assert_eq!(lotteries[0].1.last_ticket_id, 4);
+    assert_eq!(lotteries[0].1.sales_lamports, 4 * TICKET_PRICE);
+    assert_eq!(lotteries[0].1.prize_tier_count, 1);
+    assert_eq!(lotteries[1].1.sales_lamports, 2 * TICKET_PRICE);
+    assert_eq!(lotteries[2].1.sales_lamports, 0);
+}
+
+#[test]
+fn test_list_tickets_for_owner_reads_legacy_tickets_and_the_lottery_ledger() {
+    let owner = Pubkey::new_unique();
+    let other = Pubkey::new_unique();
+    let lottery = pda::lottery(2);
+    let ranges = [
+        TicketRange {
+            owner,
+            first_ticket_id: 1,
+            count: 2,
+        },
+        TicketRange {
+            owner: other,
+            first_ticket_id: 3,
+            count: 3,
+        },
+    ];
+    let ledger = ticket_ledger(2, &ranges);
+    let rpc = mock_rpc(vec![
+        (
+            RpcRequest::GetProgramAccounts,
+            program_accounts(&[
+                (pda::ticket(&lottery, 9), ticket(2, 9, &owner)),
+                (pda::ticket(&lottery, 6), legacy_ticket(2, 6, &owner)),
+            ]),
+        ),
+        (
+            RpcRequest::GetAccountInfo,
+            json!({ "context": { "slot": 1 }, "value": ui_account(&ledger) }),
+        ),
+    ]);
+
+    let tickets = rpc::list_tickets_for_owner(&rpc, &owner, Some(2)).unwrap();
+    let ticket_ids: Vec<u32> = tickets.iter().map(|ticket| ticket.ticket_id).collect();
+    assert_eq!(ticket_ids, vec![1, 2, 6, 9]);
+    assert!(tickets.iter().all(|ticket| ticket.lottery == lottery));
+    assert_eq!(tickets[0].ticket_pda, None);
+    assert_eq!(tickets[2].ticket_pda, Some(pda::ticket(&lottery, 6)));
+
+    // A lottery without a ledger only has its `TicketPDA`s
+    let rpc = mock_rpc(vec![(
+        RpcRequest::GetProgramAccounts,
+        program_accounts(&[(pda::ticket(&lottery, 6), legacy_ticket(2, 6, &owner))]),
+    )]);
+    let tickets = rpc::list_tickets_for_owner(&rpc, &owner, Some(2)).unwrap();
+    assert_eq!(tickets.len(), 1);
+    assert_eq!(tickets[0].ticket_id, 6);
+}
+
+#[test]
+fn test_fetch_ticket_reads_a_legacy_ticket() {
+    let owner = Pubkey::new_unique();
+    let data = legacy_ticket(2, 6, &owner);
+    assert_eq!(data.len(), 48);
+    let rpc = mock_rpc(vec![(
+        RpcRequest::GetAccountInfo,
+        json!({ "context": { "slot": 1 }, "value": ui_account(&data) }),
+    )]);
+
+    let ticket = rpc::fetch_ticket(&rpc, 2, 6).unwrap();
+    assert_eq!(ticket.ticket_id, 6);
+    assert_eq!(ticket.owner, owner);
+    assert_eq!(ticket.version, TicketPDA::VERSION);
+    assert!(ticket.numbers.iter().all(|&number| number == 0));
+}
@@ -1,269 +1,3868 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{
+    hash::hashv,
+    program::{invoke, invoke_signed},
+    system_instruction,
+    sysvar::slot_hashes,
+};
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token_2022::{
+    spl_token_2022::{
+        self,
+        extension::{BaseStateWithExtensions, ExtensionType, StateWithExtensions},
+        instruction::AuthorityType,
+    },
+    Token2022,
+};
+use anchor_spl::token_2022_extensions::{
+    spl_token_metadata_interface::state::{Field, TokenMetadata},
+    token_metadata_initialize, token_metadata_update_field, TokenMetadataInitialize,
+    TokenMetadataUpdateField,
+};
+use anchor_spl::token_interface::{
+    burn, close_account, mint_to, set_authority, transfer_checked, Burn, CloseAccount, Mint,
+    MintTo, SetAuthority, TokenAccount, TokenInterface, TransferChecked,
+};
 
 declare_id!("HUJGjErk2znng9Ew3sUdfTfZUiXSSgigdG1ospGKsPwt");
 
 #[program]
 pub mod lottery {
     use super::*;
-    use anchor_lang::solana_program::{blake3::hash, program::invoke, system_instruction};
 
-    pub fn init_master(_ctx: Context<InitMaster>) -> Result<()> {
+    pub fn init_master(ctx: Context<InitMaster>) -> Result<()> {
         msg!("Initializing Master PDA for all lotteries...");
+        let master = &mut ctx.accounts.master_pda;
+        master.last_lottery_id = 0;
+        master.admin = ctx.accounts.payer.key();
+        master.fee_bps = 0;
+        master.paused = false;
+        master.version = MasterPDA::VERSION;
+        ctx.accounts.treasury_pda.version = TreasuryPDA::VERSION;
+        Ok(())
+    }
+
+    /// Upgrades a master PDA created before it had an admin and fee: grows the account to the
+    /// current size and creates the treasury. Only the program's upgrade authority may call it.
+    pub fn migrate_master(ctx: Context<MigrateMaster>) -> Result<()> {
+        let master_info = ctx.accounts.master_pda.to_account_info();
+        let admin = &ctx.accounts.admin;
+        if has_master_admin(&master_info) {
+            return err!(LotteryError::AlreadyMigrated);
+        }
+
+        grow_account(
+            &master_info,
+            &admin.to_account_info(),
+            &ctx.accounts.system_program,
+            8 + MasterPDA::INIT_SPACE,
+        )?;
+        // The appended fields are zeroed, so the old data now reads as the new layout
+        let mut master = MasterPDA::try_deserialize(&mut &master_info.try_borrow_data()?[..])?;
+        master.admin = admin.key();
+        master.fee_bps = 0;
+        master.paused = false;
+        master.version = MasterPDA::VERSION;
+        ctx.accounts.treasury_pda.version = TreasuryPDA::VERSION;
+        master.try_serialize(&mut &mut master_info.try_borrow_mut_data()?[..])?;
+        msg!("Migrated Master PDA, admin: {}", master.admin);
+        Ok(())
+    }
+
+    /// Brings any account of this program to the current layout of its type: grows it with
+    /// zeroes to its current size, the payer covering the extra rent, and bumps its version.
+    /// Anyone may call it, as it doesn't change what the account holds.
+    pub fn migrate_account(ctx: Context<MigrateAccount>) -> Result<()> {
+        let account = &ctx.accounts.account;
+        let payer = &ctx.accounts.payer.to_account_info();
+        let system_program = &ctx.accounts.system_program;
+        let mut discriminator = [0u8; 8];
+        discriminator.copy_from_slice(&account.try_borrow_data()?[..8]);
+
+        let version = match &discriminator[..] {
+            d if d == MasterPDA::DISCRIMINATOR => {
+                if !has_master_admin(account) {
+                    return err!(LotteryError::MasterNotMigrated);
+                }
+                migrate_versioned::<MasterPDA>(account, payer, system_program)?
+            }
+            d if d == TreasuryPDA::DISCRIMINATOR => {
+                migrate_versioned::<TreasuryPDA>(account, payer, system_program)?
+            }
+            d if d == JackpotPDA::DISCRIMINATOR => {
+                migrate_versioned::<JackpotPDA>(account, payer, system_program)?
+            }
+            d if d == LotteryPDA::DISCRIMINATOR => {
+                if account.data_len() == LegacyLotteryPDAv0::LEN {
+                    migrate_legacy_lottery(account, payer, system_program)?
+                } else {
+                    migrate_versioned::<LotteryPDA>(account, payer, system_program)?
+                }
+            }
+            d if d == WalletTicketsPDA::DISCRIMINATOR => {
+                migrate_versioned::<WalletTicketsPDA>(account, payer, system_program)?
+            }
+            d if d == TicketPDA::DISCRIMINATOR => {
+                migrate_versioned::<TicketPDA>(account, payer, system_program)?
+            }
+            d if d == ListingPDA::DISCRIMINATOR => {
+                migrate_versioned::<ListingPDA>(account, payer, system_program)?
+            }
+            d if d == ReferralPDA::DISCRIMINATOR => {
+                migrate_versioned::<ReferralPDA>(account, payer, system_program)?
+            }
+            d if d == LotterySeries::DISCRIMINATOR => {
+                migrate_versioned::<LotterySeries>(account, payer, system_program)?
+            }
+            d if d == PlayerStats::DISCRIMINATOR => {
+                migrate_versioned::<PlayerStats>(account, payer, system_program)?
+            }
+            _ => return err!(LotteryError::UnknownAccount),
+        };
+        msg!("Migrated account {} to version {}", account.key(), version);
+        Ok(())
+    }
+
+    /// Stops new lotteries, ticket sales and `crank_draw` while `paused`. Claims, refunds and the
+    /// authority's reveal keep working, so a pause longer than `REVEAL_WINDOW_SLOTS` doesn't
+    /// cost an authority its draw.
+    pub fn set_paused(ctx: Context<SetPaused>, paused: bool) -> Result<()> {
+        let master = &mut ctx.accounts.master_pda;
+        if ctx.accounts.admin.key() != master.admin {
+            return err!(LotteryError::UnauthorizedAction);
+        }
+
+        master.paused = paused;
+        msg!("Program paused: {}", paused);
+        Ok(())
+    }
+
+    pub fn set_fee(ctx: Context<SetFee>, fee_bps: u16) -> Result<()> {
+        let master = &mut ctx.accounts.master_pda;
+        if ctx.accounts.admin.key() != master.admin {
+            return err!(LotteryError::UnauthorizedAction);
+        }
+        if fee_bps > MAX_FEE_BPS {
+            return err!(LotteryError::FeeTooHigh);
+        }
+
+        master.fee_bps = fee_bps;
+        msg!("Protocol fee set to {} bps", fee_bps);
+        Ok(())
+    }
+
+    pub fn withdraw_fees(ctx: Context<WithdrawFees>, amount: u64) -> Result<()> {
+        let treasury = &ctx.accounts.treasury_pda.to_account_info();
+        let admin = &ctx.accounts.admin;
+        if admin.key() != ctx.accounts.master_pda.admin {
+            return err!(LotteryError::UnauthorizedAction);
+        }
+
+        let rent_exempt_minimum = Rent::get()?.minimum_balance(treasury.data_len());
+        if treasury.lamports().saturating_sub(rent_exempt_minimum) < amount {
+            return err!(LotteryError::InsufficientTreasuryBalance);
+        }
+
+        **admin.to_account_info().try_borrow_mut_lamports()? += amount;
+        **treasury.try_borrow_mut_lamports()? -= amount;
+        msg!("Withdrew {} lamports of protocol fees", amount);
+        Ok(())
+    }
+
+    /// Withdraws protocol fees collected in `fee_mint` by token lotteries
+    pub fn withdraw_token_fees(ctx: Context<WithdrawTokenFees>, amount: u64) -> Result<()> {
+        if ctx.accounts.admin.key() != ctx.accounts.master_pda.admin {
+            return err!(LotteryError::UnauthorizedAction);
+        }
+        if ctx.accounts.treasury_token_account.amount < amount {
+            return err!(LotteryError::InsufficientTreasuryBalance);
+        }
+
+        transfer_tokens(
+            &ctx.accounts.treasury_token_account,
+            &ctx.accounts.admin_token_account,
+            amount,
+            &ctx.accounts.fee_mint,
+            &ctx.accounts.treasury_pda.to_account_info(),
+            &ctx.accounts.token_program,
+            Some(&[TREASURY_SEED, &[ctx.bumps.treasury_pda]]),
+        )?;
+        msg!(
+            "Withdrew {} of protocol fees in mint {}",
+            amount,
+            ctx.accounts.fee_mint.key()
+        );
+        Ok(())
+    }
+
+    /// Creates a lottery without a randomness commitment. Every way of buying a ticket is
+    /// rejected with `RandomnessNotCommitted` until the authority calls `commit_randomness`.
+    pub fn create_lottery(ctx: Context<CreateLottery>, params: CreateLotteryParams) -> Result<()> {
+        msg!("Creating a new lottery...");
+        ctx.accounts.master_pda.check_not_paused()?;
+        let lottery = &mut ctx.accounts.lottery_pda;
+        lottery.init(
+            &mut ctx.accounts.master_pda,
+            ctx.accounts.authority.key(),
+            &params,
+        )?;
+        emit!(lottery.created_event(lottery.key()));
+        Ok(())
+    }
+
+    /// Switches a lottery from SOL to `ticket_mint` and creates its prize vault.
+    /// Only possible before the first ticket is sold.
+    pub fn set_ticket_mint(ctx: Context<SetTicketMint>) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery_pda;
+        if ctx.accounts.authority.key() != lottery.authority {
+            return err!(LotteryError::UnauthorizedAction);
+        }
+        if lottery.ticket_mint.is_some() {
+            return err!(LotteryError::TicketMintAlreadySet);
+        }
+        if lottery.last_ticket_id != 0 {
+            return err!(LotteryError::TicketsAlreadySold);
+        }
+        lottery.check_nft_tickets(false)?;
+        lottery.check_raffle(false)?;
+        // Jackpots and series carry-overs are in lamports, numbers games are SOL only
+        if lottery.jackpot_lamports != 0
+            || lottery.carry_over_bps != 0
+            || lottery.game_mode != GameMode::TicketDraw
+        {
+            return err!(LotteryError::WrongTicketMint);
+        }
+        check_no_transfer_fee(&ctx.accounts.ticket_mint)?;
+
+        lottery.ticket_mint = Some(ctx.accounts.ticket_mint.key());
+        msg!(
+            "Lottery ID: {} is now paid in mint {}",
+            lottery.id,
+            ctx.accounts.ticket_mint.key()
+        );
+        Ok(())
+    }
+
+    /// Moves the prize of an asset raffle from the authority into the lottery's prize vault.
+    /// Tickets can't be bought until it's deposited.
+    pub fn deposit_prize(ctx: Context<DepositPrize>) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery_pda;
+        if ctx.accounts.authority.key() != lottery.authority {
+            return err!(LotteryError::UnauthorizedAction);
+        }
+        lottery.check_active()?;
+        lottery.check_raffle(true)?;
+        if lottery.prize_mint != Some(ctx.accounts.prize_mint.key()) {
+            return err!(LotteryError::WrongPrizeMint);
+        }
+        if lottery.prize_deposited {
+            return err!(LotteryError::PrizeAlreadyDeposited);
+        }
+        check_no_transfer_fee(&ctx.accounts.prize_mint)?;
+
+        transfer_tokens(
+            &ctx.accounts.authority_prize_account,
+            &ctx.accounts.prize_vault,
+            lottery.prize_amount,
+            &ctx.accounts.prize_mint,
+            &ctx.accounts.authority.to_account_info(),
+            &ctx.accounts.token_program,
+            None,
+        )?;
+        lottery.prize_deposited = true;
+        msg!(
+            "Prize of {} of mint {} deposited for lottery ID: {}",
+            lottery.prize_amount,
+            ctx.accounts.prize_mint.key(),
+            lottery.id
+        );
+        Ok(())
+    }
+
+    /// Commits the authority to `sha256(secret)` before any ticket is sold.
+    /// Clients send this together with `create_lottery`; tickets can't be bought until it's set.
+    pub fn commit_randomness(ctx: Context<CommitRandomness>, commitment: [u8; 32]) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery_pda;
+        if ctx.accounts.authority.key() != lottery.authority {
+            return err!(LotteryError::UnauthorizedAction);
+        }
+        if lottery.has_commitment() {
+            return err!(LotteryError::RandomnessAlreadyCommitted);
+        }
+
+        lottery.randomness_commitment = commitment;
+        msg!("Randomness committed for lottery ID: {}", lottery.id);
+        Ok(())
+    }
+
+    /// First step of handing a lottery to `new_authority`, who then calls `accept_lottery_authority`.
+    /// Proposing again replaces the pending authority.
+    pub fn transfer_lottery_authority(
+        ctx: Context<TransferLotteryAuthority>,
+        new_authority: Pubkey,
+    ) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery_pda;
+        if ctx.accounts.authority.key() != lottery.authority {
+            return err!(LotteryError::UnauthorizedAction);
+        }
+
+        lottery.pending_authority = Some(new_authority);
+        msg!(
+            "Authority of lottery ID: {} proposed to {}",
+            lottery.id,
+            new_authority
+        );
+        Ok(())
+    }
+
+    pub fn accept_lottery_authority(ctx: Context<AcceptLotteryAuthority>) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery_pda;
+        let new_authority = ctx.accounts.new_authority.key();
+        if lottery.pending_authority != Some(new_authority) {
+            return err!(LotteryError::UnauthorizedAction);
+        }
+
+        lottery.authority = new_authority;
+        lottery.pending_authority = None;
+        msg!(
+            "Authority of lottery ID: {} is now {}",
+            lottery.id,
+            new_authority
+        );
+        Ok(())
+    }
+
+    /// `entropy` is an optional contribution from the buyer, folded into the lottery's accumulator.
+    /// `numbers` are the buyer's picks in a numbers game, and must be empty otherwise.
+    pub fn buy_ticket(
+        ctx: Context<BuyTicket>,
+        entropy: Option<[u8; 32]>,
+        numbers: Vec<u8>,
+    ) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery_pda;
+        msg!("Buying a ticket for lottery ID: {}", lottery.id);
+        let ticket = &mut ctx.accounts.ticket_pda;
+        let buyer = &ctx.accounts.buyer;
+        ctx.accounts.master_pda.check_not_paused()?;
+        lottery.check_sales_open(Clock::get()?.unix_timestamp)?;
+        lottery.check_ticket_mint(None)?;
+        lottery.check_nft_tickets(false)?;
+        let numbers = lottery.check_picks(&numbers)?;
+        let ticket_id = lottery.add_tickets(&mut ctx.accounts.wallet_tickets, 1)?;
+        let price = lottery.add_sales(1)?;
+        lottery.accrue_referral(ctx.accounts.referral_pda.as_deref_mut(), &buyer.key(), 1)?;
+        let first_entry = ctx.accounts.wallet_tickets.enter();
+        ctx.accounts
+            .player_stats
+            .record_purchase(buyer.key(), 1, price, first_entry);
+
+        // Transfer SOL to the lottery PDA
+        invoke(
+            &system_instruction::transfer(&buyer.key(), &lottery.key(), price),
+            &[
+                buyer.to_account_info(),
+                lottery.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+
+        if let Some(entropy) = entropy {
+            lottery.mix_entropy(&buyer.key(), &entropy);
+        }
+        // Create ticket account
+        ticket.lottery_id = lottery.id;
+        ticket.ticket_id = ticket_id;
+        ticket.owner = *buyer.key;
+        ticket.numbers = numbers;
+        ticket.version = TicketPDA::VERSION;
+
+        emit!(TicketBought {
+            lottery_id: lottery.id,
+            buyer: buyer.key(),
+            first_ticket_id: ticket_id,
+            count: 1,
+            amount_paid: price,
+            ticket_mint: lottery.ticket_mint,
+        });
+        Ok(())
+    }
+
+    /// `buy_ticket` for lotteries paid in their `ticket_mint`
+    pub fn buy_ticket_with_token(
+        ctx: Context<BuyTicketWithToken>,
+        entropy: Option<[u8; 32]>,
+    ) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery_pda;
+        msg!("Buying a ticket with tokens for lottery ID: {}", lottery.id);
+        let ticket = &mut ctx.accounts.ticket_pda;
+        let buyer = &ctx.accounts.buyer;
+        ctx.accounts.master_pda.check_not_paused()?;
+        lottery.check_sales_open(Clock::get()?.unix_timestamp)?;
+        lottery.check_ticket_mint(Some(ctx.accounts.ticket_mint.key()))?;
+        let ticket_id = lottery.add_tickets(&mut ctx.accounts.wallet_tickets, 1)?;
+        let price = lottery.add_sales(1)?;
+        let first_entry = ctx.accounts.wallet_tickets.enter();
+        ctx.accounts
+            .player_stats
+            .record_purchase(buyer.key(), 1, 0, first_entry);
+
+        transfer_tokens(
+            &ctx.accounts.buyer_token_account,
+            &ctx.accounts.prize_vault,
+            price,
+            &ctx.accounts.ticket_mint,
+            &buyer.to_account_info(),
+            &ctx.accounts.token_program,
+            None,
+        )?;
+
+        if let Some(entropy) = entropy {
+            lottery.mix_entropy(&buyer.key(), &entropy);
+        }
+        ticket.lottery_id = lottery.id;
+        ticket.ticket_id = ticket_id;
+        ticket.owner = *buyer.key;
+        ticket.version = TicketPDA::VERSION;
+
+        emit!(TicketBought {
+            lottery_id: lottery.id,
+            buyer: buyer.key(),
+            first_ticket_id: ticket_id,
+            count: 1,
+            amount_paid: price,
+            ticket_mint: lottery.ticket_mint,
+        });
+        Ok(())
+    }
+
+    /// `buy_ticket` for lotteries with `nft_tickets`: also mints the ticket to the buyer as a
+    /// supply-1 Token-2022 NFT carrying its lottery and ticket IDs in its metadata
+    pub fn buy_nft_ticket(ctx: Context<BuyNftTicket>, entropy: Option<[u8; 32]>) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery_pda;
+        msg!("Buying an NFT ticket for lottery ID: {}", lottery.id);
+        let ticket = &mut ctx.accounts.ticket_pda;
+        let buyer = &ctx.accounts.buyer;
+        ctx.accounts.master_pda.check_not_paused()?;
+        lottery.check_sales_open(Clock::get()?.unix_timestamp)?;
+        lottery.check_ticket_mint(None)?;
+        lottery.check_nft_tickets(true)?;
+        let ticket_id = lottery.add_tickets(&mut ctx.accounts.wallet_tickets, 1)?;
+        let price = lottery.add_sales(1)?;
+        lottery.accrue_referral(ctx.accounts.referral_pda.as_deref_mut(), &buyer.key(), 1)?;
+        let first_entry = ctx.accounts.wallet_tickets.enter();
+        ctx.accounts
+            .player_stats
+            .record_purchase(buyer.key(), 1, price, first_entry);
+
+        invoke(
+            &system_instruction::transfer(&buyer.key(), &lottery.key(), price),
+            &[
+                buyer.to_account_info(),
+                lottery.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+
+        if let Some(entropy) = entropy {
+            lottery.mix_entropy(&buyer.key(), &entropy);
+        }
+        // `owner` is only who gets the ticket's rent back, the NFT holder owns the ticket
+        ticket.lottery_id = lottery.id;
+        ticket.ticket_id = ticket_id;
+        ticket.owner = *buyer.key;
+        ticket.version = TicketPDA::VERSION;
+
+        let lottery_id_bytes = lottery.id.to_le_bytes();
+        mint_ticket_nft(
+            lottery,
+            &[
+                LOTTERY_SEED,
+                lottery_id_bytes.as_ref(),
+                &[ctx.bumps.lottery_pda],
+            ],
+            ticket_id,
+            &ctx.accounts.ticket_nft_mint,
+            &ctx.accounts.buyer_nft_account,
+            &buyer.to_account_info(),
+            &ctx.accounts.token_program,
+            &ctx.accounts.system_program,
+        )?;
+
+        emit!(TicketBought {
+            lottery_id: lottery.id,
+            buyer: buyer.key(),
+            first_ticket_id: ticket_id,
+            count: 1,
+            amount_paid: price,
+            ticket_mint: None,
+        });
+        Ok(())
+    }
+
+    /// Creates the ledger that `buy_tickets` records ticket ranges in
+    pub fn init_ticket_ledger(ctx: Context<InitTicketLedger>) -> Result<()> {
+        let lottery = &ctx.accounts.lottery_pda;
+        if ctx.accounts.authority.key() != lottery.authority {
+            return err!(LotteryError::UnauthorizedAction);
+        }
+
+        let mut ledger = ctx.accounts.ticket_ledger.load_init()?;
+        ledger.lottery = lottery.key();
+        ledger.range_count = 0;
+        ledger.version = TicketLedger::VERSION;
+        msg!("Ticket ledger created for lottery ID: {}", lottery.id);
+        Ok(())
+    }
+
+    /// Buys `count` consecutive tickets, recorded as one range in the ticket ledger
+    /// instead of one `TicketPDA` per ticket
+    pub fn buy_tickets(
+        ctx: Context<BuyTickets>,
+        count: u32,
+        entropy: Option<[u8; 32]>,
+    ) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery_pda;
+        msg!("Buying {} tickets for lottery ID: {}", count, lottery.id);
+        let buyer = &ctx.accounts.buyer;
+        ctx.accounts.master_pda.check_not_paused()?;
+        lottery.check_sales_open(Clock::get()?.unix_timestamp)?;
+        lottery.check_ticket_mint(None)?;
+        lottery.check_nft_tickets(false)?;
+        // Numbers games need each ticket's picks in its own `TicketPDA`
+        lottery.check_game_mode(GameMode::TicketDraw)?;
+        if count == 0 {
+            return err!(LotteryError::InvalidTicketCount);
+        }
+        let first_ticket_id = lottery.add_tickets(&mut ctx.accounts.wallet_tickets, count)?;
+
+        let total_price = lottery.add_sales(count)?;
+        lottery.accrue_referral(
+            ctx.accounts.referral_pda.as_deref_mut(),
+            &buyer.key(),
+            count,
+        )?;
+        let first_entry = ctx.accounts.wallet_tickets.enter();
+        ctx.accounts
+            .player_stats
+            .record_purchase(buyer.key(), count, total_price, first_entry);
+        invoke(
+            &system_instruction::transfer(&buyer.key(), &lottery.key(), total_price),
+            &[
+                buyer.to_account_info(),
+                lottery.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+
+        if let Some(entropy) = entropy {
+            lottery.mix_entropy(&buyer.key(), &entropy);
+        }
+        TicketLedger::record(
+            ctx.accounts.ticket_ledger.as_ref(),
+            &buyer.to_account_info(),
+            &ctx.accounts.system_program,
+            buyer.key(),
+            first_ticket_id,
+            count,
+        )?;
+        msg!(
+            "Tickets {} to {} bought",
+            first_ticket_id,
+            lottery.last_ticket_id
+        );
+
+        emit!(TicketBought {
+            lottery_id: lottery.id,
+            buyer: buyer.key(),
+            first_ticket_id,
+            count,
+            amount_paid: total_price,
+            ticket_mint: None,
+        });
+        Ok(())
+    }
+
+    /// `buy_ticket` paid by `payer` for a ticket owned by `recipient`, who then gets its refund
+    /// or prize. The ticket counts towards the recipient's `max_tickets_per_wallet` and stats.
+    pub fn buy_ticket_for(
+        ctx: Context<BuyTicketFor>,
+        recipient: Pubkey,
+        entropy: Option<[u8; 32]>,
+        numbers: Vec<u8>,
+    ) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery_pda;
+        msg!(
+            "Buying a ticket for {} in lottery ID: {}",
+            recipient,
+            lottery.id
+        );
+        let ticket = &mut ctx.accounts.ticket_pda;
+        let payer = &ctx.accounts.payer;
+        ctx.accounts.master_pda.check_not_paused()?;
+        lottery.check_sales_open(Clock::get()?.unix_timestamp)?;
+        lottery.check_ticket_mint(None)?;
+        lottery.check_nft_tickets(false)?;
+        let numbers = lottery.check_picks(&numbers)?;
+        let ticket_id = lottery.add_tickets(&mut ctx.accounts.wallet_tickets, 1)?;
+        let price = lottery.add_sales(1)?;
+        let first_entry = ctx.accounts.wallet_tickets.enter();
+        ctx.accounts
+            .player_stats
+            .record_purchase(recipient, 1, 0, first_entry);
+
+        invoke(
+            &system_instruction::transfer(&payer.key(), &lottery.key(), price),
+            &[
+                payer.to_account_info(),
+                lottery.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+
+        if let Some(entropy) = entropy {
+            lottery.mix_entropy(&payer.key(), &entropy);
+        }
+        ticket.lottery_id = lottery.id;
+        ticket.ticket_id = ticket_id;
+        ticket.owner = recipient;
+        ticket.numbers = numbers;
+        ticket.version = TicketPDA::VERSION;
+
+        emit!(TicketBought {
+            lottery_id: lottery.id,
+            buyer: recipient,
+            first_ticket_id: ticket_id,
+            count: 1,
+            amount_paid: price,
+            ticket_mint: None,
+        });
+        Ok(())
+    }
+
+    /// Gifts one ticket to each of `recipients`, paid by `payer` and recorded in the ticket ledger.
+    /// The remaining accounts are each recipient's `WalletTicketsPDA` followed by its
+    /// `PlayerStats`, in the same order; those that don't exist yet are created.
+    /// At most `MAX_GIFT_RECIPIENTS` per call.
+    pub fn buy_tickets_for<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BuyTicketsFor<'info>>,
+        recipients: Vec<Pubkey>,
+        entropy: Option<[u8; 32]>,
+    ) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery_pda;
+        msg!(
+            "Gifting {} tickets in lottery ID: {}",
+            recipients.len(),
+            lottery.id
+        );
+        let payer = &ctx.accounts.payer;
+        ctx.accounts.master_pda.check_not_paused()?;
+        lottery.check_sales_open(Clock::get()?.unix_timestamp)?;
+        lottery.check_ticket_mint(None)?;
+        lottery.check_nft_tickets(false)?;
+        lottery.check_game_mode(GameMode::TicketDraw)?;
+        if recipients.is_empty()
+            || recipients.len() > MAX_GIFT_RECIPIENTS
+            || ctx.remaining_accounts.len() != 2 * recipients.len()
+        {
+            return err!(LotteryError::InvalidRecipients);
+        }
+
+        let lottery_key = lottery.key();
+        let payer_info = payer.to_account_info();
+        let system_program = &ctx.accounts.system_program;
+        let mut total_price = 0u64;
+        for (recipient, accounts) in recipients.iter().zip(ctx.remaining_accounts.chunks(2)) {
+            let mut wallet_tickets = load_or_create_pda::<WalletTicketsPDA>(
+                &accounts[0],
+                &payer_info,
+                system_program,
+                &[
+                    WALLET_TICKETS_SEED,
+                    lottery_key.as_ref(),
+                    recipient.as_ref(),
+                ],
+            )?;
+            let mut player_stats = load_or_create_pda::<PlayerStats>(
+                &accounts[1],
+                &payer_info,
+                system_program,
+                &[PLAYER_STATS_SEED, recipient.as_ref()],
+            )?;
+            let ticket_id = lottery.add_tickets(&mut wallet_tickets, 1)?;
+            let price = lottery.add_sales(1)?;
+            // Can't overflow, the sales already hold it
+            total_price += price;
+            let first_entry = wallet_tickets.enter();
+            player_stats.record_purchase(*recipient, 1, 0, first_entry);
+            // Written back right away, a recipient listed twice loads them again
+            wallet_tickets.exit(&crate::ID)?;
+            player_stats.exit(&crate::ID)?;
+            TicketLedger::record(
+                ctx.accounts.ticket_ledger.as_ref(),
+                &payer_info,
+                system_program,
+                *recipient,
+                ticket_id,
+                1,
+            )?;
+
+            emit!(TicketBought {
+                lottery_id: lottery.id,
+                buyer: *recipient,
+                first_ticket_id: ticket_id,
+                count: 1,
+                amount_paid: price,
+                ticket_mint: None,
+            });
+        }
+
+        invoke(
+            &system_instruction::transfer(&payer.key(), &lottery.key(), total_price),
+            &[
+                payer.to_account_info(),
+                lottery.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+        if let Some(entropy) = entropy {
+            lottery.mix_entropy(&payer.key(), &entropy);
+        }
+        Ok(())
+    }
+
+    /// Closes the player's stats account, returning its rent
+    pub fn close_player_stats(ctx: Context<ClosePlayerStats>) -> Result<()> {
+        msg!("Stats of player {} closed", ctx.accounts.player.key());
+        Ok(())
+    }
+
+    /// Gives a ticket bought with `buy_ticket` to `new_owner`, who then gets its refund or prize
+    pub fn transfer_ticket(ctx: Context<TransferTicket>, new_owner: Pubkey) -> Result<()> {
+        // NFT tickets change hands by transferring the NFT
+        ctx.accounts.lottery_pda.check_nft_tickets(false)?;
+        let ticket = &mut ctx.accounts.ticket_pda;
+        ticket.owner = new_owner;
+        msg!(
+            "Ticket {} of lottery ID: {} transferred to {}",
+            ticket.ticket_id,
+            ticket.lottery_id,
+            new_owner
+        );
+        Ok(())
+    }
+
+    /// Offers a ticket for sale at `price_lamports` until the winners are drawn
+    pub fn list_ticket(ctx: Context<ListTicket>, price_lamports: u64) -> Result<()> {
+        let lottery = &ctx.accounts.lottery_pda;
+        let ticket = &ctx.accounts.ticket_pda;
+        lottery.check_active()?;
+        lottery.check_nft_tickets(false)?;
+        if lottery.is_drawn() {
+            return err!(LotteryError::ListingClosed);
+        }
+
+        let listing = &mut ctx.accounts.listing_pda;
+        listing.lottery_id = lottery.id;
+        listing.ticket_id = ticket.ticket_id;
+        listing.seller = ticket.owner;
+        listing.price_lamports = price_lamports;
+        listing.version = ListingPDA::VERSION;
+        msg!(
+            "Ticket {} of lottery ID: {} listed for {} lamports",
+            ticket.ticket_id,
+            lottery.id,
+            price_lamports
+        );
+        Ok(())
+    }
+
+    /// Buys a listed ticket, paying the seller. `price_lamports` must match the listing, so a
+    /// seller can't raise the price under a pending purchase.
+    pub fn buy_listed_ticket(ctx: Context<BuyListedTicket>, price_lamports: u64) -> Result<()> {
+        let lottery = &ctx.accounts.lottery_pda;
+        let ticket = &mut ctx.accounts.ticket_pda;
+        let listing = &ctx.accounts.listing_pda;
+        let buyer = &ctx.accounts.buyer;
+        lottery.check_active()?;
+        if lottery.is_drawn() {
+            return err!(LotteryError::ListingClosed);
+        }
+        if ticket.owner != listing.seller {
+            return err!(LotteryError::ListingStale);
+        }
+        if price_lamports != listing.price_lamports {
+            return err!(LotteryError::ListingPriceChanged);
+        }
+
+        invoke(
+            &system_instruction::transfer(&buyer.key(), &listing.seller, listing.price_lamports),
+            &[
+                buyer.to_account_info(),
+                ctx.accounts.seller.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+        ticket.owner = buyer.key();
+        msg!(
+            "Listed ticket {} of lottery ID: {} bought for {} lamports",
+            ticket.ticket_id,
+            lottery.id,
+            listing.price_lamports
+        );
+        Ok(())
+    }
+
+    /// Closes a listing, returning its rent to the seller. The seller may cancel at any time,
+    /// anyone else once the listing can no longer be bought: the winners are drawn, the lottery
+    /// is cancelled or closed, or the ticket changed hands.
+    pub fn cancel_listing(ctx: Context<CancelListing>) -> Result<()> {
+        let listing = &ctx.accounts.listing_pda;
+        if ctx.accounts.caller.key() != listing.seller {
+            let lottery_info = ctx.accounts.lottery_pda.to_account_info();
+            let ticket_info = ctx.accounts.ticket_pda.to_account_info();
+            let lottery_open = !lottery_info.data_is_empty() && {
+                let lottery =
+                    LotteryPDA::try_deserialize(&mut &lottery_info.try_borrow_data()?[..])?;
+                lottery.status == LotteryStatus::Active && !lottery.is_drawn()
+            };
+            let seller_owns_ticket = !ticket_info.data_is_empty() && {
+                let ticket = TicketPDA::try_deserialize(&mut &ticket_info.try_borrow_data()?[..])?;
+                ticket.owner == listing.seller
+            };
+            if lottery_open && seller_owns_ticket {
+                return err!(LotteryError::UnauthorizedAction);
+            }
+        }
+
+        msg!(
+            "Listing of ticket {} of lottery ID: {} cancelled",
+            listing.ticket_id,
+            listing.lottery_id
+        );
+        Ok(())
+    }
+
+    /// Fixes the slot whose hash seeds the draw. Permissionless once `draw_after_ts` has passed.
+    /// The reveal must land in a later slot, within `REVEAL_WINDOW_SLOTS`.
+    pub fn close_sales(ctx: Context<CloseSales>) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery_pda;
+        lottery.check_active()?;
+        lottery.close_sales(&Clock::get()?)
+    }
+
+    pub fn reveal_and_pick_winner(
+        ctx: Context<RevealAndPickWinner>,
+        secret: [u8; 32],
+    ) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery_pda;
+        let authority = &ctx.accounts.authority;
+        lottery.check_active()?;
+
+        if lottery.is_drawn() {
+            return err!(LotteryError::WinnerAlreadyExists);
+        }
+        if authority.key() != lottery.authority {
+            return err!(LotteryError::UnauthorizedAction);
+        }
+        if lottery.last_ticket_id == 0 {
+            return err!(LotteryError::NoTicketsPurchasedYet);
+        }
+        if lottery.sales_closed_slot.is_none() {
+            return err!(LotteryError::SalesNotClosed);
+        }
+        let clock = Clock::get()?;
+        if clock.unix_timestamp < lottery.draw_after_ts {
+            return err!(LotteryError::DrawTooEarly);
+        }
+        if lottery.reveal_forfeited || clock.slot > lottery.entropy_slot + REVEAL_WINDOW_SLOTS {
+            return err!(LotteryError::RevealWindowExpired);
+        }
+        if hashv(&[&secret]).to_bytes() != lottery.randomness_commitment {
+            return err!(LotteryError::InvalidReveal);
+        }
+
+        msg!("Picking a winner for lottery ID: {}", lottery.id);
+        // The secret was committed before sales opened, the buyers' entropy is final once sales
+        // closed, and the slot hash was unknown to everyone when `close_sales` fixed the slot.
+        let slot_hash = slot_hash_at(&ctx.accounts.slot_hashes, lottery.entropy_slot)?;
+        let seed = hashv(&[&secret, &lottery.entropy_accumulator, &slot_hash]).to_bytes();
+        lottery.revealed_secret = secret;
+        lottery.draw_winners(seed)?;
+        lottery.log_winners();
+        Ok(())
+    }
+
+    /// Moves a stalled draw forward for anyone, one step per call. The first call after
+    /// `draw_after_ts` closes the sales. The draw stays the authority's during its reveal window;
+    /// once that is over without a reveal, the next call forfeits the reveal and fixes a new
+    /// entropy slot, and a call in a later slot draws from the buyers' entropy and that slot's
+    /// hash, earning `crank_bounty_lamports` from the ticket sales, at most
+    /// `MAX_CRANK_BOUNTY_BPS` of them. An authority withholding its reveal thus only gets a fresh
+    /// draw that nobody knew in advance, never one of its choosing.
+    pub fn crank_draw(ctx: Context<CrankDraw>) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery_pda;
+        ctx.accounts.master_pda.check_not_paused()?;
+        lottery.check_active()?;
+
+        let clock = Clock::get()?;
+        if lottery.sales_closed_slot.is_none() {
+            return lottery.close_sales(&clock);
+        }
+        if lottery.is_drawn() {
+            return err!(LotteryError::WinnerAlreadyExists);
+        }
+        if !lottery.reveal_forfeited {
+            return lottery.forfeit_reveal(clock.slot);
+        }
+        if clock.slot <= lottery.entropy_slot {
+            return err!(LotteryError::SlotHashNotAvailable);
+        }
+        if clock.slot > lottery.entropy_slot + REVEAL_WINDOW_SLOTS {
+            // Nobody cranked while the hash was in SlotHashes, fix a new slot
+            lottery.entropy_slot = clock.slot;
+            msg!(
+                "Entropy slot of lottery ID: {} moved to {}",
+                lottery.id,
+                clock.slot
+            );
+            return Ok(());
+        }
+
+        let bounty = lottery.crank_bounty();
+        if bounty > 0 {
+            withdraw_lamports(&lottery.to_account_info(), bounty)?;
+            **ctx.accounts.caller.try_borrow_mut_lamports()? += bounty;
+            lottery.sales_lamports -= bounty;
+            lottery.crank_bounty_paid_lamports = bounty;
+            msg!(
+                "Crank bounty of {} lamports paid to {}",
+                bounty,
+                ctx.accounts.caller.key()
+            );
+        }
+
+        msg!("Picking a winner for lottery ID: {}", lottery.id);
+        let slot_hash = slot_hash_at(&ctx.accounts.slot_hashes, lottery.entropy_slot)?;
+        let seed = hashv(&[&lottery.entropy_accumulator, &slot_hash]).to_bytes();
+        lottery.draw_winners(seed)?;
+        lottery.log_winners();
+        Ok(())
+    }
+
+    /// Cancels a lottery so its tickets can be refunded. The authority can cancel until sales are
+    /// closed; anyone can cancel once the sale has ended without reaching `min_tickets`.
+    pub fn cancel_lottery(ctx: Context<CancelLottery>) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery_pda;
+        lottery.check_active()?;
+        if lottery.is_drawn() {
+            return err!(LotteryError::WinnerAlreadyExists);
+        }
+
+        if ctx.accounts.caller.key() == lottery.authority {
+            // Once sales are closed the draw slot is fixed, and cancelling could dodge an unwanted outcome
+            if lottery.sales_closed_slot.is_some() {
+                return err!(LotteryError::SalesClosed);
+            }
+        } else {
+            let now = Clock::get()?.unix_timestamp;
+            if now < lottery.sale_end_ts || lottery.last_ticket_id >= lottery.min_tickets {
+                return err!(LotteryError::CannotCancelLottery);
+            }
+        }
+
+        // The prize of an asset raffle goes straight back to the authority
+        if lottery.prize_deposited {
+            let (
+                Some(prize_mint),
+                Some(prize_vault),
+                Some(authority_prize_account),
+                Some(token_program),
+            ) = (
+                &ctx.accounts.prize_mint,
+                &ctx.accounts.prize_vault,
+                &ctx.accounts.authority_prize_account,
+                &ctx.accounts.token_program,
+            )
+            else {
+                return err!(LotteryError::WrongPrizeMint);
+            };
+            if lottery.prize_mint != Some(prize_mint.key()) {
+                return err!(LotteryError::WrongPrizeMint);
+            }
+            let lottery_id_bytes = lottery.id.to_le_bytes();
+            transfer_tokens(
+                prize_vault,
+                authority_prize_account,
+                prize_vault.amount,
+                prize_mint,
+                &lottery.to_account_info(),
+                token_program,
+                Some(&[
+                    LOTTERY_SEED,
+                    lottery_id_bytes.as_ref(),
+                    &[ctx.bumps.lottery_pda],
+                ]),
+            )?;
+            lottery.prize_deposited = false;
+        }
+
+        lottery.status = LotteryStatus::Cancelled;
+        msg!("Lottery ID: {} cancelled", lottery.id);
+        Ok(())
+    }
+
+    /// Refunds the price of a ticket of a cancelled lottery to its owner and closes the ticket.
+    /// Anyone can call it, the refund and the ticket's rent always go to the owner.
+    pub fn refund_ticket(ctx: Context<RefundTicket>) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery_pda;
+        lottery.check_refundable()?;
+        lottery.check_ticket_mint(None)?;
+        lottery.check_nft_tickets(false)?;
+
+        let refund = lottery.refund_sales(1)?;
+        withdraw_lamports(&lottery.to_account_info(), refund)?;
+        **ctx.accounts.owner.try_borrow_mut_lamports()? += refund;
+        msg!(
+            "Ticket {} of lottery ID: {} refunded",
+            ctx.accounts.ticket_pda.ticket_id,
+            lottery.id
+        );
+        Ok(())
+    }
+
+    /// `refund_ticket` for lotteries paid in their `ticket_mint`
+    pub fn refund_ticket_with_token(ctx: Context<RefundTicketWithToken>) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery_pda;
+        lottery.check_refundable()?;
+        lottery.check_ticket_mint(Some(ctx.accounts.ticket_mint.key()))?;
+
+        let refund = lottery.refund_sales(1)?;
+        let lottery_id_bytes = lottery.id.to_le_bytes();
+        transfer_tokens(
+            &ctx.accounts.prize_vault,
+            &ctx.accounts.owner_token_account,
+            refund,
+            &ctx.accounts.ticket_mint,
+            &lottery.to_account_info(),
+            &ctx.accounts.token_program,
+            Some(&[
+                LOTTERY_SEED,
+                lottery_id_bytes.as_ref(),
+                &[ctx.bumps.lottery_pda],
+            ]),
+        )?;
+        msg!(
+            "Ticket {} of lottery ID: {} refunded",
+            ctx.accounts.ticket_pda.ticket_id,
+            lottery.id
+        );
+        Ok(())
+    }
+
+    /// `refund_ticket` for NFT tickets: burns the NFT and refunds its holder. The ticket's rent
+    /// goes back to its buyer.
+    pub fn refund_nft_ticket(ctx: Context<RefundNftTicket>) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery_pda;
+        lottery.check_refundable()?;
+        lottery.check_nft_tickets(true)?;
+
+        burn(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Burn {
+                    mint: ctx.accounts.ticket_nft_mint.to_account_info(),
+                    from: ctx.accounts.holder_nft_account.to_account_info(),
+                    authority: ctx.accounts.holder.to_account_info(),
+                },
+            ),
+            1,
+        )?;
+        let refund = lottery.refund_sales(1)?;
+        withdraw_lamports(&lottery.to_account_info(), refund)?;
+        **ctx.accounts.holder.try_borrow_mut_lamports()? += refund;
+        msg!(
+            "NFT ticket {} of lottery ID: {} refunded",
+            ctx.accounts.ticket_pda.ticket_id,
+            lottery.id
+        );
+        Ok(())
+    }
+
+    /// Refunds all tickets of range `range_index` in the ticket ledger of a cancelled lottery
+    pub fn refund_ticket_range(ctx: Context<RefundTicketRange>, range_index: u32) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery_pda;
+        lottery.check_refundable()?;
+        lottery.check_ticket_mint(None)?;
+
+        let mut ledger_data = ctx.accounts.ticket_ledger.as_ref().try_borrow_mut_data()?;
+        let range = TicketLedger::ranges_mut(&mut ledger_data)?
+            .get_mut(range_index as usize)
+            .ok_or(LotteryError::InvalidTicketRange)?;
+        if range.owner != ctx.accounts.owner.key() {
+            return err!(LotteryError::UnauthorizedAction);
+        }
+        if range.count == 0 {
+            return err!(LotteryError::TicketAlreadyRefunded);
+        }
+
+        let refund = lottery.refund_sales(range.count)?;
+        // An emptied range marks the refund, no more tickets are sold or drawn once cancelled
+        range.count = 0;
+        withdraw_lamports(&lottery.to_account_info(), refund)?;
+        **ctx.accounts.owner.try_borrow_mut_lamports()? += refund;
+        msg!(
+            "Ticket range {} of lottery ID: {} refunded",
+            range_index,
+            lottery.id
+        );
+        Ok(())
+    }
+
+    /// Closes a ticket once it can no longer win or has been paid out, returning its rent
+    /// to the owner. Tickets of a cancelled lottery are closed by `refund_ticket` instead.
+    pub fn close_ticket(ctx: Context<CloseTicket>) -> Result<()> {
+        let ticket = &ctx.accounts.ticket_pda;
+        let lottery_info = ctx.accounts.lottery_pda.to_account_info();
+        // Lottery IDs are never reused, and `close_lottery` only runs once every prize is settled
+        if !lottery_info.data_is_empty() {
+            let lottery = LotteryPDA::try_deserialize(&mut &lottery_info.try_borrow_data()?[..])?;
+            match lottery.status {
+                LotteryStatus::Active => {
+                    if !lottery.is_drawn() {
+                        return err!(LotteryError::WinnerNotChosenYet);
+                    }
+                    let unclaimed_win = match lottery.game_mode {
+                        GameMode::TicketDraw => lottery.prize_tiers
+                            [..lottery.prize_tier_count as usize]
+                            .iter()
+                            .any(|tier| tier.winner_ticket_id == ticket.ticket_id && !tier.claimed),
+                        // A matching ticket can be registered until registration closes
+                        GameMode::Numbers => {
+                            lottery.match_tier(ticket).is_some()
+                                && !ticket.match_claimed
+                                && (ticket.match_registered
+                                    || Clock::get()?.unix_timestamp
+                                        < lottery.match_registration_end_ts())
+                        }
+                    };
+                    if unclaimed_win {
+                        return err!(LotteryError::PrizeNotClaimed);
+                    }
+                }
+                LotteryStatus::Cancelled => return err!(LotteryError::LotteryCancelled),
+                // Unclaimed prizes were rolled over, so no ticket can claim anymore
+                LotteryStatus::Expired => {}
+            }
+        }
+
+        msg!(
+            "Ticket {} of lottery ID: {} closed",
+            ticket.ticket_id,
+            ticket.lottery_id
+        );
+        Ok(())
+    }
+
+    /// Closes a settled lottery and its ticket ledger, returning their rent and any rounding
+    /// leftovers of the pot to the authority. A lottery is settled once every prize is
+    /// claimed, or once every ticket of a cancelled lottery is refunded.
+    pub fn close_lottery(ctx: Context<CloseLottery>) -> Result<()> {
+        let lottery = &ctx.accounts.lottery_pda;
+        if ctx.accounts.authority.key() != lottery.authority {
+            return err!(LotteryError::UnauthorizedAction);
+        }
+        let settled = match lottery.status {
+            // An unclaimed raffle prize goes back to the authority with the vault
+            LotteryStatus::Active if lottery.prize_mint.is_some() => {
+                lottery.is_drawn()
+                    && lottery.proceeds_withdrawn
+                    && (lottery.prize_tiers[0].claimed
+                        || Clock::get()?.unix_timestamp >= lottery.claim_deadline_ts)
+                    && lottery.referral_claimed_lamports == lottery.referral_lamports
+            }
+            // Numbers games settle through `rollover_unclaimed`, tiers may have no winner
+            LotteryStatus::Active => {
+                lottery.game_mode == GameMode::TicketDraw
+                    && lottery.is_drawn()
+                    && lottery.prize_tiers[..lottery.prize_tier_count as usize]
+                        .iter()
+                        .all(|tier| tier.claimed)
+                    && lottery.referral_claimed_lamports == lottery.referral_lamports
+                    && lottery.carry_over_settled()?
+            }
+            // Referral shares are refunded with the tickets
+            LotteryStatus::Cancelled => lottery.tickets_refunded == lottery.last_ticket_id,
+            LotteryStatus::Expired => {
+                lottery.referral_claimed_lamports == lottery.referral_lamports
+                    && lottery.carry_over_settled()?
+            }
+        };
+        if !settled {
+            return err!(LotteryError::LotteryNotSettled);
+        }
+
+        // A rolled-over jackpot of a cancelled SOL lottery goes back to the master jackpot
+        if lottery.status == LotteryStatus::Cancelled
+            && lottery.ticket_mint.is_none()
+            && lottery.jackpot_lamports > 0
+        {
+            let jackpot = &mut ctx.accounts.jackpot_pda;
+            jackpot.version = JackpotPDA::VERSION;
+            **jackpot.to_account_info().try_borrow_mut_lamports()? += lottery.jackpot_lamports;
+            withdraw_lamports(&lottery.to_account_info(), lottery.jackpot_lamports)?;
+            jackpot.balance_lamports += lottery.jackpot_lamports;
+        }
+
+        if let Some(vault_mint) = lottery.ticket_mint.or(lottery.prize_mint) {
+            let (Some(mint), Some(prize_vault), Some(authority_token_account), Some(token_program)) = (
+                &ctx.accounts.ticket_mint,
+                &ctx.accounts.prize_vault,
+                &ctx.accounts.authority_token_account,
+                &ctx.accounts.token_program,
+            ) else {
+                return err!(LotteryError::WrongTicketMint);
+            };
+            if mint.key() != vault_mint {
+                return err!(LotteryError::WrongTicketMint);
+            }
+
+            let lottery_id_bytes = lottery.id.to_le_bytes();
+            let lottery_signer_seeds: &[&[u8]] = &[
+                LOTTERY_SEED,
+                lottery_id_bytes.as_ref(),
+                &[ctx.bumps.lottery_pda],
+            ];
+            if prize_vault.amount > 0 {
+                transfer_tokens(
+                    prize_vault,
+                    authority_token_account,
+                    prize_vault.amount,
+                    mint,
+                    &lottery.to_account_info(),
+                    token_program,
+                    Some(lottery_signer_seeds),
+                )?;
+            }
+            close_account(CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                CloseAccount {
+                    account: prize_vault.to_account_info(),
+                    destination: ctx.accounts.authority.to_account_info(),
+                    authority: lottery.to_account_info(),
+                },
+                &[lottery_signer_seeds],
+            ))?;
+        }
+
+        let ledger_info = ctx.accounts.ticket_ledger.to_account_info();
+        if !ledger_info.data_is_empty() {
+            require!(
+                ledger_info
+                    .try_borrow_data()?
+                    .starts_with(TicketLedger::DISCRIMINATOR),
+                ErrorCode::AccountDiscriminatorMismatch
+            );
+            close_program_account(&ledger_info, &ctx.accounts.authority.to_account_info())?;
+        }
+
+        msg!("Lottery ID: {} closed", lottery.id);
+        Ok(())
+    }
+
+    /// Pays the winner of prize tier `tier` (0 is the first prize) their share of the pot
+    pub fn claim_prize(ctx: Context<ClaimPrize>, tier: u8) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery_pda;
+        let winner = &ctx.accounts.winner;
+        lottery.check_ticket_mint(None)?;
+        lottery.check_nft_tickets(false)?;
+        lottery.check_raffle(false)?;
+
+        let ledger_data = ctx
+            .accounts
+            .ticket_ledger
+            .as_ref()
+            .map(|ledger| ledger.as_ref().try_borrow_data())
+            .transpose()?;
+        let ticket_ledger = ledger_data
+            .as_ref()
+            .map(|data| TicketLedger::ranges(data))
+            .transpose()?;
+        let prize_tier = lottery.check_claim(
+            tier,
+            ctx.accounts.winner_ticket.as_deref(),
+            ticket_ledger,
+            &winner.key(),
+            Clock::get()?.unix_timestamp,
+        )?;
+
+        // Transfer the tier's share of the pot (minus the protocol fee) to the winner
+        let tier_total_balance = lottery.tier_prize_lamports(&prize_tier);
+        let fee = lottery.fee_for(tier_total_balance);
+        let prize = tier_total_balance - fee;
+        lottery.pay_prize(tier_total_balance)?;
+        withdraw_lamports(&lottery.to_account_info(), tier_total_balance)?;
+        **winner.to_account_info().try_borrow_mut_lamports()? += prize;
+        **ctx
+            .accounts
+            .treasury_pda
+            .to_account_info()
+            .try_borrow_mut_lamports()? += fee;
+        ctx.accounts.treasury_pda.collected_fees_lamports += fee;
+        ctx.accounts.player_stats.record_win(winner.key(), prize);
+
+        lottery.prize_tiers[tier as usize].claimed = true;
+        msg!("Tier {} claimed for lottery ID: {}", tier, lottery.id);
+        emit!(PrizeClaimed {
+            lottery_id: lottery.id,
+            tier,
+            ticket_id: prize_tier.winner_ticket_id,
+            winner: winner.key(),
+            prize_amount: prize,
+            fee_amount: fee,
+            ticket_mint: None,
+        });
+        Ok(())
+    }
+
+    /// `claim_prize` for lotteries paid in their `ticket_mint`, the fee goes to the treasury's
+    /// token account for that mint
+    pub fn claim_prize_with_token(ctx: Context<ClaimPrizeWithToken>, tier: u8) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery_pda;
+        lottery.check_ticket_mint(Some(ctx.accounts.ticket_mint.key()))?;
+        // Token lotteries have no bulk purchases, so every ticket is its own TicketPDA
+        let prize_tier = lottery.check_claim(
+            tier,
+            Some(&ctx.accounts.winner_ticket),
+            None,
+            &ctx.accounts.winner.key(),
+            Clock::get()?.unix_timestamp,
+        )?;
+
+        let tier_total_balance = lottery.tier_prize_lamports(&prize_tier);
+        let fee = lottery.fee_for(tier_total_balance);
+        let prize = tier_total_balance - fee;
+        lottery.pay_prize(tier_total_balance)?;
+        let lottery_id_bytes = lottery.id.to_le_bytes();
+        let lottery_signer_seeds: &[&[u8]] = &[
+            LOTTERY_SEED,
+            lottery_id_bytes.as_ref(),
+            &[ctx.bumps.lottery_pda],
+        ];
+        transfer_tokens(
+            &ctx.accounts.prize_vault,
+            &ctx.accounts.winner_token_account,
+            prize,
+            &ctx.accounts.ticket_mint,
+            &lottery.to_account_info(),
+            &ctx.accounts.token_program,
+            Some(lottery_signer_seeds),
+        )?;
+        if fee > 0 {
+            transfer_tokens(
+                &ctx.accounts.prize_vault,
+                &ctx.accounts.treasury_token_account,
+                fee,
+                &ctx.accounts.ticket_mint,
+                &lottery.to_account_info(),
+                &ctx.accounts.token_program,
+                Some(lottery_signer_seeds),
+            )?;
+        }
+        ctx.accounts
+            .player_stats
+            .record_win(ctx.accounts.winner.key(), 0);
+
+        lottery.prize_tiers[tier as usize].claimed = true;
+        msg!("Tier {} claimed for lottery ID: {}", tier, lottery.id);
+        emit!(PrizeClaimed {
+            lottery_id: lottery.id,
+            tier,
+            ticket_id: prize_tier.winner_ticket_id,
+            winner: ctx.accounts.winner.key(),
+            prize_amount: prize,
+            fee_amount: fee,
+            ticket_mint: lottery.ticket_mint,
+        });
+        Ok(())
+    }
+
+    /// `claim_prize` for NFT tickets: the holder of the winning ticket's NFT claims, and the NFT
+    /// is burned
+    pub fn claim_prize_nft(ctx: Context<ClaimPrizeNft>, tier: u8) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery_pda;
+        let winner = &ctx.accounts.winner;
+        lottery.check_nft_tickets(true)?;
+        let prize_tier = lottery.check_claimable(tier, Clock::get()?.unix_timestamp)?;
+
+        // Fails unless the winner holds the NFT
+        burn(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Burn {
+                    mint: ctx.accounts.ticket_nft_mint.to_account_info(),
+                    from: ctx.accounts.winner_nft_account.to_account_info(),
+                    authority: winner.to_account_info(),
+                },
+            ),
+            1,
+        )?;
+
+        let tier_total_balance = lottery.tier_prize_lamports(&prize_tier);
+        let fee = lottery.fee_for(tier_total_balance);
+        let prize = tier_total_balance - fee;
+        lottery.pay_prize(tier_total_balance)?;
+        withdraw_lamports(&lottery.to_account_info(), tier_total_balance)?;
+        **winner.to_account_info().try_borrow_mut_lamports()? += prize;
+        **ctx
+            .accounts
+            .treasury_pda
+            .to_account_info()
+            .try_borrow_mut_lamports()? += fee;
+        ctx.accounts.treasury_pda.collected_fees_lamports += fee;
+        ctx.accounts.player_stats.record_win(winner.key(), prize);
+
+        lottery.prize_tiers[tier as usize].claimed = true;
+        msg!("Tier {} claimed for lottery ID: {}", tier, lottery.id);
+        emit!(PrizeClaimed {
+            lottery_id: lottery.id,
+            tier,
+            ticket_id: prize_tier.winner_ticket_id,
+            winner: winner.key(),
+            prize_amount: prize,
+            fee_amount: fee,
+            ticket_mint: None,
+        });
+        Ok(())
+    }
+
+    /// Opens the caller's referral account for a lottery with referrals, to pass to ticket purchases
+    pub fn init_referral(ctx: Context<InitReferral>) -> Result<()> {
+        let lottery = &ctx.accounts.lottery_pda;
+        lottery.check_active()?;
+        if lottery.referral_bps == 0 {
+            return err!(LotteryError::ReferralsDisabled);
+        }
+
+        let referral = &mut ctx.accounts.referral_pda;
+        referral.lottery_id = lottery.id;
+        referral.referrer = ctx.accounts.referrer.key();
+        referral.accrued_lamports = 0;
+        referral.version = ReferralPDA::VERSION;
+        msg!(
+            "Referral account opened for {} in lottery ID: {}",
+            referral.referrer,
+            lottery.id
+        );
+        Ok(())
+    }
+
+    /// Pays the referrer its accrued shares once the winners are drawn and closes its referral
+    /// account. The shares of a cancelled lottery are refunded to the buyers instead, so the
+    /// referrer only gets the account's rent back.
+    pub fn claim_referral_rewards(ctx: Context<ClaimReferralRewards>) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery_pda;
+        let referral = &ctx.accounts.referral_pda;
+        if lottery.status == LotteryStatus::Cancelled {
+            msg!(
+                "Lottery ID: {} was cancelled, no referral rewards",
+                lottery.id
+            );
+            return Ok(());
+        }
+        if !lottery.is_drawn() {
+            return err!(LotteryError::WinnerNotChosenYet);
+        }
+
+        **ctx.accounts.referrer.try_borrow_mut_lamports()? += referral.accrued_lamports;
+        withdraw_lamports(&lottery.to_account_info(), referral.accrued_lamports)?;
+        lottery.referral_claimed_lamports += referral.accrued_lamports;
+        msg!(
+            "Referrer {} claimed {} lamports from lottery ID: {}",
+            referral.referrer,
+            referral.accrued_lamports,
+            lottery.id
+        );
+        Ok(())
+    }
+
+    /// `claim_prize` for asset raffles: pays the winner the contents of the prize vault
+    pub fn claim_raffle_prize(ctx: Context<ClaimRafflePrize>) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery_pda;
+        let winner = &ctx.accounts.winner;
+        lottery.check_raffle(true)?;
+        if lottery.prize_mint != Some(ctx.accounts.prize_mint.key()) {
+            return err!(LotteryError::WrongPrizeMint);
+        }
+
+        let ledger_data = ctx
+            .accounts
+            .ticket_ledger
+            .as_ref()
+            .map(|ledger| ledger.as_ref().try_borrow_data())
+            .transpose()?;
+        let ticket_ledger = ledger_data
+            .as_ref()
+            .map(|data| TicketLedger::ranges(data))
+            .transpose()?;
+        let prize_tier = lottery.check_claim(
+            0,
+            ctx.accounts.winner_ticket.as_deref(),
+            ticket_ledger,
+            &winner.key(),
+            Clock::get()?.unix_timestamp,
+        )?;
+
+        let prize = ctx.accounts.prize_vault.amount;
+        let lottery_id_bytes = lottery.id.to_le_bytes();
+        transfer_tokens(
+            &ctx.accounts.prize_vault,
+            &ctx.accounts.winner_prize_account,
+            prize,
+            &ctx.accounts.prize_mint,
+            &lottery.to_account_info(),
+            &ctx.accounts.token_program,
+            Some(&[
+                LOTTERY_SEED,
+                lottery_id_bytes.as_ref(),
+                &[ctx.bumps.lottery_pda],
+            ]),
+        )?;
+        ctx.accounts.player_stats.record_win(winner.key(), 0);
+
+        lottery.prize_tiers[0].claimed = true;
+        msg!("Raffle prize of lottery ID: {} claimed", lottery.id);
+        emit!(PrizeClaimed {
+            lottery_id: lottery.id,
+            tier: 0,
+            ticket_id: prize_tier.winner_ticket_id,
+            winner: winner.key(),
+            prize_amount: prize,
+            fee_amount: 0,
+            ticket_mint: lottery.prize_mint,
+        });
+        Ok(())
+    }
+
+    /// Pays the ticket proceeds of an asset raffle, less the protocol fee, to the authority once
+    /// the winner is drawn
+    pub fn withdraw_proceeds(ctx: Context<WithdrawProceeds>) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery_pda;
+        if ctx.accounts.authority.key() != lottery.authority {
+            return err!(LotteryError::UnauthorizedAction);
+        }
+        lottery.check_active()?;
+        lottery.check_raffle(true)?;
+        if !lottery.is_drawn() {
+            return err!(LotteryError::WinnerNotChosenYet);
+        }
+        if lottery.proceeds_withdrawn {
+            return err!(LotteryError::ProceedsAlreadyWithdrawn);
+        }
+
+        let proceeds = lottery.prize_pool_lamports;
+        let fee = lottery.fee_for(proceeds);
+        lottery.pay_prize(proceeds)?;
+        withdraw_lamports(&lottery.to_account_info(), proceeds)?;
+        **ctx.accounts.authority.try_borrow_mut_lamports()? += proceeds - fee;
+        **ctx
+            .accounts
+            .treasury_pda
+            .to_account_info()
+            .try_borrow_mut_lamports()? += fee;
+        ctx.accounts.treasury_pda.collected_fees_lamports += fee;
+        lottery.proceeds_withdrawn = true;
+        msg!(
+            "Proceeds of {} lamports withdrawn from lottery ID: {}",
+            proceeds - fee,
+            lottery.id
+        );
+        Ok(())
+    }
+
+    /// Registers a ticket of a numbers game that matches enough of the winning numbers for a
+    /// prize tier. Each tier's share of the pot is split between the tickets registered for it
+    /// within `MATCH_REGISTRATION_SECS` of the draw. Permissionless.
+    pub fn register_match(ctx: Context<RegisterMatch>) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery_pda;
+        let ticket = &mut ctx.accounts.ticket_pda;
+        lottery.check_active()?;
+        lottery.check_game_mode(GameMode::Numbers)?;
+        if !lottery.is_drawn() {
+            return err!(LotteryError::WinnerNotChosenYet);
+        }
+        if Clock::get()?.unix_timestamp >= lottery.match_registration_end_ts() {
+            return err!(LotteryError::MatchRegistrationClosed);
+        }
+        if ticket.match_registered {
+            return err!(LotteryError::MatchAlreadyRegistered);
+        }
+        let Some(tier) = lottery.match_tier(ticket) else {
+            return err!(LotteryError::NoMatchPrize);
+        };
+
+        lottery.match_winner_counts[tier as usize] += 1;
+        ticket.match_registered = true;
+        msg!(
+            "Ticket {} of lottery ID: {} registered for tier {}",
+            ticket.ticket_id,
+            lottery.id,
+            tier
+        );
+        Ok(())
+    }
+
+    /// Pays the owner of a registered ticket its share of its tier, once registration closed
+    pub fn claim_match_prize(ctx: Context<ClaimMatchPrize>) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery_pda;
+        let ticket = &mut ctx.accounts.ticket_pda;
+        lottery.check_active()?;
+        lottery.check_game_mode(GameMode::Numbers)?;
+        let now = Clock::get()?.unix_timestamp;
+        if now < lottery.match_registration_end_ts() {
+            return err!(LotteryError::MatchRegistrationOpen);
+        }
+        if now >= lottery.claim_deadline_ts {
+            return err!(LotteryError::ClaimDeadlinePassed);
+        }
+        if !ticket.match_registered {
+            return err!(LotteryError::MatchNotRegistered);
+        }
+        if ticket.match_claimed {
+            return err!(LotteryError::TicketAlreadyClaimed);
+        }
+        let Some(tier) = lottery.match_tier(ticket) else {
+            return err!(LotteryError::NoMatchPrize);
+        };
+
+        let tier_total_balance = lottery.match_prize_lamports(tier);
+        let fee = lottery.fee_for(tier_total_balance);
+        let prize = tier_total_balance - fee;
+        lottery.pay_prize(tier_total_balance)?;
+        withdraw_lamports(&lottery.to_account_info(), tier_total_balance)?;
+        **ctx.accounts.owner.try_borrow_mut_lamports()? += prize;
+        **ctx
+            .accounts
+            .treasury_pda
+            .to_account_info()
+            .try_borrow_mut_lamports()? += fee;
+        ctx.accounts.treasury_pda.collected_fees_lamports += fee;
+        ctx.accounts
+            .player_stats
+            .record_win(ctx.accounts.owner.key(), prize);
+
+        ticket.match_claimed = true;
+        msg!(
+            "Ticket {} of lottery ID: {} claimed tier {}",
+            ticket.ticket_id,
+            lottery.id,
+            tier
+        );
+        emit!(PrizeClaimed {
+            lottery_id: lottery.id,
+            tier,
+            ticket_id: ticket.ticket_id,
+            winner: ctx.accounts.owner.key(),
+            prize_amount: prize,
+            fee_amount: fee,
+            ticket_mint: None,
+        });
+        Ok(())
+    }
+
+    /// Moves the prizes still unclaimed at `claim_deadline_ts` into `next_lottery`'s pot, or into
+    /// the master jackpot when no next lottery is given, and marks the lottery expired.
+    /// In numbers games this includes the share of every tier nobody matched, the jackpot tier
+    /// first among them.
+    /// Permissionless. Token lotteries can only roll over into a later lottery with the same mint.
+    pub fn rollover_unclaimed(ctx: Context<RolloverUnclaimed>) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery_pda;
+        lottery.check_active()?;
+        // Raffle proceeds go to the authority, and an unclaimed prize back to it on close
+        lottery.check_raffle(false)?;
+        if !lottery.is_drawn() {
+            return err!(LotteryError::WinnerNotChosenYet);
+        }
+        if Clock::get()?.unix_timestamp < lottery.claim_deadline_ts {
+            return err!(LotteryError::ClaimDeadlineNotPassed);
+        }
+
+        let unclaimed = lottery.prize_pool_lamports - lottery.prizes_paid_lamports;
+        match ctx.accounts.next_lottery.as_mut() {
+            Some(next_lottery) => {
+                if next_lottery.id <= lottery.id
+                    || next_lottery.authority != lottery.authority
+                    || next_lottery.ticket_mint != lottery.ticket_mint
+                    || next_lottery.prize_mint.is_some()
+                    || next_lottery.status != LotteryStatus::Active
+                    || next_lottery.sales_closed_slot.is_some()
+                {
+                    return err!(LotteryError::InvalidRolloverTarget);
+                }
+
+                if lottery.ticket_mint.is_some() {
+                    let (
+                        Some(mint),
+                        Some(prize_vault),
+                        Some(next_prize_vault),
+                        Some(token_program),
+                    ) = (
+                        &ctx.accounts.ticket_mint,
+                        &ctx.accounts.prize_vault,
+                        &ctx.accounts.next_prize_vault,
+                        &ctx.accounts.token_program,
+                    )
+                    else {
+                        return err!(LotteryError::WrongTicketMint);
+                    };
+                    lottery.check_ticket_mint(Some(mint.key()))?;
+                    let lottery_id_bytes = lottery.id.to_le_bytes();
+                    transfer_tokens(
+                        prize_vault,
+                        next_prize_vault,
+                        unclaimed,
+                        mint,
+                        &lottery.to_account_info(),
+                        token_program,
+                        Some(&[
+                            LOTTERY_SEED,
+                            lottery_id_bytes.as_ref(),
+                            &[ctx.bumps.lottery_pda],
+                        ]),
+                    )?;
+                } else {
+                    **next_lottery.to_account_info().try_borrow_mut_lamports()? += unclaimed;
+                    withdraw_lamports(&lottery.to_account_info(), unclaimed)?;
+                }
+                next_lottery.jackpot_lamports += unclaimed;
+                msg!(
+                    "Rolled {} unclaimed over from lottery ID: {} to lottery ID: {}",
+                    unclaimed,
+                    lottery.id,
+                    next_lottery.id
+                );
+            }
+            None => {
+                if lottery.ticket_mint.is_some() {
+                    return err!(LotteryError::InvalidRolloverTarget);
+                }
+                let jackpot = &mut ctx.accounts.jackpot_pda;
+                jackpot.version = JackpotPDA::VERSION;
+                **jackpot.to_account_info().try_borrow_mut_lamports()? += unclaimed;
+                withdraw_lamports(&lottery.to_account_info(), unclaimed)?;
+                jackpot.balance_lamports += unclaimed;
+                msg!(
+                    "Rolled {} unclaimed lamports over from lottery ID: {} to the jackpot",
+                    unclaimed,
+                    lottery.id
+                );
+            }
+        }
+
+        lottery.status = LotteryStatus::Expired;
+        Ok(())
+    }
+
+    /// Creates a recurring lottery whose rounds `advance_series` creates from `params`.
+    /// Rounds are created permissionlessly, so the series pays each round's rent back to its
+    /// creator: the authority keeps the series funded by transferring SOL to it.
+    pub fn create_series(
+        ctx: Context<CreateSeries>,
+        series_id: u32,
+        params: SeriesParams,
+    ) -> Result<()> {
+        if params.sale_duration_secs <= 0 || params.claim_period_secs <= 0 {
+            return err!(LotteryError::InvalidSchedule);
+        }
+        if params.carry_over_bps > MAX_CARRY_OVER_BPS {
+            return err!(LotteryError::InvalidCarryOver);
+        }
+        if params.prize_shares_bps.len() > MAX_PRIZE_TIERS {
+            return err!(LotteryError::InvalidPrizeTiers);
+        }
+        if params.max_fee_bps > MAX_FEE_BPS {
+            return err!(LotteryError::FeeTooHigh);
+        }
+
+        let series = &mut ctx.accounts.series;
+        series.authority = ctx.accounts.authority.key();
+        series.series_id = series_id;
+        series.ticket_price_lamports = params.ticket_price_lamports;
+        series.sale_duration_secs = params.sale_duration_secs;
+        series.claim_period_secs = params.claim_period_secs;
+        series.prize_shares_bps = [0; MAX_PRIZE_TIERS];
+        series.prize_shares_bps[..params.prize_shares_bps.len()]
+            .copy_from_slice(&params.prize_shares_bps);
+        series.prize_tier_count = params.prize_shares_bps.len() as u8;
+        series.min_tickets = params.min_tickets;
+        series.max_tickets = params.max_tickets;
+        series.max_tickets_per_wallet = params.max_tickets_per_wallet;
+        series.referral_bps = params.referral_bps;
+        series.carry_over_bps = params.carry_over_bps;
+        series.crank_bounty_lamports = params.crank_bounty_lamports;
+        series.max_fee_bps = params.max_fee_bps;
+        series.current_lottery_id = 0;
+        series.version = LotterySeries::VERSION;
+        // Checks the rest of the template the way `create_lottery` would
+        series
+            .round_params(Clock::get()?.unix_timestamp)
+            .validate()?;
+        msg!("Series {} created by {}", series_id, series.authority);
+        Ok(())
+    }
+
+    /// Creates the next round of a series once its current round is drawn or cancelled.
+    /// Permissionless. The next round takes `carry_over_bps` of the current round's pot, and its
+    /// sales open once the authority commits its randomness with `commit_randomness`, as for
+    /// any lottery. No round opens while the protocol fee is above the series' `max_fee_bps`.
+    pub fn advance_series(ctx: Context<AdvanceSeries>) -> Result<()> {
+        let series = &mut ctx.accounts.series;
+        let master = &mut ctx.accounts.master_pda;
+        master.check_not_paused()?;
+        if master.fee_bps > series.max_fee_bps {
+            return err!(LotteryError::SeriesFeeTooHigh);
+        }
+
+        let mut carry_over = 0;
+        if series.current_lottery_id != 0 {
+            let Some(current) = ctx.accounts.current_lottery.as_mut() else {
+                return err!(LotteryError::SeriesRoundMissing);
+            };
+            if current.status != LotteryStatus::Cancelled {
+                if !current.is_drawn() {
+                    return err!(LotteryError::WinnerNotChosenYet);
+                }
+                carry_over = current.carry_over_lamports()?;
+                current.carried_over = true;
+            }
+        }
+
+        let next = &mut ctx.accounts.next_lottery;
+        next.init(
+            master,
+            series.authority,
+            &series.round_params(Clock::get()?.unix_timestamp),
+        )?;
+        next.carry_over_bps = series.carry_over_bps;
+
+        // Pay the new round's rent back to the caller, from what the series holds above its own rent
+        let next_rent = next.to_account_info().lamports();
+        let series_info = series.to_account_info();
+        let series_rent = Rent::get()?.minimum_balance(series_info.data_len());
+        if series_info.lamports().saturating_sub(series_rent) < next_rent {
+            return err!(LotteryError::SeriesUnderfunded);
+        }
+        **series_info.try_borrow_mut_lamports()? -= next_rent;
+        **ctx.accounts.caller.try_borrow_mut_lamports()? += next_rent;
+
+        if let Some(current) = ctx.accounts.current_lottery.as_ref() {
+            if carry_over > 0 {
+                withdraw_lamports(&current.to_account_info(), carry_over)?;
+                **next.to_account_info().try_borrow_mut_lamports()? += carry_over;
+                next.jackpot_lamports = carry_over;
+            }
+        }
+        series.current_lottery_id = next.id;
+        msg!(
+            "Series {} advanced to lottery ID: {}, carrying over {} lamports",
+            series.series_id,
+            next.id,
+            carry_over
+        );
+        emit!(next.created_event(next.key()));
+        Ok(())
+    }
+
+    /// Ends a series and returns its balance to the authority. The share the current round holds
+    /// back for a next round goes to the master jackpot instead.
+    pub fn close_series(ctx: Context<CloseSeries>) -> Result<()> {
+        let series = &ctx.accounts.series;
+        if let Some(current) = ctx.accounts.current_lottery.as_mut() {
+            if current.status != LotteryStatus::Cancelled && !current.carried_over {
+                // Before the draw the pot can still grow, so the share isn't known yet
+                if !current.is_drawn() {
+                    return err!(LotteryError::WinnerNotChosenYet);
+                }
+                let carry_over = current.carry_over_lamports()?;
+                let jackpot = &mut ctx.accounts.jackpot_pda;
+                jackpot.version = JackpotPDA::VERSION;
+                withdraw_lamports(&current.to_account_info(), carry_over)?;
+                **jackpot.to_account_info().try_borrow_mut_lamports()? += carry_over;
+                jackpot.balance_lamports += carry_over;
+                current.carried_over = true;
+            }
+        } else if series.current_lottery_id != 0 {
+            return err!(LotteryError::SeriesRoundMissing);
+        }
+        msg!("Series {} closed", series.series_id);
+        Ok(())
+    }
+
+    /// Adds `amount` lamports from the master jackpot to the pot of a SOL lottery that is still selling
+    pub fn seed_from_jackpot(ctx: Context<SeedFromJackpot>, amount: u64) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery_pda;
+        let jackpot = &mut ctx.accounts.jackpot_pda;
+        if ctx.accounts.admin.key() != ctx.accounts.master_pda.admin {
+            return err!(LotteryError::UnauthorizedAction);
+        }
+        lottery.check_active()?;
+        lottery.check_ticket_mint(None)?;
+        lottery.check_raffle(false)?;
+        if lottery.sales_closed_slot.is_some() {
+            return err!(LotteryError::SalesClosed);
+        }
+        if jackpot.balance_lamports < amount {
+            return err!(LotteryError::InsufficientJackpot);
+        }
+
+        **lottery.to_account_info().try_borrow_mut_lamports()? += amount;
+        **jackpot.to_account_info().try_borrow_mut_lamports()? -= amount;
+        jackpot.balance_lamports -= amount;
+        lottery.jackpot_lamports += amount;
+        msg!(
+            "Seeded lottery ID: {} with {} lamports from the jackpot",
+            lottery.id,
+            amount
+        );
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct InitMaster<'info> {
+    #[account(init, payer = payer, space=8+MasterPDA::INIT_SPACE, seeds = [MASTER_PDA_SEED], bump)]
+    pub master_pda: Account<'info, MasterPDA>,
+    #[account(init, payer = payer, space=8+TreasuryPDA::INIT_SPACE, seeds = [TREASURY_SEED], bump)]
+    pub treasury_pda: Account<'info, TreasuryPDA>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>, // to create accounts
+}
+
+#[derive(Accounts)]
+pub struct MigrateMaster<'info> {
+    /// CHECK: still in the old layout, so it is resized and decoded by hand in `migrate_master`
+    #[account(mut, seeds = [MASTER_PDA_SEED], bump, owner = crate::ID)]
+    pub master_pda: UncheckedAccount<'info>,
+    #[account(init, payer = admin, space=8+TreasuryPDA::INIT_SPACE, seeds = [TREASURY_SEED], bump)]
+    pub treasury_pda: Account<'info, TreasuryPDA>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, crate::program::Lottery>,
+    #[account(constraint = program_data.upgrade_authority_address == Some(admin.key()) @ LotteryError::UnauthorizedAction)]
+    pub program_data: Account<'info, ProgramData>,
+    pub system_program: Program<'info, System>, // to pay for the extra space
+}
+
+#[derive(Accounts)]
+pub struct MigrateAccount<'info> {
+    /// Any account owned by this program, its type is told by its discriminator
+    #[account(mut, owner = crate::ID)]
+    pub account: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetPaused<'info> {
+    #[account(mut, seeds = [MASTER_PDA_SEED], bump)]
+    pub master_pda: Account<'info, MasterPDA>,
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetFee<'info> {
+    #[account(mut, seeds = [MASTER_PDA_SEED], bump)]
+    pub master_pda: Account<'info, MasterPDA>,
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFees<'info> {
+    #[account(seeds = [MASTER_PDA_SEED], bump)]
+    pub master_pda: Account<'info, MasterPDA>,
+    #[account(mut, seeds = [TREASURY_SEED], bump)]
+    pub treasury_pda: Account<'info, TreasuryPDA>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawTokenFees<'info> {
+    #[account(seeds = [MASTER_PDA_SEED], bump)]
+    pub master_pda: Account<'info, MasterPDA>,
+    #[account(seeds = [TREASURY_SEED], bump)]
+    pub treasury_pda: Account<'info, TreasuryPDA>,
+    #[account(mint::token_program = token_program)]
+    pub fee_mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, associated_token::mint = fee_mint, associated_token::authority = treasury_pda, associated_token::token_program = token_program)]
+    pub treasury_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, token::mint = fee_mint, token::token_program = token_program)]
+    pub admin_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub admin: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct CreateLottery<'info> {
+    #[account(init, payer = authority, space=8+LotteryPDA::INIT_SPACE, seeds = [LOTTERY_SEED, &(master_pda.last_lottery_id+1).to_le_bytes()], bump)]
+    pub lottery_pda: Account<'info, LotteryPDA>,
+    #[account(mut,seeds=[MASTER_PDA_SEED], bump)]
+    pub master_pda: Account<'info, MasterPDA>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>, // to create accounts
+}
+
+#[derive(Accounts)]
+pub struct BuyTicket<'info> {
+    #[account(init, payer = buyer, space=8+TicketPDA::INIT_SPACE, seeds = [TICKET_SEED, &lottery_pda.key().to_bytes(), &lottery_pda.next_ticket_id()?.to_le_bytes()], bump)]
+    pub ticket_pda: Account<'info, TicketPDA>,
+    #[account(mut, seeds = [LOTTERY_SEED, &lottery_pda.id.to_le_bytes()], bump)]
+    pub lottery_pda: Account<'info, LotteryPDA>,
+    #[account(init_if_needed, payer = buyer, space=8+WalletTicketsPDA::INIT_SPACE, seeds = [WALLET_TICKETS_SEED, &lottery_pda.key().to_bytes(), &buyer.key().to_bytes()], bump)]
+    pub wallet_tickets: Account<'info, WalletTicketsPDA>,
+    /// The referrer's account for this lottery, when the buyer was referred
+    #[account(mut, seeds = [REFERRAL_SEED, &lottery_pda.key().to_bytes(), &referral_pda.referrer.to_bytes()], bump)]
+    pub referral_pda: Option<Account<'info, ReferralPDA>>,
+    #[account(init_if_needed, payer = buyer, space=8+PlayerStats::INIT_SPACE, seeds = [PLAYER_STATS_SEED, &buyer.key().to_bytes()], bump)]
+    pub player_stats: Account<'info, PlayerStats>,
+    #[account(seeds = [MASTER_PDA_SEED], bump)]
+    pub master_pda: Account<'info, MasterPDA>,
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+    pub system_program: Program<'info, System>, // to create accounts
+}
+
+#[derive(Accounts)]
+pub struct ClosePlayerStats<'info> {
+    #[account(mut, close = player, seeds = [PLAYER_STATS_SEED, &player.key().to_bytes()], bump, has_one = player)]
+    pub player_stats: Account<'info, PlayerStats>,
+    #[account(mut)]
+    pub player: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetTicketMint<'info> {
+    #[account(mut, seeds = [LOTTERY_SEED, &lottery_pda.id.to_le_bytes()], bump)]
+    pub lottery_pda: Account<'info, LotteryPDA>,
+    #[account(mint::token_program = token_program)]
+    pub ticket_mint: InterfaceAccount<'info, Mint>,
+    /// Holds the ticket sales until they are paid out as prizes. Anyone can open an ATA, so it
+    /// may exist already
+    #[account(init_if_needed, payer = authority, associated_token::mint = ticket_mint, associated_token::authority = lottery_pda, associated_token::token_program = token_program)]
+    pub prize_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>, // to create the vault
+}
+
+#[derive(Accounts)]
+pub struct DepositPrize<'info> {
+    #[account(mut, seeds = [LOTTERY_SEED, &lottery_pda.id.to_le_bytes()], bump)]
+    pub lottery_pda: Account<'info, LotteryPDA>,
+    #[account(mint::token_program = token_program)]
+    pub prize_mint: InterfaceAccount<'info, Mint>,
+    /// Holds the prize until the winner claims it. Anyone can open an ATA, so it may exist already
+    #[account(init_if_needed, payer = authority, associated_token::mint = prize_mint, associated_token::authority = lottery_pda, associated_token::token_program = token_program)]
+    pub prize_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, token::mint = prize_mint, token::authority = authority, token::token_program = token_program)]
+    pub authority_prize_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>, // to create the vault
+}
+
+#[derive(Accounts)]
+pub struct BuyTicketWithToken<'info> {
+    #[account(init, payer = buyer, space=8+TicketPDA::INIT_SPACE, seeds = [TICKET_SEED, &lottery_pda.key().to_bytes(), &lottery_pda.next_ticket_id()?.to_le_bytes()], bump)]
+    pub ticket_pda: Account<'info, TicketPDA>,
+    #[account(mut, seeds = [LOTTERY_SEED, &lottery_pda.id.to_le_bytes()], bump)]
+    pub lottery_pda: Account<'info, LotteryPDA>,
+    #[account(init_if_needed, payer = buyer, space=8+WalletTicketsPDA::INIT_SPACE, seeds = [WALLET_TICKETS_SEED, &lottery_pda.key().to_bytes(), &buyer.key().to_bytes()], bump)]
+    pub wallet_tickets: Account<'info, WalletTicketsPDA>,
+    #[account(mint::token_program = token_program)]
+    pub ticket_mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, associated_token::mint = ticket_mint, associated_token::authority = lottery_pda, associated_token::token_program = token_program)]
+    pub prize_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, token::mint = ticket_mint, token::authority = buyer, token::token_program = token_program)]
+    pub buyer_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(init_if_needed, payer = buyer, space=8+PlayerStats::INIT_SPACE, seeds = [PLAYER_STATS_SEED, &buyer.key().to_bytes()], bump)]
+    pub player_stats: Account<'info, PlayerStats>,
+    #[account(seeds = [MASTER_PDA_SEED], bump)]
+    pub master_pda: Account<'info, MasterPDA>,
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>, // to create accounts
+}
+
+#[derive(Accounts)]
+pub struct BuyNftTicket<'info> {
+    #[account(init, payer = buyer, space=8+TicketPDA::INIT_SPACE, seeds = [TICKET_SEED, &lottery_pda.key().to_bytes(), &lottery_pda.next_ticket_id()?.to_le_bytes()], bump)]
+    pub ticket_pda: Account<'info, TicketPDA>,
+    #[account(mut, seeds = [LOTTERY_SEED, &lottery_pda.id.to_le_bytes()], bump)]
+    pub lottery_pda: Account<'info, LotteryPDA>,
+    #[account(init_if_needed, payer = buyer, space=8+WalletTicketsPDA::INIT_SPACE, seeds = [WALLET_TICKETS_SEED, &lottery_pda.key().to_bytes(), &buyer.key().to_bytes()], bump)]
+    pub wallet_tickets: Account<'info, WalletTicketsPDA>,
+    #[account(init, payer = buyer, seeds = [TICKET_NFT_SEED, &lottery_pda.key().to_bytes(), &lottery_pda.next_ticket_id()?.to_le_bytes()], bump, mint::decimals = 0, mint::authority = lottery_pda, mint::token_program = token_program, extensions::metadata_pointer::authority = lottery_pda, extensions::metadata_pointer::metadata_address = ticket_nft_mint)]
+    pub ticket_nft_mint: InterfaceAccount<'info, Mint>,
+    #[account(init, payer = buyer, associated_token::mint = ticket_nft_mint, associated_token::authority = buyer, associated_token::token_program = token_program)]
+    pub buyer_nft_account: InterfaceAccount<'info, TokenAccount>,
+    /// The referrer's account for this lottery, when the buyer was referred
+    #[account(mut, seeds = [REFERRAL_SEED, &lottery_pda.key().to_bytes(), &referral_pda.referrer.to_bytes()], bump)]
+    pub referral_pda: Option<Account<'info, ReferralPDA>>,
+    #[account(init_if_needed, payer = buyer, space=8+PlayerStats::INIT_SPACE, seeds = [PLAYER_STATS_SEED, &buyer.key().to_bytes()], bump)]
+    pub player_stats: Account<'info, PlayerStats>,
+    #[account(seeds = [MASTER_PDA_SEED], bump)]
+    pub master_pda: Account<'info, MasterPDA>,
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+    pub token_program: Program<'info, Token2022>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitTicketLedger<'info> {
+    #[account(init, payer = authority, space=TicketLedger::space(0), seeds = [TICKET_LEDGER_SEED, &lottery_pda.key().to_bytes()], bump)]
+    pub ticket_ledger: AccountLoader<'info, TicketLedger>,
+    #[account(seeds = [LOTTERY_SEED, &lottery_pda.id.to_le_bytes()], bump)]
+    pub lottery_pda: Account<'info, LotteryPDA>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>, // to create accounts
+}
+
+#[derive(Accounts)]
+pub struct BuyTickets<'info> {
+    #[account(mut, seeds = [TICKET_LEDGER_SEED, &lottery_pda.key().to_bytes()], bump)]
+    pub ticket_ledger: AccountLoader<'info, TicketLedger>,
+    #[account(mut, seeds = [LOTTERY_SEED, &lottery_pda.id.to_le_bytes()], bump)]
+    pub lottery_pda: Account<'info, LotteryPDA>,
+    #[account(init_if_needed, payer = buyer, space=8+WalletTicketsPDA::INIT_SPACE, seeds = [WALLET_TICKETS_SEED, &lottery_pda.key().to_bytes(), &buyer.key().to_bytes()], bump)]
+    pub wallet_tickets: Account<'info, WalletTicketsPDA>,
+    /// The referrer's account for this lottery, when the buyer was referred
+    #[account(mut, seeds = [REFERRAL_SEED, &lottery_pda.key().to_bytes(), &referral_pda.referrer.to_bytes()], bump)]
+    pub referral_pda: Option<Account<'info, ReferralPDA>>,
+    #[account(init_if_needed, payer = buyer, space=8+PlayerStats::INIT_SPACE, seeds = [PLAYER_STATS_SEED, &buyer.key().to_bytes()], bump)]
+    pub player_stats: Account<'info, PlayerStats>,
+    #[account(seeds = [MASTER_PDA_SEED], bump)]
+    pub master_pda: Account<'info, MasterPDA>,
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+    pub system_program: Program<'info, System>, // to transfer SOL
+}
+
+#[derive(Accounts)]
+#[instruction(recipient: Pubkey)]
+pub struct BuyTicketFor<'info> {
+    #[account(init, payer = payer, space=8+TicketPDA::INIT_SPACE, seeds = [TICKET_SEED, &lottery_pda.key().to_bytes(), &lottery_pda.next_ticket_id()?.to_le_bytes()], bump)]
+    pub ticket_pda: Account<'info, TicketPDA>,
+    #[account(mut, seeds = [LOTTERY_SEED, &lottery_pda.id.to_le_bytes()], bump)]
+    pub lottery_pda: Account<'info, LotteryPDA>,
+    #[account(init_if_needed, payer = payer, space=8+WalletTicketsPDA::INIT_SPACE, seeds = [WALLET_TICKETS_SEED, &lottery_pda.key().to_bytes(), &recipient.to_bytes()], bump)]
+    pub wallet_tickets: Account<'info, WalletTicketsPDA>,
+    #[account(init_if_needed, payer = payer, space=8+PlayerStats::INIT_SPACE, seeds = [PLAYER_STATS_SEED, &recipient.to_bytes()], bump)]
+    pub player_stats: Account<'info, PlayerStats>,
+    #[account(seeds = [MASTER_PDA_SEED], bump)]
+    pub master_pda: Account<'info, MasterPDA>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>, // to create accounts
+}
+
+#[derive(Accounts)]
+pub struct BuyTicketsFor<'info> {
+    #[account(mut, seeds = [TICKET_LEDGER_SEED, &lottery_pda.key().to_bytes()], bump)]
+    pub ticket_ledger: AccountLoader<'info, TicketLedger>,
+    #[account(mut, seeds = [LOTTERY_SEED, &lottery_pda.id.to_le_bytes()], bump)]
+    pub lottery_pda: Account<'info, LotteryPDA>,
+    #[account(seeds = [MASTER_PDA_SEED], bump)]
+    pub master_pda: Account<'info, MasterPDA>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>, // to create the wallet counters and stats
+}
+
+#[derive(Accounts)]
+pub struct TransferTicket<'info> {
+    #[account(seeds = [LOTTERY_SEED, &ticket_pda.lottery_id.to_le_bytes()], bump)]
+    pub lottery_pda: Account<'info, LotteryPDA>,
+    #[account(mut, has_one = owner @ LotteryError::UnauthorizedAction)]
+    pub ticket_pda: Account<'info, TicketPDA>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ListTicket<'info> {
+    #[account(seeds = [LOTTERY_SEED, &lottery_pda.id.to_le_bytes()], bump)]
+    pub lottery_pda: Account<'info, LotteryPDA>,
+    #[account(seeds = [TICKET_SEED, &lottery_pda.key().to_bytes(), &ticket_pda.ticket_id.to_le_bytes()], bump, has_one = owner @ LotteryError::UnauthorizedAction)]
+    pub ticket_pda: Account<'info, TicketPDA>,
+    #[account(init, payer = owner, space=8+ListingPDA::INIT_SPACE, seeds = [LISTING_SEED, &ticket_pda.key().to_bytes()], bump)]
+    pub listing_pda: Account<'info, ListingPDA>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct BuyListedTicket<'info> {
+    #[account(seeds = [LOTTERY_SEED, &lottery_pda.id.to_le_bytes()], bump)]
+    pub lottery_pda: Account<'info, LotteryPDA>,
+    #[account(mut, seeds = [TICKET_SEED, &lottery_pda.key().to_bytes(), &ticket_pda.ticket_id.to_le_bytes()], bump)]
+    pub ticket_pda: Account<'info, TicketPDA>,
+    #[account(mut, close = seller, seeds = [LISTING_SEED, &ticket_pda.key().to_bytes()], bump, has_one = seller)]
+    pub listing_pda: Account<'info, ListingPDA>,
+    #[account(mut)]
+    pub seller: SystemAccount<'info>,
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+    pub system_program: Program<'info, System>, // to pay the seller
+}
+
+#[derive(Accounts)]
+pub struct CancelListing<'info> {
+    /// CHECK: may already be closed, otherwise decoded in `cancel_listing`
+    #[account(seeds = [LOTTERY_SEED, &listing_pda.lottery_id.to_le_bytes()], bump)]
+    pub lottery_pda: UncheckedAccount<'info>,
+    /// CHECK: may already be closed, otherwise decoded in `cancel_listing`
+    #[account(seeds = [TICKET_SEED, &lottery_pda.key().to_bytes(), &listing_pda.ticket_id.to_le_bytes()], bump)]
+    pub ticket_pda: UncheckedAccount<'info>,
+    #[account(mut, close = seller, seeds = [LISTING_SEED, &ticket_pda.key().to_bytes()], bump, has_one = seller)]
+    pub listing_pda: Account<'info, ListingPDA>,
+    #[account(mut)]
+    pub seller: SystemAccount<'info>,
+    pub caller: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct TransferLotteryAuthority<'info> {
+    #[account(mut, seeds = [LOTTERY_SEED, &lottery_pda.id.to_le_bytes()], bump)]
+    pub lottery_pda: Account<'info, LotteryPDA>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptLotteryAuthority<'info> {
+    #[account(mut, seeds = [LOTTERY_SEED, &lottery_pda.id.to_le_bytes()], bump)]
+    pub lottery_pda: Account<'info, LotteryPDA>,
+    pub new_authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CommitRandomness<'info> {
+    #[account(mut, seeds = [LOTTERY_SEED, &lottery_pda.id.to_le_bytes()], bump)]
+    pub lottery_pda: Account<'info, LotteryPDA>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseSales<'info> {
+    #[account(mut, seeds = [LOTTERY_SEED, &lottery_pda.id.to_le_bytes()], bump)]
+    pub lottery_pda: Account<'info, LotteryPDA>,
+}
+
+#[derive(Accounts)]
+pub struct RevealAndPickWinner<'info> {
+    #[account(mut, seeds = [LOTTERY_SEED, &lottery_pda.id.to_le_bytes()], bump)]
+    pub lottery_pda: Account<'info, LotteryPDA>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: address is checked, the raw data is read by `slot_hash_at`
+    #[account(address = slot_hashes::ID)]
+    pub slot_hashes: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CrankDraw<'info> {
+    #[account(mut, seeds = [LOTTERY_SEED, &lottery_pda.id.to_le_bytes()], bump)]
+    pub lottery_pda: Account<'info, LotteryPDA>,
+    #[account(seeds = [MASTER_PDA_SEED], bump)]
+    pub master_pda: Account<'info, MasterPDA>,
+    #[account(mut)]
+    pub caller: Signer<'info>,
+    /// CHECK: address is checked, the raw data is read by `slot_hash_at`
+    #[account(address = slot_hashes::ID)]
+    pub slot_hashes: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelLottery<'info> {
+    #[account(mut, seeds = [LOTTERY_SEED, &lottery_pda.id.to_le_bytes()], bump)]
+    pub lottery_pda: Account<'info, LotteryPDA>,
+    pub caller: Signer<'info>,
+    /// The token accounts are only needed for asset raffles with a deposited prize
+    pub prize_mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(mut, associated_token::mint = prize_mint, associated_token::authority = lottery_pda, associated_token::token_program = token_program)]
+    pub prize_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, token::mint = prize_mint, token::authority = lottery_pda.authority, token::token_program = token_program)]
+    pub authority_prize_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+}
+
+#[derive(Accounts)]
+pub struct RefundTicket<'info> {
+    #[account(mut, seeds = [LOTTERY_SEED, &lottery_pda.id.to_le_bytes()], bump)]
+    pub lottery_pda: Account<'info, LotteryPDA>,
+    #[account(mut, close = owner, seeds = [TICKET_SEED, &lottery_pda.key().to_bytes(), &ticket_pda.ticket_id.to_le_bytes()], bump)]
+    pub ticket_pda: Account<'info, TicketPDA>,
+    #[account(mut, address = ticket_pda.owner @ LotteryError::UnauthorizedAction)]
+    pub owner: SystemAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RefundTicketWithToken<'info> {
+    #[account(mut, seeds = [LOTTERY_SEED, &lottery_pda.id.to_le_bytes()], bump)]
+    pub lottery_pda: Account<'info, LotteryPDA>,
+    #[account(mut, close = owner, seeds = [TICKET_SEED, &lottery_pda.key().to_bytes(), &ticket_pda.ticket_id.to_le_bytes()], bump)]
+    pub ticket_pda: Account<'info, TicketPDA>,
+    #[account(mut, address = ticket_pda.owner @ LotteryError::UnauthorizedAction)]
+    pub owner: SystemAccount<'info>,
+    #[account(mint::token_program = token_program)]
+    pub ticket_mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, associated_token::mint = ticket_mint, associated_token::authority = lottery_pda, associated_token::token_program = token_program)]
+    pub prize_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, token::mint = ticket_mint, token::authority = owner, token::token_program = token_program)]
+    pub owner_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct RefundNftTicket<'info> {
+    #[account(mut, seeds = [LOTTERY_SEED, &lottery_pda.id.to_le_bytes()], bump)]
+    pub lottery_pda: Account<'info, LotteryPDA>,
+    #[account(mut, close = owner, seeds = [TICKET_SEED, &lottery_pda.key().to_bytes(), &ticket_pda.ticket_id.to_le_bytes()], bump)]
+    pub ticket_pda: Account<'info, TicketPDA>,
+    /// The ticket's buyer, who paid its rent
+    #[account(mut, address = ticket_pda.owner @ LotteryError::UnauthorizedAction)]
+    pub owner: SystemAccount<'info>,
+    #[account(mut, seeds = [TICKET_NFT_SEED, &lottery_pda.key().to_bytes(), &ticket_pda.ticket_id.to_le_bytes()], bump)]
+    pub ticket_nft_mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, token::mint = ticket_nft_mint, token::authority = holder, token::token_program = token_program)]
+    pub holder_nft_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub holder: Signer<'info>,
+    pub token_program: Program<'info, Token2022>,
+}
+
+#[derive(Accounts)]
+pub struct RefundTicketRange<'info> {
+    #[account(mut, seeds = [LOTTERY_SEED, &lottery_pda.id.to_le_bytes()], bump)]
+    pub lottery_pda: Account<'info, LotteryPDA>,
+    #[account(mut, seeds = [TICKET_LEDGER_SEED, &lottery_pda.key().to_bytes()], bump)]
+    pub ticket_ledger: AccountLoader<'info, TicketLedger>,
+    #[account(mut)]
+    pub owner: SystemAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseTicket<'info> {
+    #[account(mut, close = owner, seeds = [TICKET_SEED, &lottery_pda.key().to_bytes(), &ticket_pda.ticket_id.to_le_bytes()], bump, has_one = owner @ LotteryError::UnauthorizedAction)]
+    pub ticket_pda: Account<'info, TicketPDA>,
+    /// CHECK: may already be closed by `close_lottery`, otherwise decoded in `close_ticket`
+    #[account(seeds = [LOTTERY_SEED, &ticket_pda.lottery_id.to_le_bytes()], bump)]
+    pub lottery_pda: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitReferral<'info> {
+    #[account(seeds = [LOTTERY_SEED, &lottery_pda.id.to_le_bytes()], bump)]
+    pub lottery_pda: Account<'info, LotteryPDA>,
+    #[account(init, payer = referrer, space=8+ReferralPDA::INIT_SPACE, seeds = [REFERRAL_SEED, &lottery_pda.key().to_bytes(), &referrer.key().to_bytes()], bump)]
+    pub referral_pda: Account<'info, ReferralPDA>,
+    #[account(mut)]
+    pub referrer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimReferralRewards<'info> {
+    #[account(mut, seeds = [LOTTERY_SEED, &lottery_pda.id.to_le_bytes()], bump)]
+    pub lottery_pda: Account<'info, LotteryPDA>,
+    #[account(mut, close = referrer, seeds = [REFERRAL_SEED, &lottery_pda.key().to_bytes(), &referrer.key().to_bytes()], bump, has_one = referrer)]
+    pub referral_pda: Account<'info, ReferralPDA>,
+    #[account(mut)]
+    pub referrer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseLottery<'info> {
+    #[account(mut, close = authority, seeds = [LOTTERY_SEED, &lottery_pda.id.to_le_bytes()], bump)]
+    pub lottery_pda: Account<'info, LotteryPDA>,
+    /// CHECK: always passed so the ledger's rent can't be left behind, closed in `close_lottery`
+    /// when the lottery has one
+    #[account(mut, seeds = [TICKET_LEDGER_SEED, &lottery_pda.key().to_bytes()], bump)]
+    pub ticket_ledger: UncheckedAccount<'info>,
+    /// The token accounts are only needed for lotteries with a `ticket_mint`, or a `prize_mint`
+    /// for asset raffles which then goes here
+    pub ticket_mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(mut, associated_token::mint = ticket_mint, associated_token::authority = lottery_pda, associated_token::token_program = token_program)]
+    pub prize_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, token::mint = ticket_mint, token::authority = authority, token::token_program = token_program)]
+    pub authority_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(init_if_needed, payer = authority, space=8+JackpotPDA::INIT_SPACE, seeds = [JACKPOT_SEED], bump)]
+    pub jackpot_pda: Account<'info, JackpotPDA>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub system_program: Program<'info, System>, // to create the jackpot
+}
+
+#[derive(Accounts)]
+pub struct RolloverUnclaimed<'info> {
+    #[account(mut, seeds = [LOTTERY_SEED, &lottery_pda.id.to_le_bytes()], bump)]
+    pub lottery_pda: Account<'info, LotteryPDA>,
+    /// Receives the unclaimed prizes, the master jackpot does when it's not set
+    #[account(mut, seeds = [LOTTERY_SEED, &next_lottery.id.to_le_bytes()], bump)]
+    pub next_lottery: Option<Account<'info, LotteryPDA>>,
+    #[account(init_if_needed, payer = caller, space=8+JackpotPDA::INIT_SPACE, seeds = [JACKPOT_SEED], bump)]
+    pub jackpot_pda: Account<'info, JackpotPDA>,
+    /// The token accounts are only needed for lotteries with a `ticket_mint`
+    pub ticket_mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(mut, associated_token::mint = ticket_mint, associated_token::authority = lottery_pda, associated_token::token_program = token_program)]
+    pub prize_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, associated_token::mint = ticket_mint, associated_token::authority = next_lottery, associated_token::token_program = token_program)]
+    pub next_prize_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut)]
+    pub caller: Signer<'info>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub system_program: Program<'info, System>, // to create the jackpot
+}
+
+#[derive(Accounts)]
+#[instruction(series_id: u32)]
+pub struct CreateSeries<'info> {
+    #[account(init, payer = authority, space=8+LotterySeries::INIT_SPACE, seeds = [SERIES_SEED, &authority.key().to_bytes(), &series_id.to_le_bytes()], bump)]
+    pub series: Account<'info, LotterySeries>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AdvanceSeries<'info> {
+    #[account(mut, seeds = [SERIES_SEED, &series.authority.to_bytes(), &series.series_id.to_le_bytes()], bump)]
+    pub series: Account<'info, LotterySeries>,
+    /// The series' current round, not set before the first round
+    #[account(mut, seeds = [LOTTERY_SEED, &series.current_lottery_id.to_le_bytes()], bump)]
+    pub current_lottery: Option<Account<'info, LotteryPDA>>,
+    #[account(init, payer = caller, space=8+LotteryPDA::INIT_SPACE, seeds = [LOTTERY_SEED, &(master_pda.last_lottery_id+1).to_le_bytes()], bump)]
+    pub next_lottery: Account<'info, LotteryPDA>,
+    #[account(mut, seeds = [MASTER_PDA_SEED], bump)]
+    pub master_pda: Account<'info, MasterPDA>,
+    #[account(mut)]
+    pub caller: Signer<'info>,
+    pub system_program: Program<'info, System>, // to create accounts
+}
+
+#[derive(Accounts)]
+pub struct CloseSeries<'info> {
+    #[account(mut, close = authority, seeds = [SERIES_SEED, &series.authority.to_bytes(), &series.series_id.to_le_bytes()], bump, has_one = authority @ LotteryError::UnauthorizedAction)]
+    pub series: Account<'info, LotterySeries>,
+    /// The series' current round, not set before the first round
+    #[account(mut, seeds = [LOTTERY_SEED, &series.current_lottery_id.to_le_bytes()], bump)]
+    pub current_lottery: Option<Account<'info, LotteryPDA>>,
+    #[account(init_if_needed, payer = authority, space=8+JackpotPDA::INIT_SPACE, seeds = [JACKPOT_SEED], bump)]
+    pub jackpot_pda: Account<'info, JackpotPDA>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>, // to create the jackpot
+}
+
+#[derive(Accounts)]
+pub struct SeedFromJackpot<'info> {
+    #[account(seeds = [MASTER_PDA_SEED], bump)]
+    pub master_pda: Account<'info, MasterPDA>,
+    #[account(mut, seeds = [JACKPOT_SEED], bump)]
+    pub jackpot_pda: Account<'info, JackpotPDA>,
+    #[account(mut, seeds = [LOTTERY_SEED, &lottery_pda.id.to_le_bytes()], bump)]
+    pub lottery_pda: Account<'info, LotteryPDA>,
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimRafflePrize<'info> {
+    #[account(mut, seeds = [LOTTERY_SEED, &lottery_pda.id.to_le_bytes()], bump)]
+    pub lottery_pda: Account<'info, LotteryPDA>,
+    /// Set when the winning ticket was bought with `buy_ticket`
+    #[account(seeds = [TICKET_SEED, &lottery_pda.key().to_bytes(), &lottery_pda.prize_tiers[0].winner_ticket_id.to_le_bytes()], bump)]
+    pub winner_ticket: Option<Account<'info, TicketPDA>>,
+    /// Set when the winning ticket was bought with `buy_tickets`
+    #[account(seeds = [TICKET_LEDGER_SEED, &lottery_pda.key().to_bytes()], bump)]
+    pub ticket_ledger: Option<AccountLoader<'info, TicketLedger>>,
+    #[account(mint::token_program = token_program)]
+    pub prize_mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, associated_token::mint = prize_mint, associated_token::authority = lottery_pda, associated_token::token_program = token_program)]
+    pub prize_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(init_if_needed, payer = winner, associated_token::mint = prize_mint, associated_token::authority = winner, associated_token::token_program = token_program)]
+    pub winner_prize_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(init_if_needed, payer = winner, space=8+PlayerStats::INIT_SPACE, seeds = [PLAYER_STATS_SEED, &winner.key().to_bytes()], bump)]
+    pub player_stats: Account<'info, PlayerStats>,
+    #[account(mut)]
+    pub winner: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>, // to create the winner's token account
+}
+
+#[derive(Accounts)]
+pub struct WithdrawProceeds<'info> {
+    #[account(mut, seeds = [LOTTERY_SEED, &lottery_pda.id.to_le_bytes()], bump)]
+    pub lottery_pda: Account<'info, LotteryPDA>,
+    #[account(mut, seeds = [TREASURY_SEED], bump)]
+    pub treasury_pda: Account<'info, TreasuryPDA>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterMatch<'info> {
+    #[account(mut, seeds = [LOTTERY_SEED, &lottery_pda.id.to_le_bytes()], bump)]
+    pub lottery_pda: Account<'info, LotteryPDA>,
+    #[account(mut, seeds = [TICKET_SEED, &lottery_pda.key().to_bytes(), &ticket_pda.ticket_id.to_le_bytes()], bump)]
+    pub ticket_pda: Account<'info, TicketPDA>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimMatchPrize<'info> {
+    #[account(mut, seeds = [LOTTERY_SEED, &lottery_pda.id.to_le_bytes()], bump)]
+    pub lottery_pda: Account<'info, LotteryPDA>,
+    #[account(mut, seeds = [TICKET_SEED, &lottery_pda.key().to_bytes(), &ticket_pda.ticket_id.to_le_bytes()], bump, has_one = owner @ LotteryError::UnauthorizedAction)]
+    pub ticket_pda: Account<'info, TicketPDA>,
+    #[account(mut, seeds = [TREASURY_SEED], bump)]
+    pub treasury_pda: Account<'info, TreasuryPDA>,
+    #[account(init_if_needed, payer = owner, space=8+PlayerStats::INIT_SPACE, seeds = [PLAYER_STATS_SEED, &owner.key().to_bytes()], bump)]
+    pub player_stats: Account<'info, PlayerStats>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>, // to create the player's stats
+}
+
+#[derive(Accounts)]
+#[instruction(tier: u8)]
+pub struct ClaimPrize<'info> {
+    #[account(mut, seeds = [LOTTERY_SEED, &lottery_pda.id.to_le_bytes()], bump)]
+    pub lottery_pda: Account<'info, LotteryPDA>,
+    /// Set when the winning ticket was bought with `buy_ticket`
+    #[account(mut, seeds = [TICKET_SEED, &lottery_pda.key().to_bytes(), &lottery_pda.prize_tier(tier)?.winner_ticket_id.to_le_bytes()], bump)]
+    pub winner_ticket: Option<Account<'info, TicketPDA>>,
+    /// Set when the winning ticket was bought with `buy_tickets`
+    #[account(seeds = [TICKET_LEDGER_SEED, &lottery_pda.key().to_bytes()], bump)]
+    pub ticket_ledger: Option<AccountLoader<'info, TicketLedger>>,
+    #[account(mut, seeds = [TREASURY_SEED], bump)]
+    pub treasury_pda: Account<'info, TreasuryPDA>,
+    #[account(init_if_needed, payer = winner, space=8+PlayerStats::INIT_SPACE, seeds = [PLAYER_STATS_SEED, &winner.key().to_bytes()], bump)]
+    pub player_stats: Account<'info, PlayerStats>,
+    #[account(mut)]
+    pub winner: Signer<'info>,
+    pub system_program: Program<'info, System>, // to create the player's stats
+}
+
+#[derive(Accounts)]
+#[instruction(tier: u8)]
+pub struct ClaimPrizeWithToken<'info> {
+    #[account(mut, seeds = [LOTTERY_SEED, &lottery_pda.id.to_le_bytes()], bump)]
+    pub lottery_pda: Account<'info, LotteryPDA>,
+    #[account(seeds = [TICKET_SEED, &lottery_pda.key().to_bytes(), &lottery_pda.prize_tier(tier)?.winner_ticket_id.to_le_bytes()], bump)]
+    pub winner_ticket: Account<'info, TicketPDA>,
+    #[account(mint::token_program = token_program)]
+    pub ticket_mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, associated_token::mint = ticket_mint, associated_token::authority = lottery_pda, associated_token::token_program = token_program)]
+    pub prize_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(init_if_needed, payer = winner, associated_token::mint = ticket_mint, associated_token::authority = winner, associated_token::token_program = token_program)]
+    pub winner_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(seeds = [TREASURY_SEED], bump)]
+    pub treasury_pda: Account<'info, TreasuryPDA>,
+    #[account(init_if_needed, payer = winner, associated_token::mint = ticket_mint, associated_token::authority = treasury_pda, associated_token::token_program = token_program)]
+    pub treasury_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(init_if_needed, payer = winner, space=8+PlayerStats::INIT_SPACE, seeds = [PLAYER_STATS_SEED, &winner.key().to_bytes()], bump)]
+    pub player_stats: Account<'info, PlayerStats>,
+    #[account(mut)]
+    pub winner: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>, // to create the token accounts
+}
+
+#[derive(Accounts)]
+#[instruction(tier: u8)]
+pub struct ClaimPrizeNft<'info> {
+    #[account(mut, seeds = [LOTTERY_SEED, &lottery_pda.id.to_le_bytes()], bump)]
+    pub lottery_pda: Account<'info, LotteryPDA>,
+    #[account(mut, seeds = [TICKET_NFT_SEED, &lottery_pda.key().to_bytes(), &lottery_pda.prize_tier(tier)?.winner_ticket_id.to_le_bytes()], bump)]
+    pub ticket_nft_mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, token::mint = ticket_nft_mint, token::authority = winner, token::token_program = token_program)]
+    pub winner_nft_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, seeds = [TREASURY_SEED], bump)]
+    pub treasury_pda: Account<'info, TreasuryPDA>,
+    #[account(init_if_needed, payer = winner, space=8+PlayerStats::INIT_SPACE, seeds = [PLAYER_STATS_SEED, &winner.key().to_bytes()], bump)]
+    pub player_stats: Account<'info, PlayerStats>,
+    #[account(mut)]
+    pub winner: Signer<'info>,
+    pub token_program: Program<'info, Token2022>,
+    pub system_program: Program<'info, System>, // to create the player's stats
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct CreateLotteryParams {
+    pub ticket_price_lamports: u64,
+    /// Tickets can be bought from `sale_start_ts` until just before `sale_end_ts`
+    pub sale_start_ts: i64,
+    pub sale_end_ts: i64,
+    /// Earliest time the draw can be locked in by `close_sales`
+    pub draw_after_ts: i64,
+    /// Share of the pot for each prize tier, first prize first. Must add up to 10_000.
+    pub prize_shares_bps: Vec<u16>,
+    /// Tickets that must be sold for the draw to happen, at least one per prize tier.
+    /// Below that, anyone can cancel the lottery once the sale has ended.
+    pub min_tickets: u32,
+    /// Total ticket supply, 0 for no cap
+    pub max_tickets: u32,
+    /// Tickets a single wallet may buy, 0 for no cap
+    pub max_tickets_per_wallet: u32,
+    /// Prizes still unclaimed at this time can be rolled over by `rollover_unclaimed`
+    pub claim_deadline_ts: i64,
+    /// Sell every ticket as an NFT with `buy_nft_ticket`, SOL lotteries only
+    pub nft_tickets: bool,
+    /// Share of referred SOL ticket purchases that goes to the referrer, at most `MAX_REFERRAL_BPS`
+    pub referral_bps: u16,
+    /// Paid from the ticket sales to whoever draws the lottery with `crank_draw` after the
+    /// authority missed its reveal, SOL lotteries only
+    pub crank_bounty_lamports: u64,
+    /// `GameMode::Numbers` games are SOL lotteries sold with `buy_ticket` only
+    pub game_mode: GameMode,
+    /// Numbers each ticket picks in a numbers game, 0 otherwise. Prize tier `i` pays tickets
+    /// matching all but `i` of them, so there are at most this many tiers.
+    pub numbers_per_ticket: u8,
+    /// Numbers are picked from 1 to `max_number`, 0 outside numbers games
+    pub max_number: u8,
+    /// Makes the lottery an asset raffle: its single winner gets `prize_amount` of this mint,
+    /// deposited with `deposit_prize`, and the ticket sales go to the authority instead.
+    /// SOL tickets only.
+    pub prize_mint: Option<Pubkey>,
+    pub prize_amount: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct SeriesParams {
+    pub ticket_price_lamports: u64,
+    /// Each round sells tickets from its creation for this long, then can be drawn
+    pub sale_duration_secs: i64,
+    /// Time winners have after the draw time to claim
+    pub claim_period_secs: i64,
+    pub prize_shares_bps: Vec<u16>,
+    pub min_tickets: u32,
+    pub max_tickets: u32,
+    pub max_tickets_per_wallet: u32,
+    pub referral_bps: u16,
+    /// Share of each round's pot that seeds the next round instead of paying prizes, at most
+    /// `MAX_CARRY_OVER_BPS`
+    pub carry_over_bps: u16,
+    pub crank_bounty_lamports: u64,
+    /// Highest protocol fee a round may be created with, at most `MAX_FEE_BPS`
+    pub max_fee_bps: u16,
+}
+
+impl CreateLotteryParams {
+    fn validate(&self) -> Result<()> {
+        if self.sale_start_ts >= self.sale_end_ts
+            || self.sale_end_ts > self.draw_after_ts
+            || self.draw_after_ts >= self.claim_deadline_ts
+        {
+            return err!(LotteryError::InvalidSchedule);
+        }
+        let prize_tier_count = self.prize_shares_bps.len();
+        if prize_tier_count == 0
+            || prize_tier_count > MAX_PRIZE_TIERS
+            || self.prize_shares_bps.contains(&0)
+            || self
+                .prize_shares_bps
+                .iter()
+                .map(|&bps| bps as u32)
+                .sum::<u32>()
+                != 10_000
+        {
+            return err!(LotteryError::InvalidPrizeTiers);
+        }
+        if (self.min_tickets as usize) < prize_tier_count {
+            return err!(LotteryError::InvalidMinTickets);
+        }
+        if self.max_tickets != 0 && self.max_tickets < self.min_tickets {
+            return err!(LotteryError::InvalidTicketCaps);
+        }
+        if self.referral_bps > MAX_REFERRAL_BPS {
+            return err!(LotteryError::InvalidReferralShare);
+        }
+        let numbers_valid = match self.game_mode {
+            GameMode::TicketDraw => self.numbers_per_ticket == 0 && self.max_number == 0,
+            GameMode::Numbers => {
+                !self.nft_tickets
+                    && self.numbers_per_ticket != 0
+                    && self.numbers_per_ticket as usize <= MAX_NUMBER_PICKS
+                    && self.max_number >= self.numbers_per_ticket
+                    && self.max_number <= MAX_NUMBER
+                    && prize_tier_count <= self.numbers_per_ticket as usize
+            }
+        };
+        if !numbers_valid {
+            return err!(LotteryError::InvalidNumbersGame);
+        }
+        let raffle_valid = match self.prize_mint {
+            None => self.prize_amount == 0,
+            Some(_) => {
+                self.prize_amount != 0
+                    && prize_tier_count == 1
+                    && !self.nft_tickets
+                    && self.game_mode == GameMode::TicketDraw
+            }
+        };
+        if !raffle_valid {
+            return err!(LotteryError::InvalidRafflePrize);
+        }
+        Ok(())
+    }
+}
+
+/// Each account's layout version, stored in its `version` byte. Fields are only ever appended
+/// after it and must read zero as their default, so an account with an older layout becomes
+/// the current one once `migrate_account` has grown it with zeroes and bumped its version.
+/// Tickets created before versioning read as version 0, lotteries had another layout and are
+/// converted from `LegacyLotteryPDAv0` instead.
+pub trait Versioned {
+    const VERSION: u8;
+    fn version_mut(&mut self) -> &mut u8;
+    /// Fills in the fields added since `from_version`, which are zero after the migration grew
+    /// the account
+    fn migrate(&mut self, _from_version: u8) -> Result<()> {
+        Ok(())
+    }
+}
+
+pub const MASTER_PDA_SEED: &[u8] = b"master";
+#[account]
+#[derive(Debug, InitSpace)]
+pub struct MasterPDA {
+    pub last_lottery_id: u32,
+    pub admin: Pubkey,
+    /// Protocol fee taken from each prize, in basis points
+    pub fee_bps: u16,
+    pub paused: bool,
+    pub version: u8,
+}
+impl Versioned for MasterPDA {
+    const VERSION: u8 = 1;
+    fn version_mut(&mut self) -> &mut u8 {
+        &mut self.version
+    }
+}
+impl MasterPDA {
+    pub fn pda() -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[MASTER_PDA_SEED], &crate::ID)
+    }
+    pub fn check_not_paused(&self) -> Result<()> {
+        if self.paused {
+            return err!(LotteryError::ProgramPaused);
+        }
+        Ok(())
+    }
+}
+
+/// Upper bound for `MasterPDA::fee_bps` (10%)
+pub const MAX_FEE_BPS: u16 = 1_000;
+
+pub const TREASURY_SEED: &[u8] = b"treasury";
+/// Holds the protocol fees as lamports until the admin withdraws them
+#[account]
+#[derive(Debug, InitSpace)]
+pub struct TreasuryPDA {
+    pub collected_fees_lamports: u64,
+    pub version: u8,
+}
+impl Versioned for TreasuryPDA {
+    const VERSION: u8 = 1;
+    fn version_mut(&mut self) -> &mut u8 {
+        &mut self.version
+    }
+}
+
+pub const JACKPOT_SEED: &[u8] = b"jackpot";
+/// Collects unclaimed prizes that weren't rolled into a specific lottery, until the admin seeds
+/// a lottery's pot with them
+#[account]
+#[derive(Debug, InitSpace)]
+pub struct JackpotPDA {
+    pub balance_lamports: u64,
+    pub version: u8,
+}
+impl Versioned for JackpotPDA {
+    const VERSION: u8 = 1;
+    fn version_mut(&mut self) -> &mut u8 {
+        &mut self.version
+    }
+}
+// impl PDA for MasterPDA {
+//     type Args = ();
+//     fn seeds(_args: ()) -> &'static [&'static [u8]] {
+//         &[MASTER_PDA_SEED]
+//     }
+// }
+
+pub const SERIES_SEED: &[u8] = b"series";
+/// Upper bound for the `crank_draw` bounty, as a share of the ticket sales (1%)
+pub const MAX_CRANK_BOUNTY_BPS: u16 = 100;
+/// Upper bound for `LotterySeries::carry_over_bps` (50%)
+pub const MAX_CARRY_OVER_BPS: u16 = 5_000;
+/// Template of a recurring lottery, see `advance_series`. Like any lottery, each round takes
+/// the protocol fee in effect when it's created, which players can count on staying within
+/// `max_fee_bps`.
+#[account]
+#[derive(Debug, InitSpace)]
+pub struct LotterySeries {
+    pub authority: Pubkey,
+    pub series_id: u32,
+    pub ticket_price_lamports: u64,
+    pub sale_duration_secs: i64,
+    pub claim_period_secs: i64,
+    /// Only the first `prize_tier_count` entries are used
+    pub prize_shares_bps: [u16; MAX_PRIZE_TIERS],
+    pub prize_tier_count: u8,
+    pub min_tickets: u32,
+    pub max_tickets: u32,
+    pub max_tickets_per_wallet: u32,
+    pub referral_bps: u16,
+    pub carry_over_bps: u16,
+    pub crank_bounty_lamports: u64,
+    pub max_fee_bps: u16,
+    /// 0 until the first round is created
+    pub current_lottery_id: u32,
+    pub version: u8,
+}
+impl Versioned for LotterySeries {
+    const VERSION: u8 = 1;
+    fn version_mut(&mut self) -> &mut u8 {
+        &mut self.version
+    }
+}
+impl LotterySeries {
+    /// Parameters of a round created at `now`
+    fn round_params(&self, now: i64) -> CreateLotteryParams {
+        let sale_end_ts = now.saturating_add(self.sale_duration_secs);
+        CreateLotteryParams {
+            ticket_price_lamports: self.ticket_price_lamports,
+            sale_start_ts: now,
+            sale_end_ts,
+            draw_after_ts: sale_end_ts,
+            prize_shares_bps: self.prize_shares_bps[..self.prize_tier_count as usize].to_vec(),
+            min_tickets: self.min_tickets,
+            max_tickets: self.max_tickets,
+            max_tickets_per_wallet: self.max_tickets_per_wallet,
+            claim_deadline_ts: sale_end_ts.saturating_add(self.claim_period_secs),
+            nft_tickets: false,
+            referral_bps: self.referral_bps,
+            crank_bounty_lamports: self.crank_bounty_lamports,
+            game_mode: GameMode::TicketDraw,
+            numbers_per_ticket: 0,
+            max_number: 0,
+            prize_mint: None,
+            prize_amount: 0,
+        }
+    }
+}
+
+pub const LOTTERY_SEED: &[u8] = b"lottery";
+/// Slots the authority has after `close_sales` to reveal its secret (~2.5 minutes).
+/// Must stay below the 512 entries kept by the SlotHashes sysvar.
+pub const REVEAL_WINDOW_SLOTS: u64 = 400;
+pub const MAX_PRIZE_TIERS: usize = 5;
+pub const MAX_NUMBER_PICKS: usize = 8;
+pub const MAX_NUMBER: u8 = 99;
+/// Time after the draw for matching tickets of a numbers game to be registered (1 day).
+/// Claims open once it's over, and the draw pushes `claim_deadline_ts` back to leave them
+/// at least as long.
+pub const MATCH_REGISTRATION_SECS: i64 = 24 * 60 * 60;
+
+#[derive(
+    AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq, InitSpace,
+)]
+pub enum GameMode {
+    /// Each prize tier is won by one drawn ticket
+    #[default]
+    TicketDraw,
+    /// Tickets pick numbers, tiers are won by matching the drawn numbers
+    Numbers,
+}
+
+#[derive(
+    AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq, InitSpace,
+)]
+pub enum LotteryStatus {
+    #[default]
+    Active,
+    /// No draw will happen, ticket owners can get refunds
+    Cancelled,
+    /// The claim deadline passed and the unclaimed prizes were rolled over
+    Expired,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, InitSpace)]
+pub struct PrizeTier {
+    pub share_bps: u16,
+    /// 0 until the winners are drawn, ticket IDs start at 1
+    pub winner_ticket_id: u32,
+    pub claimed: bool,
+}
+#[account]
+#[derive(Debug, InitSpace)]
+pub struct LotteryPDA {
+    pub id: u32,
+    pub authority: Pubkey,
+    pub ticket_price_lamports: u64,
+    pub last_ticket_id: u32,
+    /// Only the first `prize_tier_count` entries are used
+    pub prize_tiers: [PrizeTier; MAX_PRIZE_TIERS],
+    pub prize_tier_count: u8,
+    /// `sha256(secret)` committed by the authority, all zeroes until `commit_randomness`
+    pub randomness_commitment: [u8; 32],
+    pub sales_closed_slot: Option<u64>,
+    /// Seed the winners were drawn from, kept so the draw can be audited
+    pub draw_seed: [u8; 32],
+    /// The authority missed its reveal and the draw fell to `crank_draw`
+    pub reveal_forfeited: bool,
+    /// Running hash of the entropy contributed by ticket buyers
+    pub entropy_accumulator: [u8; 32],
+    /// Slot whose SlotHashes entry seeds the draw
+    pub entropy_slot: u64,
+    pub sale_start_ts: i64,
+    pub sale_end_ts: i64,
+    pub draw_after_ts: i64,
+    pub fee_bps: u16,
+    /// When set, tickets are paid and prizes paid out in this mint instead of SOL, and the
+    /// ticket price and the pot amounts (sales, jackpot, prize pool, prizes paid) are in the
+    /// mint's base units despite their `_lamports` names
+    pub ticket_mint: Option<Pubkey>,
+    pub min_tickets: u32,
+    pub status: LotteryStatus,
+    /// 0 for no cap
+    pub max_tickets: u32,
+    /// 0 for no cap
+    pub max_tickets_per_wallet: u32,
+    pub tickets_refunded: u32,
+    pub claim_deadline_ts: i64,
+    /// Rolled over from earlier lotteries or seeded from the master jackpot, added to the pot.
+    /// Same currency as the pot: only lotteries with the same `ticket_mint` roll over into it.
+    pub jackpot_lamports: u64,
+    /// Proposed by `transfer_lottery_authority`, becomes the authority once it accepts
+    pub pending_authority: Option<Pubkey>,
+    pub version: u8,
+    /// Tickets are minted as NFTs by `buy_nft_ticket`, and their holder claims or refunds them
+    pub nft_tickets: bool,
+    /// Share of referred ticket purchases accrued to the referrer instead of the pot
+    pub referral_bps: u16,
+    /// Referral shares accrued so far, not part of the pot
+    pub referral_lamports: u64,
+    pub referral_claimed_lamports: u64,
+    /// Share of the pot held back from prizes for the next round of its series
+    pub carry_over_bps: u16,
+    /// Set once `advance_series` or `close_series` took the held back share
+    pub carried_over: bool,
+    /// The authority's secret once revealed, kept so the draw can be audited
+    pub revealed_secret: [u8; 32],
+    /// Offered to whoever draws the lottery with `crank_draw` after a missed reveal
+    pub crank_bounty_lamports: u64,
+    /// Taken from the ticket sales, before the prize pool, when `crank_draw` drew the lottery
+    pub crank_bounty_paid_lamports: u64,
+    /// Ticket sales held by the lottery, less refunds
+    pub sales_lamports: u64,
+    /// The pot as of the draw, split between the prize tiers
+    pub prize_pool_lamports: u64,
+    /// Prizes claimed so far, fees included
+    pub prizes_paid_lamports: u64,
+    pub game_mode: GameMode,
+    pub numbers_per_ticket: u8,
+    pub max_number: u8,
+    /// Drawn numbers of a numbers game in ascending order, zero until the draw
+    pub winning_numbers: [u8; MAX_NUMBER_PICKS],
+    pub drawn_ts: i64,
+    /// Tickets registered for each prize tier of a numbers game
+    pub match_winner_counts: [u32; MAX_PRIZE_TIERS],
+    /// Set for asset raffles, whose prize is `prize_amount` of this mint instead of the pot
+    pub prize_mint: Option<Pubkey>,
+    pub prize_amount: u64,
+    /// The raffle prize is in the prize vault
+    pub prize_deposited: bool,
+    /// `withdraw_proceeds` paid the raffle's ticket sales to the authority
+    pub proceeds_withdrawn: bool,
+}
+/// `LotteryPDA` as created before versioning, with a single winner and no version byte
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct LegacyLotteryPDAv0 {
+    id: u32,
+    authority: Pubkey,
+    ticket_price_lamports: u64,
+    last_ticket_id: u32,
+    winner_ticket_id: Option<u32>,
+    claimed: bool,
+}
+impl LegacyLotteryPDAv0 {
+    /// These accounts were sized with `std::mem::size_of`, which no versioned lottery matches
+    pub const LEN: usize = 8 + std::mem::size_of::<Self>();
+
+    /// Reads a lottery account in this layout as the current one, with the derived fields filled
+    /// in like any lottery migrated from version 0
+    pub fn upgrade(data: &[u8]) -> Result<LotteryPDA> {
+        let body = data.get(8..).ok_or(ErrorCode::AccountDidNotDeserialize)?;
+        let legacy =
+            Self::deserialize(&mut &body[..]).map_err(|_| ErrorCode::AccountDidNotDeserialize)?;
+        let mut lottery = legacy.into_current();
+        lottery.migrate(0)?;
+        lottery.version = LotteryPDA::VERSION;
+        Ok(lottery)
+    }
+
+    /// The lottery had no schedule, so sales stay open, the draw may happen at any time and the
+    /// prize can be claimed without a deadline
+    fn into_current(self) -> LotteryPDA {
+        let mut prize_tiers = [PrizeTier::default(); MAX_PRIZE_TIERS];
+        prize_tiers[0] = PrizeTier {
+            share_bps: 10_000,
+            winner_ticket_id: self.winner_ticket_id.unwrap_or(0),
+            claimed: self.claimed,
+        };
+        LotteryPDA {
+            id: self.id,
+            authority: self.authority,
+            ticket_price_lamports: self.ticket_price_lamports,
+            last_ticket_id: self.last_ticket_id,
+            prize_tiers,
+            prize_tier_count: 1,
+            randomness_commitment: [0; 32],
+            sales_closed_slot: None,
+            draw_seed: [0; 32],
+            reveal_forfeited: false,
+            entropy_accumulator: [0; 32],
+            entropy_slot: 0,
+            sale_start_ts: 0,
+            sale_end_ts: i64::MAX,
+            draw_after_ts: 0,
+            fee_bps: 0,
+            ticket_mint: None,
+            min_tickets: 1,
+            status: LotteryStatus::Active,
+            max_tickets: 0,
+            max_tickets_per_wallet: 0,
+            tickets_refunded: 0,
+            claim_deadline_ts: i64::MAX,
+            jackpot_lamports: 0,
+            pending_authority: None,
+            version: 0,
+            nft_tickets: false,
+            referral_bps: 0,
+            referral_lamports: 0,
+            referral_claimed_lamports: 0,
+            carry_over_bps: 0,
+            carried_over: false,
+            revealed_secret: [0; 32],
+            crank_bounty_lamports: 0,
+            crank_bounty_paid_lamports: 0,
+            sales_lamports: 0,
+            prize_pool_lamports: 0,
+            prizes_paid_lamports: 0,
+            game_mode: GameMode::TicketDraw,
+            numbers_per_ticket: 0,
+            max_number: 0,
+            winning_numbers: [0; MAX_NUMBER_PICKS],
+            drawn_ts: 0,
+            match_winner_counts: [0; MAX_PRIZE_TIERS],
+            prize_mint: None,
+            prize_amount: 0,
+            prize_deposited: false,
+            proceeds_withdrawn: false,
+        }
+    }
+}
+impl Versioned for LotteryPDA {
+    const VERSION: u8 = 8;
+    fn version_mut(&mut self) -> &mut u8 {
+        &mut self.version
+    }
+    fn migrate(&mut self, from_version: u8) -> Result<()> {
+        if from_version < 6 {
+            // Lotteries used to derive their pot from the tickets sold
+            self.sales_lamports = 0;
+            self.add_sales(self.last_ticket_id - self.tickets_refunded)?;
+            if self.is_drawn() {
+                self.prize_pool_lamports = self.pot_lamports()?;
+                self.prizes_paid_lamports = self.prize_tiers[..self.prize_tier_count as usize]
+                    .iter()
+                    .filter(|tier| tier.claimed)
+                    .map(|tier| self.tier_prize_lamports(tier))
+                    .sum();
+            }
+        }
+        Ok(())
+    }
+}
+impl LotteryPDA {
+    /// Sets up a new lottery with the next ID of `master`
+    fn init(
+        &mut self,
+        master: &mut MasterPDA,
+        authority: Pubkey,
+        params: &CreateLotteryParams,
+    ) -> Result<()> {
+        params.validate()?;
+        self.id = master.last_lottery_id + 1;
+        self.authority = authority;
+        self.ticket_price_lamports = params.ticket_price_lamports;
+        // Snapshot the fee so a later `set_fee` can't change the terms of a running lottery
+        self.fee_bps = master.fee_bps;
+        self.last_ticket_id = 0;
+        self.prize_tiers = [PrizeTier::default(); MAX_PRIZE_TIERS];
+        for (tier, &share_bps) in self.prize_tiers.iter_mut().zip(&params.prize_shares_bps) {
+            tier.share_bps = share_bps;
+        }
+        self.prize_tier_count = params.prize_shares_bps.len() as u8;
+        self.randomness_commitment = [0; 32];
+        self.sales_closed_slot = None;
+        self.draw_seed = [0; 32];
+        self.reveal_forfeited = false;
+        self.entropy_accumulator = [0; 32];
+        self.entropy_slot = 0;
+        self.sale_start_ts = params.sale_start_ts;
+        self.sale_end_ts = params.sale_end_ts;
+        self.draw_after_ts = params.draw_after_ts;
+        self.ticket_mint = None;
+        self.min_tickets = params.min_tickets;
+        self.status = LotteryStatus::Active;
+        self.max_tickets = params.max_tickets;
+        self.max_tickets_per_wallet = params.max_tickets_per_wallet;
+        self.tickets_refunded = 0;
+        self.claim_deadline_ts = params.claim_deadline_ts;
+        self.nft_tickets = params.nft_tickets;
+        self.referral_bps = params.referral_bps;
+        self.referral_lamports = 0;
+        self.referral_claimed_lamports = 0;
+        self.carry_over_bps = 0;
+        self.carried_over = false;
+        self.revealed_secret = [0; 32];
+        self.crank_bounty_lamports = params.crank_bounty_lamports;
+        self.crank_bounty_paid_lamports = 0;
+        self.sales_lamports = 0;
+        self.prize_pool_lamports = 0;
+        self.prizes_paid_lamports = 0;
+        self.game_mode = params.game_mode;
+        self.numbers_per_ticket = params.numbers_per_ticket;
+        self.max_number = params.max_number;
+        self.winning_numbers = [0; MAX_NUMBER_PICKS];
+        self.drawn_ts = 0;
+        self.match_winner_counts = [0; MAX_PRIZE_TIERS];
+        self.prize_mint = params.prize_mint;
+        self.prize_amount = params.prize_amount;
+        self.prize_deposited = false;
+        self.proceeds_withdrawn = false;
+        self.jackpot_lamports = 0;
+        self.pending_authority = None;
+        self.version = LotteryPDA::VERSION;
+        master.last_lottery_id = self.id;
+        Ok(())
+    }
+    fn created_event(&self, lottery: Pubkey) -> LotteryCreated {
+        LotteryCreated {
+            lottery_id: self.id,
+            lottery,
+            authority: self.authority,
+            ticket_price_lamports: self.ticket_price_lamports,
+            prize_shares_bps: self.prize_tiers[..self.prize_tier_count as usize]
+                .iter()
+                .map(|tier| tier.share_bps)
+                .collect(),
+            fee_bps: self.fee_bps,
+            sale_start_ts: self.sale_start_ts,
+            sale_end_ts: self.sale_end_ts,
+            draw_after_ts: self.draw_after_ts,
+            claim_deadline_ts: self.claim_deadline_ts,
+            min_tickets: self.min_tickets,
+            max_tickets: self.max_tickets,
+            max_tickets_per_wallet: self.max_tickets_per_wallet,
+        }
+    }
+    pub fn pda(lottery_id: u32) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[LOTTERY_SEED, &lottery_id.to_le_bytes()], &crate::ID)
+    }
+    pub fn next_ticket_id(&self) -> Result<u32> {
+        if self.max_tickets != 0 && self.last_ticket_id >= self.max_tickets {
+            return err!(LotteryError::SoldOut);
+        }
+        Ok(self.last_ticket_id + 1)
+    }
+    /// Hands out the next `count` ticket IDs to a wallet, within the supply and per-wallet caps.
+    /// Returns the first ticket ID.
+    fn add_tickets(&mut self, wallet_tickets: &mut WalletTicketsPDA, count: u32) -> Result<u32> {
+        let first_ticket_id = self.next_ticket_id()?;
+        let last_ticket_id = self
+            .last_ticket_id
+            .checked_add(count)
+            .ok_or(LotteryError::SoldOut)?;
+        if self.max_tickets != 0 && last_ticket_id > self.max_tickets {
+            return err!(LotteryError::SoldOut);
+        }
+        let wallet_total = wallet_tickets.tickets_bought.saturating_add(count);
+        if self.max_tickets_per_wallet != 0 && wallet_total > self.max_tickets_per_wallet {
+            return err!(LotteryError::WalletTicketLimitReached);
+        }
+
+        wallet_tickets.tickets_bought = wallet_total;
+        // Created with `init_if_needed` on the wallet's first purchase
+        wallet_tickets.version = WalletTicketsPDA::VERSION;
+        self.last_ticket_id = last_ticket_id;
+        Ok(first_ticket_id)
+    }
+    pub fn has_commitment(&self) -> bool {
+        self.randomness_commitment != [0; 32]
+    }
+    pub fn check_active(&self) -> Result<()> {
+        match self.status {
+            LotteryStatus::Active => Ok(()),
+            LotteryStatus::Cancelled => err!(LotteryError::LotteryCancelled),
+            LotteryStatus::Expired => err!(LotteryError::LotteryExpired),
+        }
+    }
+    pub fn check_refundable(&self) -> Result<()> {
+        if self.status != LotteryStatus::Cancelled {
+            return err!(LotteryError::LotteryNotCancelled);
+        }
+        Ok(())
+    }
+    pub fn check_sales_open(&self, now: i64) -> Result<()> {
+        self.check_active()?;
+        if self.is_drawn() {
+            return err!(LotteryError::WinnerAlreadyExists);
+        }
+        if !self.has_commitment() {
+            return err!(LotteryError::RandomnessNotCommitted);
+        }
+        if self.prize_mint.is_some() && !self.prize_deposited {
+            return err!(LotteryError::PrizeNotDeposited);
+        }
+        if now < self.sale_start_ts {
+            return err!(LotteryError::SalesNotOpen);
+        }
+        if now >= self.sale_end_ts || self.sales_closed_slot.is_some() {
+            return err!(LotteryError::SalesClosed);
+        }
+        Ok(())
+    }
+    /// `mint` is the currency the caller pays or claims in, `None` for SOL
+    pub fn check_ticket_mint(&self, mint: Option<Pubkey>) -> Result<()> {
+        if self.ticket_mint != mint {
+            return err!(LotteryError::WrongTicketMint);
+        }
+        Ok(())
+    }
+    pub fn check_nft_tickets(&self, nft_tickets: bool) -> Result<()> {
+        if self.nft_tickets != nft_tickets {
+            return err!(LotteryError::WrongTicketKind);
+        }
+        Ok(())
+    }
+    pub fn is_drawn(&self) -> bool {
+        self.prize_tiers[0].winner_ticket_id != 0 || self.winning_numbers[0] != 0
+    }
+    pub fn check_raffle(&self, raffle: bool) -> Result<()> {
+        if self.prize_mint.is_some() != raffle {
+            return err!(LotteryError::WrongPrizeKind);
+        }
+        Ok(())
+    }
+    pub fn check_game_mode(&self, game_mode: GameMode) -> Result<()> {
+        if self.game_mode != game_mode {
+            return err!(LotteryError::WrongGameMode);
+        }
         Ok(())
     }
-
-    pub fn create_lottery(ctx: Context<CreateLottery>, ticket_price_lamports: u64) -> Result<()> {
-        msg!("Creating a new lottery...");
-        let lottery = &mut ctx.accounts.lottery_pda;
-        let master = &mut ctx.accounts.master_pda;
-        lottery.id = master.last_lottery_id + 1;
-        lottery.authority = ctx.accounts.authority.key();
-        lottery.ticket_price_lamports = ticket_price_lamports;
-        lottery.last_ticket_id = 0;
-        lottery.winner_ticket_id = None;
-        lottery.claimed = false;
-        master.last_lottery_id = lottery.id;
+    /// Checks a buyer's picks against the game, returns them sorted and zero-padded
+    fn check_picks(&self, picks: &[u8]) -> Result<[u8; MAX_NUMBER_PICKS]> {
+        if picks.len() != self.numbers_per_ticket as usize {
+            return err!(LotteryError::InvalidNumbers);
+        }
+        let mut numbers = [0; MAX_NUMBER_PICKS];
+        numbers[..picks.len()].copy_from_slice(picks);
+        numbers[..picks.len()].sort_unstable();
+        let in_range = numbers[..picks.len()]
+            .iter()
+            .all(|&number| (1..=self.max_number).contains(&number));
+        if !in_range
+            || numbers[..picks.len()]
+                .windows(2)
+                .any(|pair| pair[0] == pair[1])
+        {
+            return err!(LotteryError::InvalidNumbers);
+        }
+        Ok(numbers)
+    }
+    /// Prize tier a ticket of a numbers game wins, if any
+    pub fn match_tier(&self, ticket: &TicketPDA) -> Option<u8> {
+        let count = self.numbers_per_ticket as usize;
+        if self.game_mode != GameMode::Numbers || !self.is_drawn() {
+            return None;
+        }
+        let matches = ticket.numbers[..count]
+            .iter()
+            .filter(|number| self.winning_numbers[..count].contains(number))
+            .count();
+        let tier = (count - matches) as u8;
+        (tier < self.prize_tier_count).then_some(tier)
+    }
+    pub fn match_registration_end_ts(&self) -> i64 {
+        self.drawn_ts.saturating_add(MATCH_REGISTRATION_SECS)
+    }
+    /// Share of the prize pool for each ticket registered for `tier`
+    pub fn match_prize_lamports(&self, tier: u8) -> u64 {
+        let winners = self.match_winner_counts[tier as usize].max(1);
+        self.tier_prize_lamports(&self.prize_tiers[tier as usize]) / winners as u64
+    }
+    pub fn prize_tier(&self, tier: u8) -> Result<PrizeTier> {
+        if tier >= self.prize_tier_count {
+            return err!(LotteryError::InvalidPrizeTier);
+        }
+        Ok(self.prize_tiers[tier as usize])
+    }
+    /// Prize money: ticket sales and jackpot, less referral shares and the series carry-over
+    pub fn pot_lamports(&self) -> Result<u64> {
+        self.gross_pot_lamports()?
+            .checked_sub(self.carry_over_lamports()?)
+            .ok_or(error!(LotteryError::ArithmeticOverflow))
+    }
+    fn gross_pot_lamports(&self) -> Result<u64> {
+        self.sales_lamports
+            .checked_add(self.jackpot_lamports)
+            .and_then(|pot| pot.checked_sub(self.referral_lamports))
+            .ok_or(error!(LotteryError::ArithmeticOverflow))
+    }
+    pub fn carry_over_lamports(&self) -> Result<u64> {
+        Ok((self.gross_pot_lamports()? as u128 * self.carry_over_bps as u128 / 10_000) as u64)
+    }
+    fn carry_over_settled(&self) -> Result<bool> {
+        Ok(self.carried_over || self.carry_over_lamports()? == 0)
+    }
+    /// Share of the prize pool won by `tier`
+    pub fn tier_prize_lamports(&self, tier: &PrizeTier) -> u64 {
+        (self.prize_pool_lamports as u128 * tier.share_bps as u128 / 10_000) as u64
+    }
+    /// Adds the price of `count` tickets to the sales, returns that price
+    fn add_sales(&mut self, count: u32) -> Result<u64> {
+        let price = self
+            .ticket_price_lamports
+            .checked_mul(count as u64)
+            .ok_or(LotteryError::ArithmeticOverflow)?;
+        self.sales_lamports = self
+            .sales_lamports
+            .checked_add(price)
+            .ok_or(LotteryError::ArithmeticOverflow)?;
+        Ok(price)
+    }
+    /// Takes the refund of `count` tickets out of the sales, returns it. That's their share of
+    /// what's left of the sales.
+    fn refund_sales(&mut self, count: u32) -> Result<u64> {
+        let tickets_left = self.last_ticket_id - self.tickets_refunded;
+        if count > tickets_left {
+            return err!(LotteryError::ArithmeticOverflow);
+        }
+        let refund = (self.sales_lamports as u128 * count as u128 / tickets_left as u128) as u64;
+        self.sales_lamports -= refund;
+        self.tickets_refunded += count;
+        Ok(refund)
+    }
+    /// Records a claimed prize of `amount`, which can't exceed what's left of the prize pool
+    fn pay_prize(&mut self, amount: u64) -> Result<()> {
+        let paid = self
+            .prizes_paid_lamports
+            .checked_add(amount)
+            .ok_or(LotteryError::ArithmeticOverflow)?;
+        if paid > self.prize_pool_lamports {
+            return err!(LotteryError::InsufficientPot);
+        }
+        self.prizes_paid_lamports = paid;
         Ok(())
     }
-
-    pub fn buy_ticket(ctx: Context<BuyTicket>) -> Result<()> {
-        let lottery = &mut ctx.accounts.lottery_pda;
-        msg!("Buying a ticket for lottery ID: {}", lottery.id);
-        let ticket = &mut ctx.accounts.ticket_pda;
-        let buyer = &ctx.accounts.buyer;
-
-        if lottery.winner_ticket_id.is_some() {
-            return err!(LotteryError::WinnerAlreadyExists);
+    pub fn fee_for(&self, amount: u64) -> u64 {
+        (amount as u128 * self.fee_bps as u128 / 10_000) as u64
+    }
+    /// Checks that `tier` has a winner that can still claim, whoever holds the ticket: NFT
+    /// tickets are claimed by the holder of their token rather than a recorded owner
+    fn check_claimable(&self, tier: u8, now: i64) -> Result<PrizeTier> {
+        self.check_active()?;
+        self.check_game_mode(GameMode::TicketDraw)?;
+        if now >= self.claim_deadline_ts {
+            return err!(LotteryError::ClaimDeadlinePassed);
         }
+        let prize_tier = self.prize_tier(tier)?;
+        if prize_tier.winner_ticket_id == 0 {
+            return err!(LotteryError::WinnerNotChosenYet);
+        }
+        if prize_tier.claimed {
+            return err!(LotteryError::TicketAlreadyClaimed);
+        }
+        Ok(prize_tier)
+    }
+    /// Checks that `winner` owns the unclaimed winning ticket of `tier`. The winning ticket is
+    /// either its own `TicketPDA` or part of a range in the ticket ledger.
+    fn check_claim(
+        &self,
+        tier: u8,
+        winner_ticket: Option<&TicketPDA>,
+        ticket_ledger: Option<&[TicketRange]>,
+        winner: &Pubkey,
+        now: i64,
+    ) -> Result<PrizeTier> {
+        let prize_tier = self.check_claimable(tier, now)?;
 
-        // Transfer SOL to the lottery PDA
-        invoke(
-            &system_instruction::transfer(
-                &buyer.key(),
-                &lottery.key(),
-                lottery.ticket_price_lamports as u64,
-            ),
-            &[
-                buyer.to_account_info(),
-                lottery.to_account_info(),
-                ctx.accounts.system_program.to_account_info(),
-            ],
-        )?;
-
-        lottery.last_ticket_id += 1;
-        // Create ticket account
-        ticket.lottery_id = lottery.id;
-        ticket.ticket_id = lottery.last_ticket_id;
-        ticket.owner = *buyer.key;
+        let ticket_owner = match (winner_ticket, ticket_ledger) {
+            (Some(winner_ticket), _) => {
+                if winner_ticket.ticket_id != prize_tier.winner_ticket_id {
+                    return err!(LotteryError::WrongTicketId);
+                }
+                if winner_ticket.lottery_id != self.id {
+                    return err!(LotteryError::WrongLotteryId);
+                }
+                winner_ticket.owner
+            }
+            (None, Some(ticket_ledger)) => {
+                TicketLedger::owner_of(ticket_ledger, prize_tier.winner_ticket_id)
+                    .ok_or(LotteryError::WrongTicketId)?
+            }
+            (None, None) => return err!(LotteryError::WrongTicketId),
+        };
+        if ticket_owner != *winner {
+            msg!("Ticket owner: {}, Winner: {}", ticket_owner, winner);
+            return err!(LotteryError::UnauthorizedAction);
+        }
+        Ok(prize_tier)
+    }
+    /// Sets aside the referral share of `count` tickets bought by `buyer` for the referrer
+    fn accrue_referral(
+        &mut self,
+        referral: Option<&mut ReferralPDA>,
+        buyer: &Pubkey,
+        count: u32,
+    ) -> Result<()> {
+        let Some(referral) = referral else {
+            return Ok(());
+        };
+        if referral.referrer == *buyer {
+            return err!(LotteryError::SelfReferral);
+        }
+        let amount = self.ticket_price_lamports as u128 * count as u128;
+        let share = (amount * self.referral_bps as u128 / 10_000) as u64;
+        referral.accrued_lamports += share;
+        self.referral_lamports += share;
         Ok(())
     }
-
-    pub fn pick_winner(ctx: Context<PickWinner>) -> Result<()> {
-        let lottery = &mut ctx.accounts.lottery_pda;
-        let authority = &ctx.accounts.authority;
-
-        if lottery.winner_ticket_id.is_some() {
-            return err!(LotteryError::WinnerAlreadyExists);
+    fn mix_entropy(&mut self, contributor: &Pubkey, entropy: &[u8; 32]) {
+        self.entropy_accumulator =
+            hashv(&[&self.entropy_accumulator, &contributor.to_bytes(), entropy]).to_bytes();
+    }
+    /// Draws a distinct winning ticket for every prize tier, or the winning numbers of a
+    /// numbers game
+    fn draw_winners(&mut self, seed: [u8; 32]) -> Result<()> {
+        self.drawn_ts = Clock::get()?.unix_timestamp;
+        match self.game_mode {
+            GameMode::TicketDraw => {
+                let tier_count = self.prize_tier_count as usize;
+                if (self.last_ticket_id as usize) < tier_count {
+                    return err!(LotteryError::NotEnoughTicketsForTiers);
+                }
+                let ticket_ids = draw_distinct(seed, tier_count, self.last_ticket_id);
+                for (tier, ticket_id) in self.prize_tiers.iter_mut().zip(ticket_ids) {
+                    tier.winner_ticket_id = ticket_id;
+                }
+            }
+            GameMode::Numbers => {
+                let count = self.numbers_per_ticket as usize;
+                let mut numbers = draw_distinct(seed, count, self.max_number as u32);
+                numbers.sort_unstable();
+                for (winning, number) in self.winning_numbers.iter_mut().zip(numbers) {
+                    *winning = number as u8;
+                }
+                // A late draw still leaves winners as long to claim as they had to register
+                self.claim_deadline_ts = self
+                    .claim_deadline_ts
+                    .max(self.match_registration_end_ts() + MATCH_REGISTRATION_SECS);
+            }
         }
-        if authority.key() != lottery.authority {
-            return err!(LotteryError::UnauthorizedAction);
+        self.draw_seed = seed;
+        self.prize_pool_lamports = self.pot_lamports()?;
+        Ok(())
+    }
+    /// Fixes the slot whose hash seeds the draw, once `draw_after_ts` has passed
+    fn close_sales(&mut self, clock: &Clock) -> Result<()> {
+        if self.sales_closed_slot.is_some() {
+            return err!(LotteryError::SalesClosed);
         }
-        if lottery.last_ticket_id == 0 {
+        if self.last_ticket_id == 0 {
             return err!(LotteryError::NoTicketsPurchasedYet);
         }
-        if lottery.claimed {
-            return err!(LotteryError::TicketAlreadyClaimed);
+        if self.last_ticket_id < self.min_tickets {
+            return err!(LotteryError::MinTicketsNotReached);
+        }
+        if clock.unix_timestamp < self.draw_after_ts {
+            return err!(LotteryError::DrawTooEarly);
         }
 
-        msg!("Picking a winner for lottery ID: {}", lottery.id);
-        let clock = Clock::get()?;
-        let pseudo_random_seed =
-            <[u8; 8]>::try_from(&hash(&clock.unix_timestamp.to_be_bytes()).to_bytes()[..8])
-                .expect("Failed to convert hash to array");
-        let pseudo_random_number =
-            ((u64::from_le_bytes(pseudo_random_seed)) % u32::MAX as u64) as u32;
+        self.sales_closed_slot = Some(clock.slot);
+        self.entropy_slot = clock.slot;
         msg!(
-            "Pseudo-random seed: {}, Pseudo-random number: {}",
-            u64::from_le_bytes(pseudo_random_seed),
-            pseudo_random_number
+            "Sales closed for lottery ID: {} at slot {}",
+            self.id,
+            clock.slot
         );
-        // pick within existing tickets
-        lottery.winner_ticket_id = Some((pseudo_random_number % lottery.last_ticket_id) + 1);
+        Ok(())
+    }
+    /// Hands the draw to `crank_draw` once the authority's reveal window is over without a
+    /// reveal, seeded by the hash of `slot`, which nobody knows yet
+    fn forfeit_reveal(&mut self, slot: u64) -> Result<()> {
+        if slot <= self.entropy_slot + REVEAL_WINDOW_SLOTS {
+            return err!(LotteryError::RevealWindowNotExpired);
+        }
 
+        self.reveal_forfeited = true;
+        self.entropy_slot = slot;
         msg!(
-            "Winner ticket ID: {} for lottery ID: {}",
-            lottery.winner_ticket_id.unwrap(),
-            lottery.id
+            "Reveal forfeited, lottery ID: {} draws from the hash of slot {}",
+            self.id,
+            slot
         );
         Ok(())
     }
-
-    pub fn claim_prize(ctx: Context<ClaimPrize>) -> Result<()> {
-        let lottery = &mut ctx.accounts.lottery_pda;
-        let winner_ticket = &ctx.accounts.winner_ticket;
-        let winner = &ctx.accounts.winner;
-
-        let lottery_winner_ticket_id = lottery
-            .winner_ticket_id
-            .ok_or(LotteryError::WinnerNotChosenYet)?;
-        if winner_ticket.ticket_id != lottery_winner_ticket_id {
-            return err!(LotteryError::WrongTicketId);
+    /// Bounty for the `crank_draw` call that draws, SOL lotteries only
+    fn crank_bounty(&self) -> u64 {
+        if self.ticket_mint.is_some() {
+            return 0;
         }
-
-        if winner_ticket.lottery_id != lottery.id {
-            return err!(LotteryError::WrongLotteryId);
+        let max_bounty =
+            (self.sales_lamports as u128 * MAX_CRANK_BOUNTY_BPS as u128 / 10_000) as u64;
+        self.crank_bounty_lamports.min(max_bounty)
+    }
+    fn log_winners(&self) {
+        if self.game_mode == GameMode::Numbers {
+            let winning_numbers = self.winning_numbers[..self.numbers_per_ticket as usize].to_vec();
+            msg!(
+                "Winning numbers {:?} for lottery ID: {}",
+                winning_numbers,
+                self.id
+            );
+            emit!(NumbersDrawn {
+                lottery_id: self.id,
+                winning_numbers,
+                draw_seed: self.draw_seed,
+            });
+            return;
         }
-        if winner_ticket.owner != *winner.key {
+        for (index, tier) in self.prize_tiers[..self.prize_tier_count as usize]
+            .iter()
+            .enumerate()
+        {
             msg!(
-                "Ticket owner: {}, Winner: {}",
-                winner_ticket.owner,
-                winner.key()
+                "Tier {} winner ticket ID: {} for lottery ID: {}",
+                index,
+                tier.winner_ticket_id,
+                self.id
             );
-            return err!(LotteryError::UnauthorizedAction);
+            emit!(WinnerPicked {
+                lottery_id: self.id,
+                tier: index as u8,
+                winner_ticket_id: tier.winner_ticket_id,
+                prize_amount: self.tier_prize_lamports(tier),
+                draw_seed: self.draw_seed,
+            });
         }
+    }
+}
+// impl PDA for LotteryPDA {
+//     type Args = u32; // lottery_id
+//     fn seeds(args: Self::Args) -> &'static [&'static [u8]] {
+//         &[LOTTERY_SEED]
+//     }
+// }
 
-        // Transfer the prize (total lottery balance) to the winner
-        let lottery_total_balance = (lottery.ticket_price_lamports as u64)
-            .checked_mul(lottery.last_ticket_id as u64)
-            .expect("Overflow in total balance calculation");
-        // let lottery_id_bytes = lottery.id.to_le_bytes();
-        // let lottery_signer_seeds: &[&[&[u8]]] = &[&[
-        //     LOTTERY_SEED,
-        //     lottery_id_bytes.as_ref(),
-        //     &[ctx.bumps.lottery_pda],
-        // ]];
-
-        // invoke_signed(
-        //     &system_instruction::transfer(&lottery.key(), &winner.key(), lottery_total_balance),
-        //     &[
-        //         lottery.to_account_info(),
-        //         winner.to_account_info(),
-        //         ctx.accounts.system_program.to_account_info(),
-        //     ],
-        //     lottery_signer_seeds,
-        // )?;
+pub const TICKET_LEDGER_SEED: &[u8] = b"ticket_ledger";
 
-        **winner.to_account_info().try_borrow_mut_lamports()? += lottery_total_balance;
-        **lottery.to_account_info().try_borrow_mut_lamports()? -= lottery_total_balance;
+#[zero_copy]
+#[derive(Debug)]
+pub struct TicketRange {
+    pub owner: Pubkey,
+    pub first_ticket_id: u32,
+    pub count: u32,
+}
 
-        lottery.claimed = true;
-        msg!("Winner claimed for lottery ID: {}", lottery.id);
-        Ok(())
+/// Records bulk purchases as ranges of consecutive ticket IDs, so buying many tickets doesn't
+/// cost a `TicketPDA` each. Ranges are appended as tickets are sold, so they stay sorted.
+/// They follow this header in the account, which grows by a range whenever a purchase can't
+/// extend the last one: the buyer pays that range's rent, so filling the ledger up to the
+/// account size limit costs far more than it could ever block.
+#[account(zero_copy)]
+#[derive(Debug)]
+pub struct TicketLedger {
+    pub lottery: Pubkey,
+    pub range_count: u32,
+    pub version: u8,
+    /// Keeps the layout free of implicit padding, as zero-copy requires
+    pub _reserved: [u8; 3],
+}
+impl Versioned for TicketLedger {
+    const VERSION: u8 = 1;
+    fn version_mut(&mut self) -> &mut u8 {
+        &mut self.version
     }
 }
+impl TicketLedger {
+    // Zero-copy accounts are `repr(C)` without padding, so this matches the on-chain layout
+    const SIZE: usize = std::mem::size_of::<Self>();
+    const RANGE_SIZE: usize = std::mem::size_of::<TicketRange>();
 
-#[derive(Accounts)]
-pub struct InitMaster<'info> {
-    #[account(init, payer = payer, space=8+MasterPDA::SIZE, seeds = [MASTER_PDA_SEED], bump)]
-    pub master_pda: Account<'info, MasterPDA>,
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    pub system_program: Program<'info, System>, // to create accounts
-}
+    /// Account size of a ledger with room for `range_count` ranges
+    pub const fn space(range_count: usize) -> usize {
+        8 + Self::SIZE + range_count * Self::RANGE_SIZE
+    }
 
-#[derive(Accounts)]
-pub struct CreateLottery<'info> {
-    #[account(init, payer = authority, space=8+LotteryPDA::SIZE, seeds = [LOTTERY_SEED, &(master_pda.last_lottery_id+1).to_le_bytes()], bump)]
-    pub lottery_pda: Account<'info, LotteryPDA>,
-    #[account(mut,seeds=[MASTER_PDA_SEED], bump)]
-    pub master_pda: Account<'info, MasterPDA>,
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    pub system_program: Program<'info, System>, // to create accounts
-}
+    /// Splits a ledger's account data into its header and the ranges recorded so far
+    pub fn split(data: &[u8]) -> Result<(&Self, &[TicketRange])> {
+        let (header, ranges) = data
+            .get(8..)
+            .filter(|body| body.len() >= Self::SIZE)
+            .ok_or(ErrorCode::AccountDidNotDeserialize)?
+            .split_at(Self::SIZE);
+        let header: &Self = bytemuck::from_bytes(header);
+        let ranges = ranges
+            .get(..header.range_count as usize * Self::RANGE_SIZE)
+            .ok_or(ErrorCode::AccountDidNotDeserialize)?;
+        Ok((header, bytemuck::cast_slice(ranges)))
+    }
 
-#[derive(Accounts)]
-pub struct BuyTicket<'info> {
-    #[account(init, payer = buyer, space=8+TicketPDA::SIZE, seeds = [TICKET_SEED, &lottery_pda.key().to_bytes(), &lottery_pda.next_ticket_id().to_le_bytes()], bump)]
-    pub ticket_pda: Account<'info, TicketPDA>,
-    #[account(mut, seeds = [LOTTERY_SEED, &lottery_pda.id.to_le_bytes()], bump)]
-    pub lottery_pda: Account<'info, LotteryPDA>,
-    #[account(mut)]
-    pub buyer: Signer<'info>,
-    pub system_program: Program<'info, System>, // to create accounts
-}
+    /// `split` for updates, the ranges past `range_count` that the account has room for included
+    fn split_mut(data: &mut [u8]) -> Result<(&mut Self, &mut [TicketRange])> {
+        let (header, ranges) = data
+            .get_mut(8..)
+            .filter(|body| body.len() >= Self::SIZE)
+            .ok_or(ErrorCode::AccountDidNotDeserialize)?
+            .split_at_mut(Self::SIZE);
+        let capacity = ranges.len() / Self::RANGE_SIZE * Self::RANGE_SIZE;
+        Ok((
+            bytemuck::from_bytes_mut(header),
+            bytemuck::cast_slice_mut(&mut ranges[..capacity]),
+        ))
+    }
 
-#[derive(Accounts)]
-pub struct PickWinner<'info> {
-    #[account(mut, seeds = [LOTTERY_SEED, &lottery_pda.id.to_le_bytes()], bump)]
-    pub lottery_pda: Account<'info, LotteryPDA>,
-    #[account(mut)]
-    pub authority: Signer<'info>,
+    pub fn ranges(data: &[u8]) -> Result<&[TicketRange]> {
+        Ok(Self::split(data)?.1)
+    }
+
+    /// The recorded ranges of a ledger's account data, for refunds
+    fn ranges_mut(data: &mut [u8]) -> Result<&mut [TicketRange]> {
+        let (header, ranges) = Self::split_mut(data)?;
+        let range_count = header.range_count as usize;
+        Ok(&mut ranges[..range_count])
+    }
+
+    /// Records `count` tickets from `first_ticket_id` for `owner` in the ledger at `ledger`,
+    /// growing it at `payer`'s expense when they need a new range
+    fn record<'info>(
+        ledger: &AccountInfo<'info>,
+        payer: &AccountInfo<'info>,
+        system_program: &Program<'info, System>,
+        owner: Pubkey,
+        first_ticket_id: u32,
+        count: u32,
+    ) -> Result<()> {
+        let range_count = {
+            let mut data = ledger.try_borrow_mut_data()?;
+            let (header, ranges) = Self::split_mut(&mut data)?;
+            let range_count = header.range_count as usize;
+            // Extend the last range when the same buyer buys again and nobody bought in between
+            if let Some(last) = ranges[..range_count].last_mut() {
+                if last.owner == owner && last.first_ticket_id + last.count == first_ticket_id {
+                    last.count += count;
+                    return Ok(());
+                }
+            }
+            range_count
+        };
+        grow_account(ledger, payer, system_program, Self::space(range_count + 1))?;
+
+        let mut data = ledger.try_borrow_mut_data()?;
+        let (header, ranges) = Self::split_mut(&mut data)?;
+        ranges[range_count] = TicketRange {
+            owner,
+            first_ticket_id,
+            count,
+        };
+        header.range_count += 1;
+        Ok(())
+    }
+
+    pub fn owner_of(ranges: &[TicketRange], ticket_id: u32) -> Option<Pubkey> {
+        let index =
+            ranges.partition_point(|range| range.first_ticket_id + range.count <= ticket_id);
+        ranges
+            .get(index)
+            .filter(|range| range.first_ticket_id <= ticket_id)
+            .map(|range| range.owner)
+    }
 }
 
-#[derive(Accounts)]
-pub struct ClaimPrize<'info> {
-    #[account(mut, seeds = [LOTTERY_SEED, &lottery_pda.id.to_le_bytes()], bump)]
-    pub lottery_pda: Account<'info, LotteryPDA>,
-    #[account(mut, seeds = [TICKET_SEED, &lottery_pda.key().to_bytes(), &lottery_pda.winner_ticket_id.unwrap().to_le_bytes()], bump)]
-    pub winner_ticket: Account<'info, TicketPDA>,
-    #[account(mut)]
-    pub winner: Signer<'info>,
-    pub system_program: Program<'info, System>, // to transfer SOL back to winner
+/// Upper bound for `LotteryPDA::referral_bps` (20%)
+pub const MAX_REFERRAL_BPS: u16 = 2_000;
+
+pub const REFERRAL_SEED: &[u8] = b"referral";
+/// Referral shares accrued to one referrer in one lottery, paid out by `claim_referral_rewards`
+#[account]
+#[derive(Debug, InitSpace)]
+pub struct ReferralPDA {
+    pub lottery_id: u32,
+    pub referrer: Pubkey,
+    pub accrued_lamports: u64,
+    pub version: u8,
+}
+impl Versioned for ReferralPDA {
+    const VERSION: u8 = 1;
+    fn version_mut(&mut self) -> &mut u8 {
+        &mut self.version
+    }
 }
 
-pub const MASTER_PDA_SEED: &[u8] = b"master";
+pub const PLAYER_STATS_SEED: &[u8] = b"player_stats";
+/// A player's totals across all lotteries, for leaderboards. Created by the player's first
+/// ticket or prize, updated by every purchase and claim, and closed with `close_player_stats`.
+/// Gifted tickets count for their recipient, but their price counts for nobody: the payer's
+/// stats aren't among the accounts of a gift.
 #[account]
-#[derive(Debug)]
-pub struct MasterPDA {
-    pub last_lottery_id: u32,
+#[derive(Debug, InitSpace)]
+pub struct PlayerStats {
+    pub player: Pubkey,
+    pub tickets_bought: u64,
+    /// SOL the player paid for its own tickets, tickets paid in tokens or gifted add nothing
+    pub lamports_spent: u64,
+    pub lotteries_entered: u32,
+    pub wins: u32,
+    /// SOL prizes received, after the protocol fee. Token and raffle prizes count as wins only.
+    pub lamports_won: u64,
+    pub version: u8,
 }
-impl MasterPDA {
-    const SIZE: usize = std::mem::size_of::<Self>();
+impl Versioned for PlayerStats {
+    const VERSION: u8 = 1;
+    fn version_mut(&mut self) -> &mut u8 {
+        &mut self.version
+    }
+}
+impl PlayerStats {
+    /// `first_entry` when these are the player's first tickets in their lottery
+    fn record_purchase(&mut self, player: Pubkey, count: u32, lamports: u64, first_entry: bool) {
+        self.init_if_new(player);
+        self.tickets_bought = self.tickets_bought.saturating_add(count as u64);
+        self.lamports_spent = self.lamports_spent.saturating_add(lamports);
+        if first_entry {
+            self.lotteries_entered = self.lotteries_entered.saturating_add(1);
+        }
+    }
+    fn record_win(&mut self, player: Pubkey, lamports: u64) {
+        self.init_if_new(player);
+        self.wins = self.wins.saturating_add(1);
+        self.lamports_won = self.lamports_won.saturating_add(lamports);
+    }
+    /// Created with `init_if_needed`
+    fn init_if_new(&mut self, player: Pubkey) {
+        self.player = player;
+        self.version = PlayerStats::VERSION;
+    }
 }
-// impl PDA for MasterPDA {
-//     type Args = ();
-//     fn seeds(_args: ()) -> &'static [&'static [u8]] {
-//         &[MASTER_PDA_SEED]
-//     }
-// }
 
-pub const LOTTERY_SEED: &[u8] = b"lottery";
+pub const WALLET_TICKETS_SEED: &[u8] = b"wallet_tickets";
+/// Recipients `buy_tickets_for` takes in one call. Each one costs 98 bytes (two accounts and a
+/// pubkey), so a full batch with entropy still fits a 1232-byte legacy transaction.
+pub const MAX_GIFT_RECIPIENTS: usize = 8;
+/// Counts the tickets one wallet bought in one lottery, for `max_tickets_per_wallet`
 #[account]
-#[derive(Debug)]
-pub struct LotteryPDA {
-    pub id: u32,
-    pub authority: Pubkey,
-    pub ticket_price_lamports: u64,
-    pub last_ticket_id: u32,
-    pub winner_ticket_id: Option<u32>,
-    pub claimed: bool,
+#[derive(Debug, InitSpace)]
+pub struct WalletTicketsPDA {
+    pub tickets_bought: u32,
+    pub version: u8,
+    /// Set once the wallet's entry into the lottery is counted in its `PlayerStats`
+    pub entered: bool,
 }
-impl LotteryPDA {
-    const SIZE: usize = std::mem::size_of::<Self>();
-    // pub fn seeds<'a>(lottery_id: u32) -> Vec<[u8]> {
-    //     [LOTTERY_SEED, &lottery_id.to_le_bytes()].to_vec()
-    // }
-    pub fn next_ticket_id(&self) -> u32 {
-        self.last_ticket_id + 1
+impl Versioned for WalletTicketsPDA {
+    const VERSION: u8 = 1;
+    fn version_mut(&mut self) -> &mut u8 {
+        &mut self.version
+    }
+}
+impl WalletTicketsPDA {
+    /// Marks the wallet as entered, returns whether this is its first entry
+    fn enter(&mut self) -> bool {
+        let first_entry = !self.entered;
+        self.entered = true;
+        first_entry
     }
 }
-// impl PDA for LotteryPDA {
-//     type Args = u32; // lottery_id
-//     fn seeds(args: Self::Args) -> &'static [&'static [u8]] {
-//         &[LOTTERY_SEED]
-//     }
-// }
 
 pub const TICKET_SEED: &[u8] = b"ticket";
+/// Seeds the mint of an NFT ticket, with the lottery PDA and the ticket ID
+pub const TICKET_NFT_SEED: &[u8] = b"ticket_nft";
+pub const TICKET_NFT_SYMBOL: &str = "TICKET";
 #[account]
-#[derive(Debug)]
+#[derive(Debug, InitSpace)]
 pub struct TicketPDA {
     pub lottery_id: u32,
     pub ticket_id: u32,
     pub owner: Pubkey,
+    pub version: u8,
+    /// Picks of a numbers game in ascending order, zero otherwise
+    pub numbers: [u8; MAX_NUMBER_PICKS],
+    /// Set by `register_match`
+    pub match_registered: bool,
+    pub match_claimed: bool,
+}
+impl Versioned for TicketPDA {
+    const VERSION: u8 = 2;
+    fn version_mut(&mut self) -> &mut u8 {
+        &mut self.version
+    }
 }
 impl TicketPDA {
-    const SIZE: usize = std::mem::size_of::<Self>();
-    pub fn seeds<'a>(lottery_id: u32, ticket_id: u32) -> Vec<u8> {
-        [
-            TICKET_SEED.as_ref(),
-            &lottery_id.to_le_bytes(),
-            &ticket_id.to_le_bytes(),
-        ]
-        .concat()
+    /// Tickets are seeded by the lottery PDA's key, not the lottery ID
+    pub fn pda(lottery: &Pubkey, ticket_id: u32) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[TICKET_SEED, lottery.as_ref(), &ticket_id.to_le_bytes()],
+            &crate::ID,
+        )
+    }
+}
+pub const LISTING_SEED: &[u8] = b"listing";
+/// A ticket offered for sale by its owner, see `list_ticket`
+#[account]
+#[derive(Debug, InitSpace)]
+pub struct ListingPDA {
+    pub lottery_id: u32,
+    pub ticket_id: u32,
+    pub seller: Pubkey,
+    pub price_lamports: u64,
+    pub version: u8,
+}
+impl Versioned for ListingPDA {
+    const VERSION: u8 = 1;
+    fn version_mut(&mut self) -> &mut u8 {
+        &mut self.version
     }
 }
+
 // impl PDA for TicketPDA {
 //     type Args = TicketPdaSeedArgs;
 //     fn seeds(args: Self::Args) -> &'static [&'static [u8]] {
@@ -279,17 +3878,370 @@ impl TicketPDA {
 //     pub ticket_id: u32,
 // }
 
-// #[cfg(test)]
-// pub mod convenience {
-//     use super::*;
-//     use solana_pubkey::Pubkey;
+/// Rejects Token-2022 mints with a transfer fee, which may be raised at any time: the program
+/// credits the amounts it sends, and the receiving vault would get less
+fn check_no_transfer_fee(mint: &InterfaceAccount<Mint>) -> Result<()> {
+    let mint_info = mint.to_account_info();
+    if *mint_info.owner != Token2022::id() {
+        return Ok(());
+    }
+    let data = mint_info.try_borrow_data()?;
+    let has_transfer_fee = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?
+        .get_extension_types()?
+        .contains(&ExtensionType::TransferFeeConfig);
+    if has_transfer_fee {
+        return err!(LotteryError::TransferFeeMint);
+    }
+    Ok(())
+}
 
-//     impl MasterPDA {
-//         pub fn pda() -> (Pubkey, u8) {
-//             Pubkey::find_program_address(&[b"master"], &crate::ID)
-//         }
-//     }
-// }
+/// Transfers tokens with `transfer_checked`, so both SPL Token and Token-2022 mints work.
+/// If `from` is owned by a PDA, its signer seeds must be provided.
+fn transfer_tokens<'info>(
+    from: &InterfaceAccount<'info, TokenAccount>,
+    to: &InterfaceAccount<'info, TokenAccount>,
+    amount: u64,
+    mint: &InterfaceAccount<'info, Mint>,
+    authority: &AccountInfo<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+    owning_pda_seeds: Option<&[&[u8]]>,
+) -> Result<()> {
+    let transfer_accounts = TransferChecked {
+        from: from.to_account_info(),
+        mint: mint.to_account_info(),
+        to: to.to_account_info(),
+        authority: authority.clone(),
+    };
+    let signers_seeds = owning_pda_seeds.map(|seeds| [seeds]);
+    transfer_checked(
+        match signers_seeds.as_ref() {
+            Some(seeds_arr) => CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                transfer_accounts,
+                seeds_arr,
+            ),
+            None => CpiContext::new(token_program.to_account_info(), transfer_accounts),
+        },
+        amount,
+        mint.decimals,
+    )
+}
+
+/// Mints the single token of an NFT ticket to `to`, with the ticket's token metadata, then
+/// drops the mint authority so the supply stays at one. The mint must already be initialized
+/// with the lottery as its mint authority and a metadata pointer to itself; `payer` funds the
+/// rent the metadata adds.
+#[allow(clippy::too_many_arguments)]
+fn mint_ticket_nft<'info>(
+    lottery: &Account<'info, LotteryPDA>,
+    lottery_signer_seeds: &[&[u8]],
+    ticket_id: u32,
+    ticket_nft_mint: &InterfaceAccount<'info, Mint>,
+    to: &InterfaceAccount<'info, TokenAccount>,
+    payer: &AccountInfo<'info>,
+    token_program: &Program<'info, Token2022>,
+    system_program: &Program<'info, System>,
+) -> Result<()> {
+    let lottery_info = lottery.to_account_info();
+    let mint_info = ticket_nft_mint.to_account_info();
+    let signer_seeds = &[lottery_signer_seeds];
+    let metadata = TokenMetadata {
+        name: format!("Lottery {} ticket #{}", lottery.id, ticket_id),
+        symbol: TICKET_NFT_SYMBOL.to_string(),
+        additional_metadata: vec![
+            ("lottery_id".to_string(), lottery.id.to_string()),
+            ("ticket_id".to_string(), ticket_id.to_string()),
+        ],
+        ..Default::default()
+    };
+    // Token-2022 grows the mint for the metadata, but the rent for it must already be there
+    fund_rent(
+        &mint_info,
+        payer,
+        system_program,
+        mint_info.data_len() + metadata.tlv_size_of()?,
+    )?;
+
+    token_metadata_initialize(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            TokenMetadataInitialize {
+                program_id: token_program.to_account_info(),
+                metadata: mint_info.clone(),
+                update_authority: lottery_info.clone(),
+                mint_authority: lottery_info.clone(),
+                mint: mint_info.clone(),
+            },
+            signer_seeds,
+        ),
+        metadata.name,
+        metadata.symbol,
+        metadata.uri,
+    )?;
+    for (key, value) in metadata.additional_metadata {
+        token_metadata_update_field(
+            CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                TokenMetadataUpdateField {
+                    program_id: token_program.to_account_info(),
+                    metadata: mint_info.clone(),
+                    update_authority: lottery_info.clone(),
+                },
+                signer_seeds,
+            ),
+            Field::Key(key),
+            value,
+        )?;
+    }
+
+    mint_to(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            MintTo {
+                mint: mint_info.clone(),
+                to: to.to_account_info(),
+                authority: lottery_info.clone(),
+            },
+            signer_seeds,
+        ),
+        1,
+    )?;
+    set_authority(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            SetAuthority {
+                current_authority: lottery_info,
+                account_or_mint: mint_info,
+            },
+            signer_seeds,
+        ),
+        AuthorityType::MintTokens,
+        None,
+    )
+}
+
+/// Grows `account` to `new_len` bytes, zeroing the new ones, with `payer` paying the extra rent:
+/// lamports the account holds beyond its rent, like a lottery's pot, are never used for it.
+/// Accounts that are already large enough are left as they are.
+fn grow_account<'info>(
+    account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+    new_len: usize,
+) -> Result<()> {
+    if account.data_len() >= new_len {
+        return Ok(());
+    }
+    let rent = Rent::get()?;
+    let extra_rent = rent
+        .minimum_balance(new_len)
+        .saturating_sub(rent.minimum_balance(account.data_len()));
+    pay_lamports(payer, account, system_program, extra_rent)?;
+    account.realloc(new_len, true)?;
+    Ok(())
+}
+
+/// Has `payer` top up `account`'s lamports to the rent-exempt minimum for `len` bytes
+fn fund_rent<'info>(
+    account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+    len: usize,
+) -> Result<()> {
+    let missing_rent = Rent::get()?
+        .minimum_balance(len)
+        .saturating_sub(account.lamports());
+    pay_lamports(payer, account, system_program, missing_rent)
+}
+
+fn pay_lamports<'info>(
+    payer: &AccountInfo<'info>,
+    account: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+    lamports: u64,
+) -> Result<()> {
+    if lamports > 0 {
+        invoke(
+            &system_instruction::transfer(payer.key, account.key, lamports),
+            &[
+                payer.clone(),
+                account.clone(),
+                system_program.to_account_info(),
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+/// Creates an account of this program at a PDA, also when someone already sent lamports to it
+fn create_pda_account<'info>(
+    account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+    len: usize,
+    signer_seeds: &[&[u8]],
+) -> Result<()> {
+    fund_rent(account, payer, system_program, len)?;
+    invoke_signed(
+        &system_instruction::allocate(account.key, len as u64),
+        &[account.clone(), system_program.to_account_info()],
+        &[signer_seeds],
+    )?;
+    invoke_signed(
+        &system_instruction::assign(account.key, &crate::ID),
+        &[account.clone(), system_program.to_account_info()],
+        &[signer_seeds],
+    )?;
+    Ok(())
+}
+
+/// Loads the program account at the PDA of `seeds` from the remaining accounts, creating it
+/// when it doesn't exist yet
+fn load_or_create_pda<'info, T>(
+    account: &'info AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+    seeds: &[&[u8]],
+) -> Result<Account<'info, T>>
+where
+    T: AccountSerialize + AccountDeserialize + Owner + Clone + Space,
+{
+    let (key, bump) = Pubkey::find_program_address(seeds, &crate::ID);
+    if account.key() != key {
+        return err!(LotteryError::InvalidRecipients);
+    }
+    if !account.data_is_empty() {
+        return Account::try_from(account);
+    }
+    let bump = [bump];
+    let mut signer_seeds = seeds.to_vec();
+    signer_seeds.push(&bump);
+    create_pda_account(
+        account,
+        payer,
+        system_program,
+        8 + T::INIT_SPACE,
+        &signer_seeds,
+    )?;
+    Account::try_from_unchecked(account)
+}
+
+/// Grows a Borsh account to the current size of `T` and bumps its version, returning it
+fn migrate_versioned<'info, T>(
+    account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+) -> Result<u8>
+where
+    T: AccountSerialize + AccountDeserialize + Space + Versioned,
+{
+    grow_account(account, payer, system_program, 8 + T::INIT_SPACE)?;
+    let mut data = T::try_deserialize(&mut &account.try_borrow_data()?[..])?;
+    let from_version = *data.version_mut();
+    if from_version >= T::VERSION {
+        return err!(LotteryError::AlreadyMigrated);
+    }
+    data.migrate(from_version)?;
+    *data.version_mut() = T::VERSION;
+    data.try_serialize(&mut &mut account.try_borrow_mut_data()?[..])?;
+    Ok(T::VERSION)
+}
+
+/// Rewrites a lottery in the `LegacyLotteryPDAv0` layout as the current one
+fn migrate_legacy_lottery<'info>(
+    account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+) -> Result<u8> {
+    let lottery = LegacyLotteryPDAv0::upgrade(&account.try_borrow_data()?)?;
+    grow_account(account, payer, system_program, 8 + LotteryPDA::INIT_SPACE)?;
+    lottery.try_serialize(&mut &mut account.try_borrow_mut_data()?[..])?;
+    Ok(LotteryPDA::VERSION)
+}
+
+/// Takes `amount` out of a program account, which must stay rent-exempt: lamports sent to it
+/// beyond what it accounts for are never paid out, but neither is its rent
+fn withdraw_lamports(account: &AccountInfo, amount: u64) -> Result<()> {
+    let rent_exempt_minimum = Rent::get()?.minimum_balance(account.data_len());
+    let balance = account
+        .lamports()
+        .checked_sub(amount)
+        .ok_or(LotteryError::InsufficientPot)?;
+    if balance < rent_exempt_minimum {
+        return err!(LotteryError::InsufficientPot);
+    }
+    **account.try_borrow_mut_lamports()? = balance;
+    Ok(())
+}
+
+/// Closes a program account, the way Anchor's `close` constraint does, for accounts that are
+/// only checked in the handler: its lamports go to `destination` and it's handed back to the
+/// system program
+fn close_program_account(account: &AccountInfo, destination: &AccountInfo) -> Result<()> {
+    let lamports = account.lamports();
+    **destination.try_borrow_mut_lamports()? = destination
+        .lamports()
+        .checked_add(lamports)
+        .ok_or(LotteryError::ArithmeticOverflow)?;
+    **account.try_borrow_mut_lamports()? = 0;
+    account.assign(&System::id());
+    account.realloc(0, false)?;
+    Ok(())
+}
+
+/// Master PDAs from before `migrate_master` only hold `last_lottery_id`
+fn has_master_admin(master_info: &AccountInfo) -> bool {
+    master_info.data_len() >= 8 + 4 + 32
+}
+
+/// Draws `count` distinct values from 1 to `range`, in draw order
+fn draw_distinct(seed: [u8; 32], count: usize, range: u32) -> Vec<u32> {
+    // Drawn values so far, kept sorted
+    let mut drawn: Vec<u32> = Vec::with_capacity(count);
+    let mut picks = Vec::with_capacity(count);
+    for index in 0..count {
+        let pick_seed = hashv(&[&seed, &[index as u8]]).to_bytes();
+        let random_number = u64::from_le_bytes(
+            <[u8; 8]>::try_from(&pick_seed[..8]).expect("Failed to convert hash to array"),
+        );
+        // pick within the values not drawn yet, then map back to a value
+        let remaining = (range as usize - index) as u64;
+        let mut value = (random_number % remaining) as u32 + 1;
+        for &won in &drawn {
+            if won <= value {
+                value += 1;
+            }
+        }
+        picks.push(value);
+        drawn.insert(drawn.partition_point(|&won| won < value), value);
+    }
+    picks
+}
+
+/// Looks up the hash of `slot` in the raw SlotHashes sysvar data, which is too large to deserialize
+/// on-chain. The data is a u64 length followed by `(slot, hash)` entries, newest slot first.
+fn slot_hash_at(slot_hashes: &AccountInfo, slot: u64) -> Result<[u8; 32]> {
+    const ENTRY_LEN: usize = 8 + 32;
+    let data = slot_hashes.try_borrow_data()?;
+    let len = data
+        .get(..8)
+        .map(|len| u64::from_le_bytes(len.try_into().unwrap()) as usize)
+        .unwrap_or_default()
+        .min(data.len().saturating_sub(8) / ENTRY_LEN);
+
+    let (mut low, mut high) = (0, len);
+    while low < high {
+        let mid = (low + high) / 2;
+        let entry = &data[8 + mid * ENTRY_LEN..8 + (mid + 1) * ENTRY_LEN];
+        let entry_slot = u64::from_le_bytes(entry[..8].try_into().unwrap());
+        match entry_slot.cmp(&slot) {
+            std::cmp::Ordering::Equal => return Ok(entry[8..].try_into().unwrap()),
+            std::cmp::Ordering::Greater => low = mid + 1,
+            std::cmp::Ordering::Less => high = mid,
+        }
+    }
+    err!(LotteryError::SlotHashNotAvailable)
+}
 
 // pub trait PDA {
 //     type Args;
@@ -297,6 +4249,67 @@ impl TicketPDA {
 // }
 
 use anchor_lang::error_code;
+#[event]
+pub struct LotteryCreated {
+    pub lottery_id: u32,
+    pub lottery: Pubkey,
+    pub authority: Pubkey,
+    pub ticket_price_lamports: u64,
+    pub prize_shares_bps: Vec<u16>,
+    pub fee_bps: u16,
+    pub sale_start_ts: i64,
+    pub sale_end_ts: i64,
+    pub draw_after_ts: i64,
+    pub claim_deadline_ts: i64,
+    pub min_tickets: u32,
+    pub max_tickets: u32,
+    pub max_tickets_per_wallet: u32,
+}
+
+/// Emitted once per purchase, bulk purchases cover `count` consecutive ticket IDs.
+/// `buyer` owns the tickets, gifted ones were paid by someone else.
+#[event]
+pub struct TicketBought {
+    pub lottery_id: u32,
+    pub buyer: Pubkey,
+    pub first_ticket_id: u32,
+    pub count: u32,
+    /// In lamports, or in base units of `ticket_mint`
+    pub amount_paid: u64,
+    pub ticket_mint: Option<Pubkey>,
+}
+
+/// Emitted when the winning numbers of a numbers game are drawn
+#[event]
+pub struct NumbersDrawn {
+    pub lottery_id: u32,
+    pub winning_numbers: Vec<u8>,
+    pub draw_seed: [u8; 32],
+}
+
+/// Emitted for every prize tier when the winners are drawn
+#[event]
+pub struct WinnerPicked {
+    pub lottery_id: u32,
+    pub tier: u8,
+    pub winner_ticket_id: u32,
+    /// The tier's share of the pot, before the protocol fee
+    pub prize_amount: u64,
+    pub draw_seed: [u8; 32],
+}
+
+#[event]
+pub struct PrizeClaimed {
+    pub lottery_id: u32,
+    pub tier: u8,
+    pub ticket_id: u32,
+    pub winner: Pubkey,
+    /// Paid to the winner, after the protocol fee
+    pub prize_amount: u64,
+    pub fee_amount: u64,
+    pub ticket_mint: Option<Pubkey>,
+}
+
 #[error_code]
 pub enum LotteryError {
     #[msg("Winner already exists")]
@@ -315,4 +4328,146 @@ pub enum LotteryError {
     WinnerNotChosenYet,
     #[msg("No tickets purchased yet")]
     NoTicketsPurchasedYet,
+    #[msg("Randomness not committed yet")]
+    RandomnessNotCommitted,
+    #[msg("Randomness already committed")]
+    RandomnessAlreadyCommitted,
+    #[msg("Ticket sales are closed")]
+    SalesClosed,
+    #[msg("Ticket sales are not closed yet")]
+    SalesNotClosed,
+    #[msg("Revealed secret does not match the commitment")]
+    InvalidReveal,
+    #[msg("Reveal window has expired")]
+    RevealWindowExpired,
+    #[msg("Reveal window has not expired yet")]
+    RevealWindowNotExpired,
+    #[msg("Slot hash for the draw is not available")]
+    SlotHashNotAvailable,
+    #[msg("Sale start must be before sale end, sale end no later than the draw, and the claim deadline after the draw")]
+    InvalidSchedule,
+    #[msg("Ticket sales are not open yet")]
+    SalesNotOpen,
+    #[msg("Too early to draw the winner")]
+    DrawTooEarly,
+    #[msg("Account is already migrated")]
+    AlreadyMigrated,
+    #[msg("Fee exceeds the maximum")]
+    FeeTooHigh,
+    #[msg("Insufficient funds in the treasury")]
+    InsufficientTreasuryBalance,
+    #[msg("Prize tiers must be 1 to 5 non-zero shares adding up to 10000 bps")]
+    InvalidPrizeTiers,
+    #[msg("Prize tier does not exist")]
+    InvalidPrizeTier,
+    #[msg("Fewer tickets sold than prize tiers")]
+    NotEnoughTicketsForTiers,
+    #[msg("Ticket count must be greater than zero")]
+    InvalidTicketCount,
+    #[msg("Ticket ledger is full")]
+    TicketLedgerFull,
+    #[msg("Lottery is paid in a different currency")]
+    WrongTicketMint,
+    #[msg("Ticket mint is already set")]
+    TicketMintAlreadySet,
+    #[msg("Tickets were already sold")]
+    TicketsAlreadySold,
+    #[msg("Minimum tickets must be at least the number of prize tiers")]
+    InvalidMinTickets,
+    #[msg("Not enough tickets sold for the draw")]
+    MinTicketsNotReached,
+    #[msg("Lottery is cancelled")]
+    LotteryCancelled,
+    #[msg("Lottery is not cancelled")]
+    LotteryNotCancelled,
+    #[msg("Lottery can only be cancelled by its authority, or by anyone once the sale ended below the minimum")]
+    CannotCancelLottery,
+    #[msg("Ticket range does not exist")]
+    InvalidTicketRange,
+    #[msg("Ticket was already refunded")]
+    TicketAlreadyRefunded,
+    #[msg("Maximum tickets must be zero or at least the minimum tickets")]
+    InvalidTicketCaps,
+    #[msg("Lottery is sold out")]
+    SoldOut,
+    #[msg("Ticket limit per wallet reached")]
+    WalletTicketLimitReached,
+    #[msg("Winning ticket has not claimed its prize yet")]
+    PrizeNotClaimed,
+    #[msg("Lottery still has unclaimed prizes or unrefunded tickets")]
+    LotteryNotSettled,
+    #[msg("Lottery has expired")]
+    LotteryExpired,
+    #[msg("Claim deadline has passed")]
+    ClaimDeadlinePassed,
+    #[msg("Claim deadline has not passed yet")]
+    ClaimDeadlineNotPassed,
+    #[msg("Unclaimed prizes can only roll over into a later, still selling lottery of the same authority and currency")]
+    InvalidRolloverTarget,
+    #[msg("Not enough lamports in the jackpot")]
+    InsufficientJackpot,
+    #[msg("Program is paused")]
+    ProgramPaused,
+    #[msg("Account is not a lottery program account")]
+    UnknownAccount,
+    #[msg("Master PDA has no admin yet, migrate it with migrate_master")]
+    MasterNotMigrated,
+    #[msg("Tickets can only be listed and bought from listings before the draw")]
+    ListingClosed,
+    #[msg("Ticket changed hands since it was listed")]
+    ListingStale,
+    #[msg("Listing price differs from the expected price")]
+    ListingPriceChanged,
+    #[msg("NFT lotteries only sell and settle NFT tickets, other lotteries none")]
+    WrongTicketKind,
+    #[msg("Referral share is above the maximum")]
+    InvalidReferralShare,
+    #[msg("Lottery has no referral rewards")]
+    ReferralsDisabled,
+    #[msg("Buyers can't refer themselves")]
+    SelfReferral,
+    #[msg("Carry-over share is above the maximum")]
+    InvalidCarryOver,
+    #[msg("The series' current round must be passed")]
+    SeriesRoundMissing,
+    #[msg("Series can't pay back the rent of its next round, fund it first")]
+    SeriesUnderfunded,
+    #[msg("Arithmetic overflow")]
+    ArithmeticOverflow,
+    #[msg("Lottery doesn't hold enough for this payout")]
+    InsufficientPot,
+    #[msg("Not available in this lottery's game mode")]
+    WrongGameMode,
+    #[msg("Numbers games need 1 to MAX_NUMBER_PICKS picks out of at most MAX_NUMBER, and no more tiers than picks")]
+    InvalidNumbersGame,
+    #[msg("Picks must be the game's count of distinct numbers within its range")]
+    InvalidNumbers,
+    #[msg("Ticket doesn't match enough numbers for a prize")]
+    NoMatchPrize,
+    #[msg("Matching tickets can only be registered for a while after the draw")]
+    MatchRegistrationClosed,
+    #[msg("Prizes can be claimed once match registration has closed")]
+    MatchRegistrationOpen,
+    #[msg("Ticket is already registered")]
+    MatchAlreadyRegistered,
+    #[msg("Ticket was not registered with register_match")]
+    MatchNotRegistered,
+    #[msg("Asset raffles need a single prize tier, a prize amount and SOL tickets")]
+    InvalidRafflePrize,
+    #[msg("Asset raffles pay their deposited prize, other lotteries their pot")]
+    WrongPrizeKind,
+    #[msg("Mint is not the lottery's prize mint")]
+    WrongPrizeMint,
+    #[msg("Raffle prize is already deposited")]
+    PrizeAlreadyDeposited,
+    #[msg("Raffle prize must be deposited before tickets are sold")]
+    PrizeNotDeposited,
+    #[msg("Proceeds are already withdrawn")]
+    ProceedsAlreadyWithdrawn,
+    #[msg("Recipients must be 1 to MAX_GIFT_RECIPIENTS, each with its wallet tickets and stats accounts")]
+    InvalidRecipients,
+    #[msg("Protocol fee is above the series' maximum")]
+    SeriesFeeTooHigh,
+    #[msg("Mints with a transfer fee are not supported")]
+    TransferFeeMint,
 }
@@ -0,0 +1,232 @@
+#![cfg(test)]
+
+mod common;
+
+use anchor_lang::{
+    prelude::*,
+    solana_program::{bpf_loader_upgradeable, hash::Hash, slot_hashes::SlotHashes},
+    Discriminator,
+};
+use common::*;
+use litesvm::LiteSVM;
+use lottery::{LotteryStatus, MasterPDA, TicketPDA, TreasuryPDA, Versioned};
+use lottery_client::{
+    instructions::{self, WinningTicket},
+    pda,
+};
+use solana_signer::Signer;
+
+/// Stores `data` at `address`, owned by `owner` and funded for its rent
+fn write_account(
+    svm: &mut LiteSVM,
+    address: Pubkey,
+    owner: Pubkey,
+    executable: bool,
+    data: Vec<u8>,
+) {
+    svm.airdrop(&address, svm.minimum_balance_for_rent_exemption(data.len()))
+        .unwrap();
+    let mut account = svm.get_account(&address).unwrap();
+    account.owner = owner;
+    account.executable = executable;
+    account.data = data;
+    svm.set_account(address, account).unwrap();
+}
+
+/// Deploys the program with the upgradeable loader, as on a real cluster, so that it has an
+/// upgrade authority
+fn deploy_upgradeable(svm: &mut LiteSVM, upgrade_authority: &Pubkey) {
+    let program_data =
+        Pubkey::find_program_address(&[lottery::ID.as_ref()], &bpf_loader_upgradeable::ID).0;
+    // `UpgradeableLoaderState::ProgramData` as bincode: the variant, the deployment slot and the
+    // upgrade authority, followed by the program
+    let mut data = vec![3, 0, 0, 0];
+    data.extend_from_slice(&0u64.to_le_bytes());
+    data.push(1);
+    data.extend_from_slice(upgrade_authority.as_ref());
+    data.extend_from_slice(include_bytes!("../../../target/deploy/lottery.so"));
+    write_account(svm, program_data, bpf_loader_upgradeable::ID, false, data);
+    // `UpgradeableLoaderState::Program`, pointing at its data
+    let mut data = vec![2, 0, 0, 0];
+    data.extend_from_slice(program_data.as_ref());
+    write_account(svm, lottery::ID, bpf_loader_upgradeable::ID, true, data);
+}
+
+#[test]
+fn test_pause_stops_lotteries_sales_and_cranks_but_not_reveals() {
+    let (mut svm, lottery_id, admin, buyers) = setup_lottery(lottery_params(), 2);
+
+    let instruction = instructions::set_paused(&buyers[0].pubkey(), true);
+    assert_fails_with(&mut svm, instruction, &buyers[0], "UnauthorizedAction");
+    send(
+        &mut svm,
+        instructions::set_paused(&admin.pubkey(), true),
+        &admin,
+    );
+
+    let instruction = instructions::create_lottery(2, &admin.pubkey(), lottery_params());
+    assert_fails_with(&mut svm, instruction, &admin, "ProgramPaused");
+
+    let instruction =
+        instructions::buy_ticket(lottery_id, 3, &buyers[0].pubkey(), None, None, vec![]);
+    assert_fails_with(&mut svm, instruction, &buyers[0], "ProgramPaused");
+    assert_eq!(load_lottery(&svm, lottery_id).last_ticket_id, 2);
+
+    set_time(&mut svm, DRAW_TS, DRAW_SLOT);
+    svm.set_sysvar(&SlotHashes::new(&[(DRAW_SLOT, Hash::new_unique())]));
+    send(&mut svm, instructions::close_sales(lottery_id), &admin);
+    set_time(&mut svm, DRAW_TS, DRAW_SLOT + 1);
+    let instruction = instructions::crank_draw(lottery_id, &buyers[0].pubkey());
+    assert_fails_with(&mut svm, instruction, &buyers[0], "ProgramPaused");
+
+    // The authority can still reveal, a long pause must not push its lottery to the crank
+    send(
+        &mut svm,
+        instructions::reveal_and_pick_winner(lottery_id, &admin.pubkey(), SECRET),
+        &admin,
+    );
+    assert!(load_lottery(&svm, lottery_id).is_drawn());
+}
+
+#[test]
+fn test_claims_go_through_while_paused() {
+    let (mut svm, lottery_id, admin, buyers) = setup_lottery(lottery_params(), 2);
+    draw(&mut svm, lottery_id, &admin);
+    send(
+        &mut svm,
+        instructions::set_paused(&admin.pubkey(), true),
+        &admin,
+    );
+
+    let lottery = load_lottery(&svm, lottery_id);
+    let winner_ticket_id = lottery.prize_tiers[0].winner_ticket_id;
+    let winner = &buyers[winner_ticket_id as usize - 1];
+    let balance = svm.get_balance(&winner.pubkey()).unwrap();
+    let instruction = instructions::claim_prize(
+        lottery_id,
+        0,
+        WinningTicket::Pda(winner_ticket_id),
+        &winner.pubkey(),
+    );
+    send(&mut svm, instruction, winner);
+    assert!(load_lottery(&svm, lottery_id).prize_tiers[0].claimed);
+    assert_eq!(
+        svm.get_balance(&winner.pubkey()).unwrap(),
+        balance + lottery.prize_pool_lamports - TX_FEE
+    );
+}
+
+#[test]
+fn test_refunds_go_through_while_paused() {
+    let (mut svm, lottery_id, admin, buyers) = setup_lottery(lottery_params(), 2);
+    send(
+        &mut svm,
+        instructions::set_paused(&admin.pubkey(), true),
+        &admin,
+    );
+    let instruction =
+        instructions::cancel_lottery(lottery_id, &admin.pubkey(), &admin.pubkey(), None);
+    send(&mut svm, instruction, &admin);
+
+    let ticket_rent = svm.minimum_balance_for_rent_exemption(8 + TicketPDA::INIT_SPACE);
+    let owner = &buyers[0];
+    let balance = svm.get_balance(&owner.pubkey()).unwrap();
+    send(
+        &mut svm,
+        instructions::refund_ticket(lottery_id, 1, &owner.pubkey()),
+        &admin,
+    );
+    assert_eq!(
+        svm.get_balance(&owner.pubkey()).unwrap(),
+        balance + TICKET_PRICE + ticket_rent
+    );
+    assert_eq!(load_lottery(&svm, lottery_id).tickets_refunded, 1);
+}
+
+#[test]
+fn test_authority_transfer_takes_two_steps() {
+    let (mut svm, lottery_id, authority, buyers) = setup_lottery(lottery_params(), 2);
+    let new_authority = funded_wallet(&mut svm, 1_000_000_000);
+    let stranger = &buyers[0];
+
+    let instruction =
+        instructions::transfer_lottery_authority(lottery_id, &stranger.pubkey(), stranger.pubkey());
+    assert_fails_with(&mut svm, instruction, stranger, "UnauthorizedAction");
+
+    send(
+        &mut svm,
+        instructions::transfer_lottery_authority(
+            lottery_id,
+            &authority.pubkey(),
+            new_authority.pubkey(),
+        ),
+        &authority,
+    );
+    let lottery = load_lottery(&svm, lottery_id);
+    assert_eq!(lottery.authority, authority.pubkey());
+    assert_eq!(lottery.pending_authority, Some(new_authority.pubkey()));
+
+    // Only the proposed authority can accept
+    let instruction = instructions::accept_lottery_authority(lottery_id, &stranger.pubkey());
+    assert_fails_with(&mut svm, instruction, stranger, "UnauthorizedAction");
+
+    send(
+        &mut svm,
+        instructions::accept_lottery_authority(lottery_id, &new_authority.pubkey()),
+        &new_authority,
+    );
+    let lottery = load_lottery(&svm, lottery_id);
+    assert_eq!(lottery.authority, new_authority.pubkey());
+    assert_eq!(lottery.pending_authority, None);
+
+    // The previous authority lost its rights, the new one has them
+    let instruction = instructions::transfer_lottery_authority(
+        lottery_id,
+        &authority.pubkey(),
+        authority.pubkey(),
+    );
+    assert_fails_with(&mut svm, instruction, &authority, "UnauthorizedAction");
+    let instruction =
+        instructions::cancel_lottery(lottery_id, &authority.pubkey(), &authority.pubkey(), None);
+    assert_fails_with(&mut svm, instruction, &authority, "CannotCancelLottery");
+    let instruction = instructions::cancel_lottery(
+        lottery_id,
+        &new_authority.pubkey(),
+        &new_authority.pubkey(),
+        None,
+    );
+    send(&mut svm, instruction, &new_authority);
+    assert_eq!(
+        load_lottery(&svm, lottery_id).status,
+        LotteryStatus::Cancelled
+    );
+}
+
+#[test]
+fn test_only_the_upgrade_authority_migrates_the_master() {
+    let mut svm = LiteSVM::new();
+    let upgrade_authority = funded_wallet(&mut svm, 1_000_000_000);
+    deploy_upgradeable(&mut svm, &upgrade_authority.pubkey());
+    // A master PDA from before it had an admin: only its discriminator and `last_lottery_id`
+    let mut data = MasterPDA::DISCRIMINATOR.to_vec();
+    data.extend_from_slice(&4u32.to_le_bytes());
+    write_account(&mut svm, pda::master(), lottery::ID, false, data);
+
+    let stranger = funded_wallet(&mut svm, 1_000_000_000);
+    let instruction = instructions::migrate_master(&stranger.pubkey());
+    assert_fails_with(&mut svm, instruction, &stranger, "UnauthorizedAction");
+    assert!(svm.get_account(&pda::treasury()).is_none());
+
+    send(
+        &mut svm,
+        instructions::migrate_master(&upgrade_authority.pubkey()),
+        &upgrade_authority,
+    );
+    let master: MasterPDA = load(&svm, &pda::master());
+    assert_eq!(master.last_lottery_id, 4);
+    assert_eq!(master.admin, upgrade_authority.pubkey());
+    assert_eq!(master.fee_bps, 0);
+    assert_eq!(master.version, MasterPDA::VERSION);
+    let treasury: TreasuryPDA = load(&svm, &pda::treasury());
+    assert_eq!(treasury.version, TreasuryPDA::VERSION);
+}
@@ -0,0 +1,126 @@
+#![cfg(test)]
+
+mod common;
+
+use anchor_lang::{
+    prelude::*,
+    solana_program::{hash::Hash, instruction::Instruction, slot_hashes::SlotHashes},
+};
+use common::*;
+use litesvm::LiteSVM;
+use lottery::{CreateLotteryParams, LotteryStatus, TicketPDA};
+use lottery_client::{instructions, pda};
+use solana_keypair::Keypair;
+use solana_signer::Signer;
+
+/// Opens a committed single-tier lottery needing `min_tickets`, and sells a ticket to each of
+/// `buyers` new wallets. Ticket `n` belongs to `buyers[n - 1]`.
+fn setup(min_tickets: u32, buyers: u32) -> (LiteSVM, u32, Keypair, Vec<Keypair>) {
+    setup_lottery(
+        CreateLotteryParams {
+            min_tickets,
+            ..lottery_params()
+        },
+        buyers,
+    )
+}
+
+fn cancel_instruction(lottery_id: u32, caller: &Keypair) -> Instruction {
+    instructions::cancel_lottery(lottery_id, &caller.pubkey(), &caller.pubkey(), None)
+}
+
+#[test]
+fn test_authority_cancels_and_owners_are_refunded() {
+    let (mut svm, lottery_id, authority, buyers) = setup(1, 2);
+
+    // Refunds only open once the lottery is cancelled
+    let instruction = instructions::refund_ticket(lottery_id, 1, &buyers[0].pubkey());
+    assert_fails_with(&mut svm, instruction, &authority, "LotteryNotCancelled");
+
+    send(
+        &mut svm,
+        cancel_instruction(lottery_id, &authority),
+        &authority,
+    );
+    assert_eq!(
+        load_lottery(&svm, lottery_id).status,
+        LotteryStatus::Cancelled
+    );
+
+    let ticket_rent = svm.minimum_balance_for_rent_exemption(8 + TicketPDA::INIT_SPACE);
+    let cranker = funded_wallet(&mut svm, 1_000_000_000);
+    for (index, buyer) in buyers.iter().enumerate() {
+        let ticket_id = index as u32 + 1;
+        let balance = svm.get_balance(&buyer.pubkey()).unwrap();
+        // Anyone can send the refund, it always goes to the ticket's owner
+        send(
+            &mut svm,
+            instructions::refund_ticket(lottery_id, ticket_id, &buyer.pubkey()),
+            &cranker,
+        );
+        assert_eq!(
+            svm.get_balance(&buyer.pubkey()).unwrap(),
+            balance + TICKET_PRICE + ticket_rent
+        );
+        assert!(is_closed(
+            &svm,
+            &pda::ticket(&pda::lottery(lottery_id), ticket_id)
+        ));
+    }
+    let instruction = instructions::refund_ticket(lottery_id, 1, &buyers[0].pubkey());
+    try_send(&mut svm, instruction, &cranker).expect_err("A closed ticket can't be refunded");
+
+    let lottery = load_lottery(&svm, lottery_id);
+    assert_eq!(lottery.tickets_refunded, 2);
+    assert_eq!(lottery.sales_lamports, 0);
+}
+
+#[test]
+fn test_anyone_cancels_below_min_tickets_after_the_sale() {
+    let (mut svm, lottery_id, _authority, buyers) = setup(3, 2);
+    let stranger = &buyers[0];
+
+    let instruction = cancel_instruction(lottery_id, stranger);
+    assert_fails_with(&mut svm, instruction, stranger, "CannotCancelLottery");
+
+    set_time(&mut svm, DRAW_TS, DRAW_SLOT);
+    send(&mut svm, cancel_instruction(lottery_id, stranger), stranger);
+    assert_eq!(
+        load_lottery(&svm, lottery_id).status,
+        LotteryStatus::Cancelled
+    );
+}
+
+#[test]
+fn test_strangers_cannot_cancel_once_min_tickets_sold() {
+    let (mut svm, lottery_id, _authority, buyers) = setup(2, 2);
+    set_time(&mut svm, DRAW_TS, DRAW_SLOT);
+
+    let instruction = cancel_instruction(lottery_id, &buyers[0]);
+    assert_fails_with(&mut svm, instruction, &buyers[0], "CannotCancelLottery");
+    assert_eq!(load_lottery(&svm, lottery_id).status, LotteryStatus::Active);
+}
+
+#[test]
+fn test_cancelled_lottery_cannot_be_drawn_or_bought() {
+    let (mut svm, lottery_id, authority, _buyers) = setup(1, 2);
+    send(
+        &mut svm,
+        cancel_instruction(lottery_id, &authority),
+        &authority,
+    );
+
+    let buyer = funded_wallet(&mut svm, 10_000_000_000);
+    let instruction = instructions::buy_ticket(lottery_id, 3, &buyer.pubkey(), None, None, vec![]);
+    assert_fails_with(&mut svm, instruction, &buyer, "LotteryCancelled");
+
+    set_time(&mut svm, DRAW_TS, DRAW_SLOT);
+    svm.set_sysvar(&SlotHashes::new(&[(DRAW_SLOT, Hash::new_unique())]));
+    let instruction = instructions::close_sales(lottery_id);
+    assert_fails_with(&mut svm, instruction, &authority, "LotteryCancelled");
+
+    set_time(&mut svm, DRAW_TS, DRAW_SLOT + 1);
+    let instruction = instructions::reveal_and_pick_winner(lottery_id, &authority.pubkey(), SECRET);
+    assert_fails_with(&mut svm, instruction, &authority, "LotteryCancelled");
+    assert!(!load_lottery(&svm, lottery_id).is_drawn());
+}
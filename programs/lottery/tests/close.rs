@@ -0,0 +1,122 @@
+#![cfg(test)]
+
+mod common;
+
+use anchor_lang::solana_program::instruction::Instruction;
+use common::*;
+use litesvm::LiteSVM;
+use lottery_client::{
+    instructions::{self, WinningTicket},
+    pda,
+};
+use solana_keypair::Keypair;
+use solana_signer::Signer;
+
+/// Runs a single-tier lottery with a ticket ledger through its draw: three buyers with a ticket
+/// each. Returns the authority and the buyers, ticket `n` belongs to `buyers[n - 1]`.
+fn setup_drawn_lottery() -> (LiteSVM, u32, Keypair, Vec<Keypair>) {
+    let (mut svm, authority) = new_svm();
+    let lottery_id = create_lottery(&mut svm, &authority, lottery_params());
+    send(
+        &mut svm,
+        instructions::init_ticket_ledger(lottery_id, &authority.pubkey()),
+        &authority,
+    );
+    let buyers = sell_tickets(&mut svm, lottery_id, 3);
+    draw(&mut svm, lottery_id, &authority);
+    (svm, lottery_id, authority, buyers)
+}
+
+fn close_ticket_instruction(lottery_id: u32, ticket_id: u32, owner: &Keypair) -> Instruction {
+    instructions::close_ticket(lottery_id, ticket_id, &owner.pubkey())
+}
+
+fn claim_instruction(lottery_id: u32, ticket_id: u32, winner: &Keypair) -> Instruction {
+    instructions::claim_prize(
+        lottery_id,
+        0,
+        WinningTicket::Pda(ticket_id),
+        &winner.pubkey(),
+    )
+}
+
+#[test]
+fn test_winning_ticket_closes_only_after_its_claim() {
+    let (mut svm, lottery_id, _authority, buyers) = setup_drawn_lottery();
+    let winner_ticket_id = load_lottery(&svm, lottery_id).prize_tiers[0].winner_ticket_id;
+    let winner = &buyers[winner_ticket_id as usize - 1];
+    let winner_ticket = pda::ticket(&pda::lottery(lottery_id), winner_ticket_id);
+
+    let instruction = close_ticket_instruction(lottery_id, winner_ticket_id, winner);
+    assert_fails_with(&mut svm, instruction, winner, "PrizeNotClaimed");
+    assert!(!is_closed(&svm, &winner_ticket));
+
+    // Losing tickets can be closed as soon as the lottery is drawn
+    let loser_ticket_id = winner_ticket_id % 3 + 1;
+    let loser = &buyers[loser_ticket_id as usize - 1];
+    let balance = svm.get_balance(&loser.pubkey()).unwrap();
+    send(
+        &mut svm,
+        close_ticket_instruction(lottery_id, loser_ticket_id, loser),
+        loser,
+    );
+    assert!(svm.get_balance(&loser.pubkey()).unwrap() > balance);
+
+    send(
+        &mut svm,
+        claim_instruction(lottery_id, winner_ticket_id, winner),
+        winner,
+    );
+    let balance = svm.get_balance(&winner.pubkey()).unwrap();
+    send(
+        &mut svm,
+        close_ticket_instruction(lottery_id, winner_ticket_id, winner),
+        winner,
+    );
+    assert!(svm.get_balance(&winner.pubkey()).unwrap() > balance);
+    assert!(is_closed(&svm, &winner_ticket));
+}
+
+#[test]
+fn test_close_lottery_closes_its_ticket_ledger() {
+    let (mut svm, lottery_id, authority, buyers) = setup_drawn_lottery();
+    let lottery_pda = pda::lottery(lottery_id);
+    let ticket_ledger = pda::ticket_ledger(&lottery_pda);
+    let winner_ticket_id = load_lottery(&svm, lottery_id).prize_tiers[0].winner_ticket_id;
+    let winner = &buyers[winner_ticket_id as usize - 1];
+
+    let instruction = instructions::close_lottery(lottery_id, &authority.pubkey(), None);
+    assert_fails_with(&mut svm, instruction, &authority, "LotteryNotSettled");
+
+    send(
+        &mut svm,
+        claim_instruction(lottery_id, winner_ticket_id, winner),
+        winner,
+    );
+    let ledger_rent = svm.get_balance(&ticket_ledger).unwrap();
+    let lottery_balance = svm.get_balance(&lottery_pda).unwrap();
+    let balance = svm.get_balance(&authority.pubkey()).unwrap();
+    send(
+        &mut svm,
+        instructions::close_lottery(lottery_id, &authority.pubkey(), None),
+        &authority,
+    );
+    // The jackpot PDA is created on the first close, paid from the returned rent
+    let jackpot_rent = svm.get_balance(&pda::jackpot()).unwrap();
+    assert_eq!(
+        svm.get_balance(&authority.pubkey()).unwrap(),
+        balance + ledger_rent + lottery_balance - jackpot_rent - TX_FEE
+    );
+    for closed in [lottery_pda, ticket_ledger] {
+        assert!(is_closed(&svm, &closed));
+    }
+
+    // Tickets of a closed lottery can still be closed
+    let loser_ticket_id = winner_ticket_id % 3 + 1;
+    let loser = &buyers[loser_ticket_id as usize - 1];
+    send(
+        &mut svm,
+        close_ticket_instruction(lottery_id, loser_ticket_id, loser),
+        loser,
+    );
+}
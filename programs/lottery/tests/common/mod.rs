@@ -0,0 +1,329 @@
+//! Fixtures shared by the litesvm tests. Every test file includes this module and uses only part
+//! of it.
+#![allow(dead_code)]
+
+use anchor_lang::solana_program::{
+    clock::Clock,
+    hash::{hashv, Hash},
+    instruction::Instruction,
+    slot_hashes::SlotHashes,
+    system_instruction,
+};
+use anchor_lang::{prelude::Pubkey, AccountDeserialize, Event};
+use anchor_spl::associated_token::spl_associated_token_account;
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{transfer_fee, ExtensionType},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use litesvm::{types::TransactionMetadata, LiteSVM};
+use lottery::{CreateLotteryParams, GameMode, LotteryPDA, MasterPDA};
+use lottery_client::{instructions, pda};
+use solana_keypair::Keypair;
+use solana_signer::Signer;
+use solana_transaction::Transaction;
+
+pub const TICKET_PRICE: u64 = 10_000_000;
+pub const DRAW_TS: i64 = 1_000;
+pub const DRAW_SLOT: u64 = 100;
+pub const SECRET: [u8; 32] = [7; 32];
+/// Fee of a transaction with a single signature
+pub const TX_FEE: u64 = 5_000;
+
+/// Sends `instructions` in one transaction paid by the first of `signers`. Returns the logs of a
+/// failed transaction as the error.
+pub fn try_send_all(
+    svm: &mut LiteSVM,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> Result<TransactionMetadata, Vec<String>> {
+    let mut transaction = Transaction::new_with_payer(instructions, Some(&signers[0].pubkey()));
+    transaction.sign(signers, svm.latest_blockhash());
+    svm.send_transaction(transaction)
+        .map_err(|failure| failure.meta.logs)
+}
+
+pub fn send_all(
+    svm: &mut LiteSVM,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> TransactionMetadata {
+    let result = try_send_all(svm, instructions, signers);
+    assert!(result.is_ok(), "Transaction failed: {:?}", result.err());
+    result.unwrap()
+}
+
+pub fn try_send(
+    svm: &mut LiteSVM,
+    instruction: Instruction,
+    signer: &Keypair,
+) -> Result<TransactionMetadata, Vec<String>> {
+    try_send_all(svm, &[instruction], &[signer])
+}
+
+pub fn send(svm: &mut LiteSVM, instruction: Instruction, signer: &Keypair) -> TransactionMetadata {
+    send_all(svm, &[instruction], &[signer])
+}
+
+pub fn assert_fails_with(
+    svm: &mut LiteSVM,
+    instruction: Instruction,
+    signer: &Keypair,
+    error: &str,
+) {
+    let logs = try_send(svm, instruction, signer).expect_err("Transaction should fail");
+    assert!(
+        logs.iter().any(|log| log.contains(error)),
+        "Expected {error} in {logs:?}"
+    );
+    // A retry of the same instruction must not be rejected as a duplicate transaction
+    svm.expire_blockhash();
+}
+
+/// Events of type `T` the program emitted in a transaction, in order
+pub fn events<T: Event>(metadata: &TransactionMetadata) -> Vec<T> {
+    metadata
+        .logs
+        .iter()
+        .filter_map(|log| log.strip_prefix("Program data: "))
+        .filter_map(|data| STANDARD.decode(data).ok())
+        .filter(|data| data.starts_with(T::DISCRIMINATOR))
+        .map(|data| T::deserialize(&mut &data[T::DISCRIMINATOR.len()..]).unwrap())
+        .collect()
+}
+
+pub fn load<T: AccountDeserialize>(svm: &LiteSVM, address: &Pubkey) -> T {
+    let account = svm.get_account(address).unwrap();
+    T::try_deserialize(&mut account.data.as_slice()).unwrap()
+}
+
+pub fn load_lottery(svm: &LiteSVM, lottery_id: u32) -> LotteryPDA {
+    load(svm, &pda::lottery(lottery_id))
+}
+
+/// Whether `address` was closed, litesvm keeps closed accounts around with no lamports
+pub fn is_closed(svm: &LiteSVM, address: &Pubkey) -> bool {
+    svm.get_account(address)
+        .is_none_or(|account| account.lamports == 0)
+}
+
+pub fn set_time(svm: &mut LiteSVM, unix_timestamp: i64, slot: u64) {
+    let mut clock = svm.get_sysvar::<Clock>();
+    clock.unix_timestamp = unix_timestamp;
+    clock.slot = slot;
+    svm.set_sysvar(&clock);
+}
+
+pub fn funded_wallet(svm: &mut LiteSVM, lamports: u64) -> Keypair {
+    let wallet = Keypair::new();
+    svm.airdrop(&wallet.pubkey(), lamports).unwrap();
+    wallet
+}
+
+/// Loads the program and initializes the master PDA. The returned wallet is the program's admin.
+pub fn new_svm() -> (LiteSVM, Keypair) {
+    let mut svm = LiteSVM::new();
+    svm.add_program(
+        lottery::ID,
+        include_bytes!("../../../../target/deploy/lottery.so"),
+    );
+    let admin = funded_wallet(&mut svm, 1_000_000_000_000);
+    send(&mut svm, instructions::init_master(&admin.pubkey()), &admin);
+    (svm, admin)
+}
+
+/// ID the next `create_lottery` or `advance_series` creates
+pub fn next_lottery_id(svm: &LiteSVM) -> u32 {
+    load::<MasterPDA>(svm, &pda::master()).last_lottery_id + 1
+}
+
+/// A single-tier lamport lottery selling until `DRAW_TS`, with no limits
+pub fn lottery_params() -> CreateLotteryParams {
+    CreateLotteryParams {
+        ticket_price_lamports: TICKET_PRICE,
+        sale_start_ts: 0,
+        sale_end_ts: DRAW_TS,
+        draw_after_ts: DRAW_TS,
+        prize_shares_bps: vec![10_000],
+        min_tickets: 1,
+        max_tickets: 0,
+        max_tickets_per_wallet: 0,
+        claim_deadline_ts: DRAW_TS + 1_000,
+        nft_tickets: false,
+        referral_bps: 0,
+        crank_bounty_lamports: 0,
+        game_mode: GameMode::TicketDraw,
+        numbers_per_ticket: 0,
+        max_number: 0,
+        prize_mint: None,
+        prize_amount: 0,
+    }
+}
+
+/// Creates a lottery committed to `SECRET` and returns its ID
+pub fn create_lottery(svm: &mut LiteSVM, authority: &Keypair, params: CreateLotteryParams) -> u32 {
+    let lottery_id = next_lottery_id(svm);
+    send(
+        svm,
+        instructions::create_lottery(lottery_id, &authority.pubkey(), params),
+        authority,
+    );
+    send(
+        svm,
+        instructions::commit_randomness(
+            lottery_id,
+            &authority.pubkey(),
+            hashv(&[&SECRET]).to_bytes(),
+        ),
+        authority,
+    );
+    lottery_id
+}
+
+/// Buys the lottery's next ticket for `buyer` and returns its ID
+pub fn buy_ticket(svm: &mut LiteSVM, lottery_id: u32, buyer: &Keypair) -> u32 {
+    let ticket_id = load_lottery(svm, lottery_id).last_ticket_id + 1;
+    send(
+        svm,
+        instructions::buy_ticket(lottery_id, ticket_id, &buyer.pubkey(), None, None, vec![]),
+        buyer,
+    );
+    ticket_id
+}
+
+/// Sells a ticket to each of `count` new wallets. On a fresh lottery ticket `n` belongs to the
+/// wallet at `n - 1`.
+pub fn sell_tickets(svm: &mut LiteSVM, lottery_id: u32, count: u32) -> Vec<Keypair> {
+    (0..count)
+        .map(|_| {
+            let buyer = funded_wallet(svm, 10_000_000_000);
+            buy_ticket(svm, lottery_id, &buyer);
+            buyer
+        })
+        .collect()
+}
+
+/// Opens a lottery with `params` from a fresh program and sells a ticket to each of `buyers` new
+/// wallets. The authority is also the program's admin.
+pub fn setup_lottery(
+    params: CreateLotteryParams,
+    buyers: u32,
+) -> (LiteSVM, u32, Keypair, Vec<Keypair>) {
+    let (mut svm, authority) = new_svm();
+    let lottery_id = create_lottery(&mut svm, &authority, params);
+    let wallets = sell_tickets(&mut svm, lottery_id, buyers);
+    (svm, lottery_id, authority, wallets)
+}
+
+/// Closes the sales at `DRAW_TS` and `DRAW_SLOT`, with a fresh hash for that slot
+pub fn close_sales(svm: &mut LiteSVM, lottery_id: u32, payer: &Keypair) {
+    set_time(svm, DRAW_TS, DRAW_SLOT);
+    svm.set_sysvar(&SlotHashes::new(&[(DRAW_SLOT, Hash::new_unique())]));
+    send(svm, instructions::close_sales(lottery_id), payer);
+}
+
+/// Closes the sales and reveals `SECRET` in the next slot
+pub fn draw(svm: &mut LiteSVM, lottery_id: u32, authority: &Keypair) -> TransactionMetadata {
+    close_sales(svm, lottery_id, authority);
+    set_time(svm, DRAW_TS, DRAW_SLOT + 1);
+    send(
+        svm,
+        instructions::reveal_and_pick_winner(lottery_id, &authority.pubkey(), SECRET),
+        authority,
+    )
+}
+
+/// `amount` follows the mint and the owner in both token programs
+pub fn token_balance(svm: &LiteSVM, token_account: &Pubkey) -> u64 {
+    let data = svm.get_account(token_account).unwrap().data;
+    u64::from_le_bytes(data[64..72].try_into().unwrap())
+}
+
+/// `supply` follows the optional mint authority in both token programs
+pub fn mint_supply(svm: &LiteSVM, mint: &Pubkey) -> u64 {
+    let data = svm.get_account(mint).unwrap().data;
+    u64::from_le_bytes(data[36..44].try_into().unwrap())
+}
+
+/// Creates a mint with 6 decimals under `token_program`, optionally with a Token-2022 transfer
+/// fee of 1%. `authority` pays for it and holds its mint authority.
+pub fn create_mint(
+    svm: &mut LiteSVM,
+    authority: &Keypair,
+    token_program: &Pubkey,
+    transfer_fee: bool,
+) -> Pubkey {
+    let mint = Keypair::new();
+    let extensions = if transfer_fee {
+        vec![ExtensionType::TransferFeeConfig]
+    } else {
+        vec![]
+    };
+    let space =
+        ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(&extensions)
+            .unwrap();
+    let mut instructions = vec![system_instruction::create_account(
+        &authority.pubkey(),
+        &mint.pubkey(),
+        svm.minimum_balance_for_rent_exemption(space),
+        space as u64,
+        token_program,
+    )];
+    if transfer_fee {
+        instructions.push(
+            transfer_fee::instruction::initialize_transfer_fee_config(
+                token_program,
+                &mint.pubkey(),
+                Some(&authority.pubkey()),
+                Some(&authority.pubkey()),
+                100,
+                u64::MAX,
+            )
+            .unwrap(),
+        );
+    }
+    instructions.push(
+        spl_token_2022::instruction::initialize_mint2(
+            token_program,
+            &mint.pubkey(),
+            &authority.pubkey(),
+            None,
+            6,
+        )
+        .unwrap(),
+    );
+    send_all(svm, &instructions, &[authority, &mint]);
+    mint.pubkey()
+}
+
+/// Mints `amount` to the associated token account of `owner`, creating it if needed, and
+/// returns that account. `authority` is the mint authority of `mint`.
+pub fn mint_tokens(
+    svm: &mut LiteSVM,
+    authority: &Keypair,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+    owner: &Pubkey,
+    amount: u64,
+) -> Pubkey {
+    let token_account = pda::token_account(owner, mint, token_program);
+    let instructions = [
+        spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+            &authority.pubkey(),
+            owner,
+            mint,
+            token_program,
+        ),
+        spl_token_2022::instruction::mint_to(
+            token_program,
+            mint,
+            &token_account,
+            &authority.pubkey(),
+            &[],
+            amount,
+        )
+        .unwrap(),
+    ];
+    send_all(svm, &instructions, &[authority]);
+    token_account
+}
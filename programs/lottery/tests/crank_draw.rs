@@ -0,0 +1,212 @@
+#![cfg(test)]
+
+mod common;
+
+use anchor_lang::solana_program::{
+    hash::{hashv, Hash},
+    slot_hashes::SlotHashes,
+};
+use common::*;
+use litesvm::LiteSVM;
+use lottery::{CreateLotteryParams, LotteryStatus, MAX_CRANK_BOUNTY_BPS, REVEAL_WINDOW_SLOTS};
+use lottery_client::{
+    instructions::{self, WinningTicket},
+    pda,
+};
+use solana_keypair::Keypair;
+use solana_signer::Signer;
+
+/// Slot of the crank that forfeits the authority's reveal, right after its window
+const FORFEIT_SLOT: u64 = DRAW_SLOT + REVEAL_WINDOW_SLOTS + 1;
+
+/// Runs a single-tier lottery offering `crank_bounty` through its sale: three buyers with a
+/// ticket each. Returns the authority and the buyers, ticket `n` belongs to `buyers[n - 1]`.
+fn setup_sold_lottery(crank_bounty: u64) -> (LiteSVM, u32, Keypair, Vec<Keypair>) {
+    setup_lottery(
+        CreateLotteryParams {
+            crank_bounty_lamports: crank_bounty,
+            ..lottery_params()
+        },
+        3,
+    )
+}
+
+fn try_crank(svm: &mut LiteSVM, lottery_id: u32, caller: &Keypair) -> Result<(), Vec<String>> {
+    let result = try_send(
+        svm,
+        instructions::crank_draw(lottery_id, &caller.pubkey()),
+        caller,
+    );
+    // The next crank by the same caller must not be rejected as a duplicate transaction
+    svm.expire_blockhash();
+    result.map(|_| ())
+}
+
+fn crank(svm: &mut LiteSVM, lottery_id: u32, caller: &Keypair) {
+    let result = try_crank(svm, lottery_id, caller);
+    assert!(result.is_ok(), "Transaction failed: {:?}", result.err());
+}
+
+/// Cranks the sales closed at `DRAW_SLOT`, then forfeits the authority's reveal at
+/// `FORFEIT_SLOT`
+fn crank_to_forfeit(svm: &mut LiteSVM, lottery_id: u32, caller: &Keypair) {
+    set_time(svm, DRAW_TS, DRAW_SLOT);
+    crank(svm, lottery_id, caller);
+    set_time(svm, DRAW_TS, FORFEIT_SLOT);
+    crank(svm, lottery_id, caller);
+}
+
+/// Moves to the slot after `slot`, with a fresh hash for `slot`. Returns that hash.
+fn next_slot_with_hash(svm: &mut LiteSVM, slot: u64) -> Hash {
+    let hash = Hash::new_unique();
+    svm.set_sysvar(&SlotHashes::new(&[(slot, hash)]));
+    set_time(svm, DRAW_TS, slot + 1);
+    hash
+}
+
+#[test]
+fn test_authority_keeps_the_draw_during_its_reveal_window() {
+    let (mut svm, lottery_id, authority, buyers) = setup_sold_lottery(1_000);
+    let caller = &buyers[0];
+    set_time(&mut svm, DRAW_TS - 1, DRAW_SLOT - 1);
+    let error = try_crank(&mut svm, lottery_id, caller).expect_err("Crank should fail");
+    assert!(error.iter().any(|log| log.contains("DrawTooEarly")));
+
+    // The first crank after `draw_after_ts` only closes the sales
+    set_time(&mut svm, DRAW_TS, DRAW_SLOT);
+    crank(&mut svm, lottery_id, caller);
+    let lottery = load_lottery(&svm, lottery_id);
+    assert_eq!(lottery.sales_closed_slot, Some(DRAW_SLOT));
+    assert!(!lottery.is_drawn());
+
+    set_time(&mut svm, DRAW_TS, DRAW_SLOT + REVEAL_WINDOW_SLOTS);
+    let error = try_crank(&mut svm, lottery_id, caller).expect_err("Crank should fail");
+    assert!(error
+        .iter()
+        .any(|log| log.contains("RevealWindowNotExpired")));
+    let lottery = load_lottery(&svm, lottery_id);
+    assert!(!lottery.reveal_forfeited);
+    assert_eq!(lottery.crank_bounty_paid_lamports, 0);
+
+    svm.set_sysvar(&SlotHashes::new(&[(DRAW_SLOT, Hash::new_unique())]));
+    send(
+        &mut svm,
+        instructions::reveal_and_pick_winner(lottery_id, &authority.pubkey(), SECRET),
+        &authority,
+    );
+    let lottery = load_lottery(&svm, lottery_id);
+    assert!(lottery.is_drawn());
+    assert_eq!(lottery.crank_bounty_paid_lamports, 0);
+    assert_eq!(lottery.prize_pool_lamports, 3 * TICKET_PRICE);
+}
+
+#[test]
+fn test_crank_draws_after_a_missed_reveal() {
+    const BOUNTY: u64 = 30_000;
+    let (mut svm, lottery_id, authority, buyers) = setup_sold_lottery(BOUNTY);
+    let caller = funded_wallet(&mut svm, 1_000_000_000);
+    crank_to_forfeit(&mut svm, lottery_id, &caller);
+
+    // The forfeit draws from the hash of its own slot, unknown until that slot is over
+    let lottery = load_lottery(&svm, lottery_id);
+    assert!(lottery.reveal_forfeited);
+    assert_eq!(lottery.entropy_slot, FORFEIT_SLOT);
+    assert_eq!(lottery.status, LotteryStatus::Active);
+    assert!(!lottery.is_drawn());
+    assert_eq!(lottery.crank_bounty_paid_lamports, 0);
+    let error = try_crank(&mut svm, lottery_id, &caller).expect_err("Crank should fail");
+    assert!(error.iter().any(|log| log.contains("SlotHashNotAvailable")));
+
+    // The moved entropy slot doesn't reopen the authority's reveal
+    let slot_hash = next_slot_with_hash(&mut svm, FORFEIT_SLOT);
+    let instruction = instructions::reveal_and_pick_winner(lottery_id, &authority.pubkey(), SECRET);
+    assert_fails_with(&mut svm, instruction, &authority, "RevealWindowExpired");
+
+    let lottery_balance = svm.get_balance(&pda::lottery(lottery_id)).unwrap();
+    let caller_balance = svm.get_balance(&caller.pubkey()).unwrap();
+    crank(&mut svm, lottery_id, &caller);
+
+    let lottery = load_lottery(&svm, lottery_id);
+    assert!(lottery.is_drawn());
+    assert_eq!(
+        lottery.draw_seed,
+        hashv(&[&lottery.entropy_accumulator, &slot_hash.to_bytes()]).to_bytes()
+    );
+    assert_eq!(lottery.revealed_secret, [0; 32]);
+    // The bounty comes out of the sales before the prize pool is set
+    assert_eq!(lottery.crank_bounty_paid_lamports, BOUNTY);
+    assert_eq!(lottery.sales_lamports, 3 * TICKET_PRICE - BOUNTY);
+    assert_eq!(lottery.prize_pool_lamports, 3 * TICKET_PRICE - BOUNTY);
+    assert_eq!(
+        svm.get_balance(&pda::lottery(lottery_id)).unwrap(),
+        lottery_balance - BOUNTY
+    );
+    assert_eq!(
+        svm.get_balance(&caller.pubkey()).unwrap(),
+        caller_balance + BOUNTY - TX_FEE
+    );
+
+    let error = try_crank(&mut svm, lottery_id, &caller).expect_err("Crank should fail");
+    assert!(error.iter().any(|log| log.contains("WinnerAlreadyExists")));
+
+    // The crank's draw pays out like a revealed one
+    let winner_ticket_id = lottery.prize_tiers[0].winner_ticket_id;
+    let winner = &buyers[winner_ticket_id as usize - 1];
+    let winner_balance = svm.get_balance(&winner.pubkey()).unwrap();
+    send(
+        &mut svm,
+        instructions::claim_prize(
+            lottery_id,
+            0,
+            WinningTicket::Pda(winner_ticket_id),
+            &winner.pubkey(),
+        ),
+        winner,
+    );
+    assert_eq!(
+        svm.get_balance(&winner.pubkey()).unwrap(),
+        winner_balance + 3 * TICKET_PRICE - BOUNTY - TX_FEE
+    );
+}
+
+#[test]
+fn test_crank_bounty_is_capped() {
+    let (mut svm, lottery_id, _authority, _buyers) = setup_sold_lottery(u64::MAX);
+    let caller = funded_wallet(&mut svm, 1_000_000_000);
+    crank_to_forfeit(&mut svm, lottery_id, &caller);
+    next_slot_with_hash(&mut svm, FORFEIT_SLOT);
+    let caller_balance = svm.get_balance(&caller.pubkey()).unwrap();
+    crank(&mut svm, lottery_id, &caller);
+
+    let max_bounty = 3 * TICKET_PRICE * MAX_CRANK_BOUNTY_BPS as u64 / 10_000;
+    let lottery = load_lottery(&svm, lottery_id);
+    assert!(lottery.is_drawn());
+    assert_eq!(lottery.crank_bounty_paid_lamports, max_bounty);
+    assert_eq!(lottery.prize_pool_lamports, 3 * TICKET_PRICE - max_bounty);
+    assert_eq!(
+        svm.get_balance(&caller.pubkey()).unwrap(),
+        caller_balance + max_bounty - TX_FEE
+    );
+}
+
+#[test]
+fn test_crank_moves_an_entropy_slot_gone_from_slot_hashes() {
+    let (mut svm, lottery_id, _authority, buyers) = setup_sold_lottery(1_000);
+    let caller = &buyers[0];
+    crank_to_forfeit(&mut svm, lottery_id, caller);
+
+    // Nobody cranked while the forfeit slot's hash was available, the next crank picks a new slot
+    let late_slot = FORFEIT_SLOT + REVEAL_WINDOW_SLOTS + 1;
+    set_time(&mut svm, DRAW_TS, late_slot);
+    crank(&mut svm, lottery_id, caller);
+    let lottery = load_lottery(&svm, lottery_id);
+    assert_eq!(lottery.entropy_slot, late_slot);
+    assert!(!lottery.is_drawn());
+    assert_eq!(lottery.crank_bounty_paid_lamports, 0);
+
+    next_slot_with_hash(&mut svm, late_slot);
+    crank(&mut svm, lottery_id, caller);
+    let lottery = load_lottery(&svm, lottery_id);
+    assert!(lottery.is_drawn());
+    assert_eq!(lottery.crank_bounty_paid_lamports, 1_000);
+}
@@ -0,0 +1,79 @@
+#![cfg(test)]
+
+mod common;
+
+use common::*;
+use lottery::{PrizeClaimed, TicketBought, WinnerPicked};
+use lottery_client::instructions::{self, WinningTicket};
+use solana_signer::Signer;
+
+#[test]
+fn test_lifecycle_events_carry_the_amounts_moved() {
+    let (mut svm, authority) = new_svm();
+    let lottery_id = create_lottery(&mut svm, &authority, lottery_params());
+    send(
+        &mut svm,
+        instructions::init_ticket_ledger(lottery_id, &authority.pubkey()),
+        &authority,
+    );
+
+    let buyer = funded_wallet(&mut svm, 1_000_000_000);
+    let instruction = instructions::buy_ticket(lottery_id, 1, &buyer.pubkey(), None, None, vec![]);
+    let bought = events::<TicketBought>(&send(&mut svm, instruction, &buyer));
+    assert_eq!(bought.len(), 1);
+    assert_eq!(bought[0].lottery_id, lottery_id);
+    assert_eq!(bought[0].buyer, buyer.pubkey());
+    assert_eq!(bought[0].first_ticket_id, 1);
+    assert_eq!(bought[0].count, 1);
+    assert_eq!(bought[0].amount_paid, TICKET_PRICE);
+    assert_eq!(bought[0].ticket_mint, None);
+
+    // A gift emits one event per recipient, each with the price of its own ticket
+    let recipients = [
+        funded_wallet(&mut svm, 1_000_000_000),
+        funded_wallet(&mut svm, 1_000_000_000),
+    ];
+    let instruction = instructions::buy_tickets_for(
+        lottery_id,
+        &buyer.pubkey(),
+        recipients
+            .iter()
+            .map(|recipient| recipient.pubkey())
+            .collect(),
+        None,
+    );
+    let gifted = events::<TicketBought>(&send(&mut svm, instruction, &buyer));
+    assert_eq!(gifted.len(), 2);
+    for ((event, recipient), ticket_id) in gifted.iter().zip(&recipients).zip(2..) {
+        assert_eq!(event.buyer, recipient.pubkey());
+        assert_eq!(event.first_ticket_id, ticket_id);
+        assert_eq!(event.amount_paid, TICKET_PRICE);
+    }
+
+    let picked = events::<WinnerPicked>(&draw(&mut svm, lottery_id, &authority));
+    let lottery = load_lottery(&svm, lottery_id);
+    assert_eq!(picked.len(), 1);
+    assert_eq!(picked[0].lottery_id, lottery_id);
+    assert_eq!(picked[0].tier, 0);
+    assert_eq!(
+        picked[0].winner_ticket_id,
+        lottery.prize_tiers[0].winner_ticket_id
+    );
+    assert_eq!(picked[0].prize_amount, 3 * TICKET_PRICE);
+    assert_eq!(picked[0].draw_seed, lottery.draw_seed);
+
+    let (winner, winning_ticket) = match picked[0].winner_ticket_id {
+        1 => (&buyer, WinningTicket::Pda(1)),
+        ticket_id => (&recipients[ticket_id as usize - 2], WinningTicket::Ledger),
+    };
+    let instruction = instructions::claim_prize(lottery_id, 0, winning_ticket, &winner.pubkey());
+    let claimed = events::<PrizeClaimed>(&send(&mut svm, instruction, winner));
+    assert_eq!(claimed.len(), 1);
+    assert_eq!(claimed[0].lottery_id, lottery_id);
+    assert_eq!(claimed[0].tier, 0);
+    assert_eq!(claimed[0].ticket_id, picked[0].winner_ticket_id);
+    assert_eq!(claimed[0].winner, winner.pubkey());
+    assert_eq!(claimed[0].prize_amount, 3 * TICKET_PRICE);
+    assert_eq!(claimed[0].fee_amount, 0);
+    assert_eq!(claimed[0].ticket_mint, None);
+}
@@ -0,0 +1,97 @@
+#![cfg(test)]
+
+mod common;
+
+use common::*;
+use lottery::{MasterPDA, TreasuryPDA, MAX_FEE_BPS};
+use lottery_client::{
+    instructions::{self, WinningTicket},
+    pda,
+};
+use solana_signer::Signer;
+
+const FEE_BPS: u16 = 500;
+
+#[test]
+fn test_only_the_admin_sets_the_fee_up_to_its_cap() {
+    let (mut svm, admin) = new_svm();
+    let stranger = funded_wallet(&mut svm, 1_000_000_000);
+
+    let instruction = instructions::set_fee(&stranger.pubkey(), FEE_BPS);
+    assert_fails_with(&mut svm, instruction, &stranger, "UnauthorizedAction");
+    let instruction = instructions::set_fee(&admin.pubkey(), MAX_FEE_BPS + 1);
+    assert_fails_with(&mut svm, instruction, &admin, "FeeTooHigh");
+    assert_eq!(load::<MasterPDA>(&svm, &pda::master()).fee_bps, 0);
+
+    send(
+        &mut svm,
+        instructions::set_fee(&admin.pubkey(), MAX_FEE_BPS),
+        &admin,
+    );
+    assert_eq!(load::<MasterPDA>(&svm, &pda::master()).fee_bps, MAX_FEE_BPS);
+}
+
+#[test]
+fn test_claim_cuts_the_fee_for_the_admin() {
+    let (mut svm, admin) = new_svm();
+    send(
+        &mut svm,
+        instructions::set_fee(&admin.pubkey(), FEE_BPS),
+        &admin,
+    );
+    let lottery_id = create_lottery(&mut svm, &admin, lottery_params());
+    let buyers = sell_tickets(&mut svm, lottery_id, 3);
+    // The lottery keeps the fee it was created with
+    send(&mut svm, instructions::set_fee(&admin.pubkey(), 0), &admin);
+    draw(&mut svm, lottery_id, &admin);
+
+    let lottery = load_lottery(&svm, lottery_id);
+    assert_eq!(lottery.fee_bps, FEE_BPS);
+    let pot = 3 * TICKET_PRICE;
+    let fee = pot * FEE_BPS as u64 / 10_000;
+    let winner_ticket_id = lottery.prize_tiers[0].winner_ticket_id;
+    let winner = &buyers[winner_ticket_id as usize - 1];
+    let winner_balance = svm.get_balance(&winner.pubkey()).unwrap();
+    let treasury_balance = svm.get_balance(&pda::treasury()).unwrap();
+    send(
+        &mut svm,
+        instructions::claim_prize(
+            lottery_id,
+            0,
+            WinningTicket::Pda(winner_ticket_id),
+            &winner.pubkey(),
+        ),
+        winner,
+    );
+    assert_eq!(
+        svm.get_balance(&winner.pubkey()).unwrap(),
+        winner_balance + pot - fee - TX_FEE
+    );
+    assert_eq!(
+        svm.get_balance(&pda::treasury()).unwrap(),
+        treasury_balance + fee
+    );
+    assert_eq!(
+        load::<TreasuryPDA>(&svm, &pda::treasury()).collected_fees_lamports,
+        fee
+    );
+
+    let stranger = &buyers[0];
+    let instruction = instructions::withdraw_fees(&stranger.pubkey(), fee);
+    assert_fails_with(&mut svm, instruction, stranger, "UnauthorizedAction");
+    // The treasury's rent isn't fees
+    let instruction = instructions::withdraw_fees(&admin.pubkey(), fee + 1);
+    assert_fails_with(&mut svm, instruction, &admin, "InsufficientTreasuryBalance");
+
+    let admin_balance = svm.get_balance(&admin.pubkey()).unwrap();
+    send(
+        &mut svm,
+        instructions::withdraw_fees(&admin.pubkey(), fee),
+        &admin,
+    );
+    assert_eq!(
+        svm.get_balance(&admin.pubkey()).unwrap(),
+        admin_balance + fee - TX_FEE
+    );
+    assert_eq!(svm.get_balance(&pda::treasury()).unwrap(), treasury_balance);
+}
@@ -0,0 +1,161 @@
+#![cfg(test)]
+
+mod common;
+
+use anchor_lang::prelude::*;
+use common::*;
+use litesvm::LiteSVM;
+use lottery::{
+    CreateLotteryParams, PlayerStats, TicketLedger, TicketPDA, WalletTicketsPDA,
+    MAX_GIFT_RECIPIENTS,
+};
+use lottery_client::{instructions, pda};
+use solana_keypair::Keypair;
+use solana_signer::Signer;
+use solana_transaction::Transaction;
+
+const MAX_TICKETS_PER_WALLET: u32 = 1;
+/// Largest transaction a validator accepts
+const PACKET_DATA_SIZE: usize = 1232;
+
+fn load_wallet_tickets(svm: &LiteSVM, lottery_id: u32, owner: &Pubkey) -> WalletTicketsPDA {
+    load(svm, &pda::wallet_tickets(&pda::lottery(lottery_id), owner))
+}
+
+/// Opens a committed lottery with a ticket ledger, selling one ticket per wallet, and funds a
+/// payer for the gifts
+fn setup() -> (LiteSVM, u32, Keypair) {
+    let (mut svm, authority) = new_svm();
+    let params = CreateLotteryParams {
+        max_tickets_per_wallet: MAX_TICKETS_PER_WALLET,
+        ..lottery_params()
+    };
+    let lottery_id = create_lottery(&mut svm, &authority, params);
+    send(
+        &mut svm,
+        instructions::init_ticket_ledger(lottery_id, &authority.pubkey()),
+        &authority,
+    );
+    let payer = funded_wallet(&mut svm, 10_000_000_000);
+    (svm, lottery_id, payer)
+}
+
+#[test]
+fn test_gifted_ticket_belongs_to_its_recipient() {
+    let (mut svm, lottery_id, payer) = setup();
+    let lottery_pda = pda::lottery(lottery_id);
+    let recipient = Pubkey::new_unique();
+
+    let lottery_balance = svm.get_balance(&lottery_pda).unwrap();
+    send(
+        &mut svm,
+        instructions::buy_ticket_for(lottery_id, 1, &payer.pubkey(), recipient, None, vec![]),
+        &payer,
+    );
+    let ticket: TicketPDA = load(&svm, &pda::ticket(&lottery_pda, 1));
+    assert_eq!(ticket.owner, recipient);
+    assert_eq!(
+        svm.get_balance(&lottery_pda).unwrap(),
+        lottery_balance + TICKET_PRICE
+    );
+
+    // The ticket counts for the recipient, who didn't spend anything on it
+    let wallet_tickets = load_wallet_tickets(&svm, lottery_id, &recipient);
+    assert_eq!(wallet_tickets.tickets_bought, 1);
+    let stats: PlayerStats = load(&svm, &pda::player_stats(&recipient));
+    assert_eq!(stats.tickets_bought, 1);
+    assert_eq!(stats.lamports_spent, 0);
+    assert_eq!(stats.lotteries_entered, 1);
+    assert!(svm
+        .get_account(&pda::wallet_tickets(&lottery_pda, &payer.pubkey()))
+        .is_none());
+
+    // The recipient's wallet limit applies to gifts
+    let instruction =
+        instructions::buy_ticket_for(lottery_id, 2, &payer.pubkey(), recipient, None, vec![]);
+    assert_fails_with(&mut svm, instruction, &payer, "WalletTicketLimitReached");
+    assert_eq!(load_lottery(&svm, lottery_id).last_ticket_id, 1);
+}
+
+#[test]
+fn test_bulk_gifts_are_recorded_per_recipient() {
+    let (mut svm, lottery_id, payer) = setup();
+    let lottery_pda = pda::lottery(lottery_id);
+    let recipients = [Pubkey::new_unique(), Pubkey::new_unique()];
+
+    // Each recipient needs its wallet counter and stats accounts
+    let mut instruction =
+        instructions::buy_tickets_for(lottery_id, &payer.pubkey(), recipients.to_vec(), None);
+    instruction
+        .accounts
+        .truncate(instruction.accounts.len() - 2);
+    assert_fails_with(&mut svm, instruction, &payer, "InvalidRecipients");
+    let instruction = instructions::buy_tickets_for(lottery_id, &payer.pubkey(), vec![], None);
+    assert_fails_with(&mut svm, instruction, &payer, "InvalidRecipients");
+
+    let lottery_balance = svm.get_balance(&lottery_pda).unwrap();
+    send(
+        &mut svm,
+        instructions::buy_tickets_for(lottery_id, &payer.pubkey(), recipients.to_vec(), None),
+        &payer,
+    );
+    assert_eq!(
+        svm.get_balance(&lottery_pda).unwrap(),
+        lottery_balance + 2 * TICKET_PRICE
+    );
+    assert_eq!(load_lottery(&svm, lottery_id).last_ticket_id, 2);
+
+    let ledger_account = svm.get_account(&pda::ticket_ledger(&lottery_pda)).unwrap();
+    let (_, ranges) = TicketLedger::split(&ledger_account.data).unwrap();
+    for (ticket_id, recipient) in (1..).zip(&recipients) {
+        assert_eq!(TicketLedger::owner_of(ranges, ticket_id), Some(*recipient));
+        assert_eq!(
+            load_wallet_tickets(&svm, lottery_id, recipient).tickets_bought,
+            1
+        );
+        let stats: PlayerStats = load(&svm, &pda::player_stats(recipient));
+        assert_eq!(stats.tickets_bought, 1);
+        assert_eq!(stats.lamports_spent, 0);
+    }
+
+    // A recipient already at its wallet limit fails the whole batch
+    let late_recipient = Pubkey::new_unique();
+    let batch = vec![late_recipient, recipients[0]];
+    let instruction = instructions::buy_tickets_for(lottery_id, &payer.pubkey(), batch, None);
+    assert_fails_with(&mut svm, instruction, &payer, "WalletTicketLimitReached");
+    assert_eq!(load_lottery(&svm, lottery_id).last_ticket_id, 2);
+    assert!(svm
+        .get_account(&pda::wallet_tickets(&lottery_pda, &late_recipient))
+        .is_none());
+}
+
+#[test]
+fn test_a_full_gift_batch_fits_a_legacy_transaction() {
+    let (mut svm, lottery_id, payer) = setup();
+    let recipients: Vec<Pubkey> = (0..=MAX_GIFT_RECIPIENTS)
+        .map(|_| Pubkey::new_unique())
+        .collect();
+
+    let instruction =
+        instructions::buy_tickets_for(lottery_id, &payer.pubkey(), recipients.clone(), None);
+    assert_fails_with(&mut svm, instruction, &payer, "InvalidRecipients");
+
+    let batch = recipients[..MAX_GIFT_RECIPIENTS].to_vec();
+    let instruction =
+        instructions::buy_tickets_for(lottery_id, &payer.pubkey(), batch.clone(), Some([1; 32]));
+    let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], svm.latest_blockhash());
+    // A signature count and one signature precede the message
+    let size = 1 + 64 + transaction.message.serialize().len();
+    assert!(size <= PACKET_DATA_SIZE, "{size} bytes");
+    svm.send_transaction(transaction).unwrap();
+
+    let lottery = load_lottery(&svm, lottery_id);
+    assert_eq!(lottery.last_ticket_id, MAX_GIFT_RECIPIENTS as u32);
+    for recipient in &batch {
+        assert_eq!(
+            load_wallet_tickets(&svm, lottery_id, recipient).tickets_bought,
+            1
+        );
+    }
+}
@@ -0,0 +1,201 @@
+#![cfg(test)]
+
+mod common;
+
+use anchor_lang::prelude::*;
+use common::*;
+use litesvm::LiteSVM;
+use lottery::{LotteryStatus, TicketPDA};
+use lottery_client::{instructions, pda};
+use solana_signer::Signer;
+
+fn listing_pda(lottery_id: u32, ticket_id: u32) -> Pubkey {
+    pda::listing(&pda::ticket(&pda::lottery(lottery_id), ticket_id))
+}
+
+fn load_ticket(svm: &LiteSVM, lottery_id: u32, ticket_id: u32) -> TicketPDA {
+    load(svm, &pda::ticket(&pda::lottery(lottery_id), ticket_id))
+}
+
+#[test]
+fn test_transferred_ticket_is_refunded_to_its_new_owner() {
+    let (mut svm, lottery_id, authority, buyers) = setup_lottery(lottery_params(), 2);
+    let (owner, stranger) = (&buyers[0], &buyers[1]);
+    let recipient = Pubkey::new_unique();
+
+    let instruction =
+        instructions::transfer_ticket(lottery_id, 1, &stranger.pubkey(), stranger.pubkey());
+    assert_fails_with(&mut svm, instruction, stranger, "UnauthorizedAction");
+
+    send(
+        &mut svm,
+        instructions::transfer_ticket(lottery_id, 1, &owner.pubkey(), recipient),
+        owner,
+    );
+    assert_eq!(load_ticket(&svm, lottery_id, 1).owner, recipient);
+    let instruction = instructions::transfer_ticket(lottery_id, 1, &owner.pubkey(), owner.pubkey());
+    assert_fails_with(&mut svm, instruction, owner, "UnauthorizedAction");
+
+    let instruction =
+        instructions::cancel_lottery(lottery_id, &authority.pubkey(), &authority.pubkey(), None);
+    send(&mut svm, instruction, &authority);
+    assert_eq!(
+        load_lottery(&svm, lottery_id).status,
+        LotteryStatus::Cancelled
+    );
+
+    let instruction = instructions::refund_ticket(lottery_id, 1, &owner.pubkey());
+    assert_fails_with(&mut svm, instruction, owner, "UnauthorizedAction");
+    send(
+        &mut svm,
+        instructions::refund_ticket(lottery_id, 1, &recipient),
+        owner,
+    );
+    let ticket_rent = svm.minimum_balance_for_rent_exemption(8 + TicketPDA::INIT_SPACE);
+    assert_eq!(
+        svm.get_balance(&recipient).unwrap(),
+        TICKET_PRICE + ticket_rent
+    );
+}
+
+#[test]
+fn test_listed_ticket_is_bought_at_its_price() {
+    let (mut svm, lottery_id, _authority, buyers) = setup_lottery(lottery_params(), 2);
+    let (seller, buyer) = (&buyers[0], &buyers[1]);
+    let price = 3 * TICKET_PRICE;
+
+    let instruction = instructions::list_ticket(lottery_id, 1, &buyer.pubkey(), price);
+    assert_fails_with(&mut svm, instruction, buyer, "UnauthorizedAction");
+    send(
+        &mut svm,
+        instructions::list_ticket(lottery_id, 1, &seller.pubkey(), price),
+        seller,
+    );
+
+    // A purchase at another price than the listing's is rejected
+    let instruction = instructions::buy_listed_ticket(
+        lottery_id,
+        1,
+        &seller.pubkey(),
+        &buyer.pubkey(),
+        price - 1,
+    );
+    assert_fails_with(&mut svm, instruction, buyer, "ListingPriceChanged");
+
+    let listing_rent = svm.get_balance(&listing_pda(lottery_id, 1)).unwrap();
+    let seller_balance = svm.get_balance(&seller.pubkey()).unwrap();
+    let buyer_balance = svm.get_balance(&buyer.pubkey()).unwrap();
+    send(
+        &mut svm,
+        instructions::buy_listed_ticket(lottery_id, 1, &seller.pubkey(), &buyer.pubkey(), price),
+        buyer,
+    );
+    assert_eq!(load_ticket(&svm, lottery_id, 1).owner, buyer.pubkey());
+    assert!(is_closed(&svm, &listing_pda(lottery_id, 1)));
+    assert_eq!(
+        svm.get_balance(&seller.pubkey()).unwrap(),
+        seller_balance + price + listing_rent
+    );
+    assert_eq!(
+        svm.get_balance(&buyer.pubkey()).unwrap(),
+        buyer_balance - price - TX_FEE
+    );
+}
+
+#[test]
+fn test_seller_cancels_a_listing() {
+    let (mut svm, lottery_id, _authority, buyers) = setup_lottery(lottery_params(), 2);
+    let seller = &buyers[0];
+    send(
+        &mut svm,
+        instructions::list_ticket(lottery_id, 1, &seller.pubkey(), TICKET_PRICE),
+        seller,
+    );
+
+    let listing_rent = svm.get_balance(&listing_pda(lottery_id, 1)).unwrap();
+    let seller_balance = svm.get_balance(&seller.pubkey()).unwrap();
+    send(
+        &mut svm,
+        instructions::cancel_listing(lottery_id, 1, &seller.pubkey(), &seller.pubkey()),
+        seller,
+    );
+    assert!(is_closed(&svm, &listing_pda(lottery_id, 1)));
+    assert_eq!(
+        svm.get_balance(&seller.pubkey()).unwrap(),
+        seller_balance + listing_rent - TX_FEE
+    );
+    assert_eq!(load_ticket(&svm, lottery_id, 1).owner, seller.pubkey());
+}
+
+#[test]
+fn test_non_seller_cannot_cancel_a_live_listing() {
+    let (mut svm, lottery_id, _authority, buyers) = setup_lottery(lottery_params(), 2);
+    let (seller, stranger) = (&buyers[0], &buyers[1]);
+    send(
+        &mut svm,
+        instructions::list_ticket(lottery_id, 1, &seller.pubkey(), TICKET_PRICE),
+        seller,
+    );
+
+    // The listing can still be bought, so only its seller may withdraw it
+    let instruction =
+        instructions::cancel_listing(lottery_id, 1, &seller.pubkey(), &stranger.pubkey());
+    assert_fails_with(&mut svm, instruction, stranger, "UnauthorizedAction");
+    // Naming itself as the seller doesn't help, the listing records the real one
+    let instruction =
+        instructions::cancel_listing(lottery_id, 1, &stranger.pubkey(), &stranger.pubkey());
+    assert_fails_with(&mut svm, instruction, stranger, "ConstraintHasOne");
+
+    assert!(!is_closed(&svm, &listing_pda(lottery_id, 1)));
+    send(
+        &mut svm,
+        instructions::buy_listed_ticket(
+            lottery_id,
+            1,
+            &seller.pubkey(),
+            &stranger.pubkey(),
+            TICKET_PRICE,
+        ),
+        stranger,
+    );
+    assert_eq!(load_ticket(&svm, lottery_id, 1).owner, stranger.pubkey());
+}
+
+#[test]
+fn test_listing_cannot_be_bought_after_the_draw() {
+    let (mut svm, lottery_id, authority, buyers) = setup_lottery(lottery_params(), 2);
+    let (seller, buyer) = (&buyers[0], &buyers[1]);
+    send(
+        &mut svm,
+        instructions::list_ticket(lottery_id, 1, &seller.pubkey(), TICKET_PRICE),
+        seller,
+    );
+    draw(&mut svm, lottery_id, &authority);
+    assert!(load_lottery(&svm, lottery_id).is_drawn());
+
+    let instruction = instructions::buy_listed_ticket(
+        lottery_id,
+        1,
+        &seller.pubkey(),
+        &buyer.pubkey(),
+        TICKET_PRICE,
+    );
+    assert_fails_with(&mut svm, instruction, buyer, "ListingClosed");
+    assert_eq!(load_ticket(&svm, lottery_id, 1).owner, seller.pubkey());
+    let instruction = instructions::list_ticket(lottery_id, 2, &buyer.pubkey(), TICKET_PRICE);
+    assert_fails_with(&mut svm, instruction, buyer, "ListingClosed");
+
+    // The dead listing can be cleaned up by anyone, its rent goes back to the seller
+    let listing_rent = svm.get_balance(&listing_pda(lottery_id, 1)).unwrap();
+    let seller_balance = svm.get_balance(&seller.pubkey()).unwrap();
+    send(
+        &mut svm,
+        instructions::cancel_listing(lottery_id, 1, &seller.pubkey(), &buyer.pubkey()),
+        buyer,
+    );
+    assert!(is_closed(&svm, &listing_pda(lottery_id, 1)));
+    assert_eq!(
+        svm.get_balance(&seller.pubkey()).unwrap(),
+        seller_balance + listing_rent
+    );
+}
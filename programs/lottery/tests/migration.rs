@@ -0,0 +1,221 @@
+#![cfg(test)]
+
+use anchor_lang::solana_program::{instruction::Instruction, system_program};
+use anchor_lang::{prelude::*, AccountDeserialize, Discriminator, InstructionData};
+use litesvm::LiteSVM;
+use lottery::{
+    self, id, GameMode, LotteryPDA, LotteryStatus, PlayerStats, PrizeTier, TicketPDA, Versioned,
+    LOTTERY_SEED, MASTER_PDA_SEED, MAX_NUMBER_PICKS, MAX_PRIZE_TIERS, PLAYER_STATS_SEED,
+    TICKET_SEED, TREASURY_SEED,
+};
+use solana_keypair::Keypair;
+use solana_pubkey::Pubkey;
+use solana_signer::Signer;
+use solana_transaction::Transaction;
+
+const TICKET_PRICE: u64 = 10_000_000;
+
+fn send(svm: &mut LiteSVM, instruction: Instruction, signer: &Keypair) {
+    let mut transaction = Transaction::new_with_payer(&[instruction], Some(&signer.pubkey()));
+    transaction.sign(&[signer], svm.latest_blockhash());
+    let result = svm.send_transaction(transaction);
+    assert!(result.is_ok(), "Transaction failed: {:?}", result.err());
+}
+
+/// Stores `data` as a program account, funded for its rent plus `extra_lamports`
+fn write_raw_account(svm: &mut LiteSVM, address: Pubkey, data: Vec<u8>, extra_lamports: u64) {
+    let lamports = svm.minimum_balance_for_rent_exemption(data.len()) + extra_lamports;
+    svm.airdrop(&address, lamports).unwrap();
+    let mut account = svm.get_account(&address).unwrap();
+    account.owner = id();
+    account.data = data;
+    svm.set_account(address, account).unwrap();
+}
+
+fn migrate(svm: &mut LiteSVM, account: Pubkey, payer: &Keypair) {
+    let instruction = Instruction {
+        program_id: id(),
+        accounts: lottery::accounts::MigrateAccount {
+            account,
+            payer: payer.pubkey(),
+            system_program: system_program::id(),
+        }
+        .to_account_metas(None),
+        data: lottery::instruction::MigrateAccount {}.data(),
+    };
+    send(svm, instruction, payer);
+}
+
+fn serialized<T: AccountSerialize>(account: &T) -> Vec<u8> {
+    let mut data = Vec::new();
+    account.try_serialize(&mut data).unwrap();
+    data
+}
+
+#[test]
+fn test_baseline_lottery_and_ticket_migrate() {
+    let mut svm = LiteSVM::new();
+    svm.add_program(
+        lottery::ID,
+        include_bytes!("../../../target/deploy/lottery.so"),
+    );
+    let payer = Keypair::new();
+    svm.airdrop(&payer.pubkey(), 1_000_000_000_000).unwrap();
+    let authority = Pubkey::new_unique();
+    let owner = Keypair::new();
+    svm.airdrop(&owner.pubkey(), 1_000_000_000).unwrap();
+
+    // A baseline lottery: three tickets sold, ticket 2 drawn and not claimed yet. It was
+    // allocated as `8 + size_of::<LotteryPDA>()`, 64 bytes with Rust's padding.
+    let lottery_pda = Pubkey::find_program_address(&[LOTTERY_SEED, &7u32.to_le_bytes()], &id()).0;
+    let mut data = LotteryPDA::DISCRIMINATOR.to_vec();
+    data.extend_from_slice(&7u32.to_le_bytes()); // id
+    data.extend_from_slice(authority.as_ref());
+    data.extend_from_slice(&TICKET_PRICE.to_le_bytes());
+    data.extend_from_slice(&3u32.to_le_bytes()); // last_ticket_id
+    data.push(1); // winner_ticket_id: Some(2)
+    data.extend_from_slice(&2u32.to_le_bytes());
+    data.push(0); // claimed
+    data.resize(8 + 64, 0);
+    write_raw_account(&mut svm, lottery_pda, data, 3 * TICKET_PRICE);
+
+    // A baseline ticket, exactly `8 + size_of::<TicketPDA>()` bytes
+    let ticket_pda = Pubkey::find_program_address(
+        &[TICKET_SEED, lottery_pda.as_ref(), &2u32.to_le_bytes()],
+        &id(),
+    )
+    .0;
+    let mut data = TicketPDA::DISCRIMINATOR.to_vec();
+    data.extend_from_slice(&7u32.to_le_bytes()); // lottery_id
+    data.extend_from_slice(&2u32.to_le_bytes()); // ticket_id
+    data.extend_from_slice(owner.pubkey().as_ref());
+    write_raw_account(&mut svm, ticket_pda, data, 0);
+
+    migrate(&mut svm, lottery_pda, &payer);
+    migrate(&mut svm, ticket_pda, &payer);
+
+    let mut prize_tiers = [PrizeTier::default(); MAX_PRIZE_TIERS];
+    prize_tiers[0] = PrizeTier {
+        share_bps: 10_000,
+        winner_ticket_id: 2,
+        claimed: false,
+    };
+    let expected_lottery = LotteryPDA {
+        id: 7,
+        authority,
+        ticket_price_lamports: TICKET_PRICE,
+        last_ticket_id: 3,
+        prize_tiers,
+        prize_tier_count: 1,
+        randomness_commitment: [0; 32],
+        sales_closed_slot: None,
+        draw_seed: [0; 32],
+        reveal_forfeited: false,
+        entropy_accumulator: [0; 32],
+        entropy_slot: 0,
+        sale_start_ts: 0,
+        sale_end_ts: i64::MAX,
+        draw_after_ts: 0,
+        fee_bps: 0,
+        ticket_mint: None,
+        min_tickets: 1,
+        status: LotteryStatus::Active,
+        max_tickets: 0,
+        max_tickets_per_wallet: 0,
+        tickets_refunded: 0,
+        claim_deadline_ts: i64::MAX,
+        jackpot_lamports: 0,
+        pending_authority: None,
+        version: LotteryPDA::VERSION,
+        nft_tickets: false,
+        referral_bps: 0,
+        referral_lamports: 0,
+        referral_claimed_lamports: 0,
+        carry_over_bps: 0,
+        carried_over: false,
+        revealed_secret: [0; 32],
+        crank_bounty_lamports: 0,
+        crank_bounty_paid_lamports: 0,
+        sales_lamports: 3 * TICKET_PRICE,
+        prize_pool_lamports: 3 * TICKET_PRICE,
+        prizes_paid_lamports: 0,
+        game_mode: GameMode::TicketDraw,
+        numbers_per_ticket: 0,
+        max_number: 0,
+        winning_numbers: [0; MAX_NUMBER_PICKS],
+        drawn_ts: 0,
+        match_winner_counts: [0; MAX_PRIZE_TIERS],
+        prize_mint: None,
+        prize_amount: 0,
+        prize_deposited: false,
+        proceeds_withdrawn: false,
+    };
+    let lottery_account = svm.get_account(&lottery_pda).unwrap();
+    assert_eq!(lottery_account.data.len(), 8 + LotteryPDA::INIT_SPACE);
+    let expected_data = serialized(&expected_lottery);
+    assert_eq!(
+        &lottery_account.data[..expected_data.len()],
+        &expected_data[..]
+    );
+    assert_eq!(
+        lottery_account.lamports,
+        svm.minimum_balance_for_rent_exemption(lottery_account.data.len()) + 3 * TICKET_PRICE
+    );
+
+    let expected_ticket = TicketPDA {
+        lottery_id: 7,
+        ticket_id: 2,
+        owner: owner.pubkey(),
+        version: TicketPDA::VERSION,
+        numbers: [0; MAX_NUMBER_PICKS],
+        match_registered: false,
+        match_claimed: false,
+    };
+    let ticket_account = svm.get_account(&ticket_pda).unwrap();
+    assert_eq!(ticket_account.data.len(), 8 + TicketPDA::INIT_SPACE);
+    assert_eq!(ticket_account.data, serialized(&expected_ticket));
+
+    // The migrated winner claims the whole pot, there was no fee back then
+    let treasury_pda = Pubkey::find_program_address(&[TREASURY_SEED], &id()).0;
+    let instruction = Instruction {
+        program_id: id(),
+        accounts: lottery::accounts::InitMaster {
+            master_pda: Pubkey::find_program_address(&[MASTER_PDA_SEED], &id()).0,
+            treasury_pda,
+            payer: payer.pubkey(),
+            system_program: system_program::id(),
+        }
+        .to_account_metas(None),
+        data: lottery::instruction::InitMaster {}.data(),
+    };
+    send(&mut svm, instruction, &payer);
+    let instruction = Instruction {
+        program_id: id(),
+        accounts: lottery::accounts::ClaimPrize {
+            lottery_pda,
+            winner_ticket: Some(ticket_pda),
+            ticket_ledger: None,
+            treasury_pda,
+            player_stats: Pubkey::find_program_address(
+                &[PLAYER_STATS_SEED, owner.pubkey().as_ref()],
+                &id(),
+            )
+            .0,
+            winner: owner.pubkey(),
+            system_program: system_program::id(),
+        }
+        .to_account_metas(None),
+        data: lottery::instruction::ClaimPrize { tier: 0 }.data(),
+    };
+    let owner_start = svm.get_balance(&owner.pubkey()).unwrap();
+    send(&mut svm, instruction, &owner);
+    let stats_rent = svm.minimum_balance_for_rent_exemption(8 + PlayerStats::INIT_SPACE);
+    assert_eq!(
+        svm.get_balance(&owner.pubkey()).unwrap(),
+        owner_start + 3 * TICKET_PRICE - stats_rent - 5_000
+    );
+    let lottery_account = svm.get_account(&lottery_pda).unwrap();
+    let lottery = LotteryPDA::try_deserialize(&mut lottery_account.data.as_slice()).unwrap();
+    assert!(lottery.prize_tiers[0].claimed);
+    assert_eq!(lottery.prizes_paid_lamports, 3 * TICKET_PRICE);
+}
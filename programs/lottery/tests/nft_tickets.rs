@@ -0,0 +1,204 @@
+#![cfg(test)]
+
+mod common;
+
+use anchor_lang::{prelude::*, solana_program::instruction::Instruction};
+use anchor_spl::{associated_token::spl_associated_token_account, token_2022::spl_token_2022};
+use common::*;
+use litesvm::LiteSVM;
+use lottery::{CreateLotteryParams, LotteryStatus};
+use lottery_client::{instructions, pda};
+use solana_keypair::Keypair;
+use solana_signer::Signer;
+
+/// The holder's Token-2022 account for the NFT of a ticket
+fn nft_account(lottery_id: u32, ticket_id: u32, holder: &Pubkey) -> Pubkey {
+    pda::token_account(
+        holder,
+        &nft_mint(lottery_id, ticket_id),
+        &spl_token_2022::ID,
+    )
+}
+
+fn nft_mint(lottery_id: u32, ticket_id: u32) -> Pubkey {
+    pda::ticket_nft_mint(&pda::lottery(lottery_id), ticket_id)
+}
+
+/// Sets up the master PDA and a committed single-tier lottery with NFT tickets, returning its
+/// authority
+fn setup() -> (LiteSVM, u32, Keypair) {
+    let (mut svm, authority) = new_svm();
+    let params = CreateLotteryParams {
+        nft_tickets: true,
+        ..lottery_params()
+    };
+    let lottery_id = create_lottery(&mut svm, &authority, params);
+    (svm, lottery_id, authority)
+}
+
+fn buy_nft_ticket_instruction(lottery_id: u32, ticket_id: u32, buyer: &Keypair) -> Instruction {
+    instructions::buy_nft_ticket(
+        lottery_id,
+        ticket_id,
+        &buyer.pubkey(),
+        None,
+        &spl_token_2022::ID,
+        None,
+    )
+}
+
+/// Gives the NFT of `ticket_id` from `from` to a new wallet, which is returned
+fn give_nft(svm: &mut LiteSVM, lottery_id: u32, ticket_id: u32, from: &Keypair) -> Keypair {
+    let holder = funded_wallet(svm, 1_000_000_000);
+    let mint = nft_mint(lottery_id, ticket_id);
+    let instructions = [
+        spl_associated_token_account::instruction::create_associated_token_account(
+            &from.pubkey(),
+            &holder.pubkey(),
+            &mint,
+            &spl_token_2022::ID,
+        ),
+        spl_token_2022::instruction::transfer_checked(
+            &spl_token_2022::ID,
+            &nft_account(lottery_id, ticket_id, &from.pubkey()),
+            &mint,
+            &nft_account(lottery_id, ticket_id, &holder.pubkey()),
+            &from.pubkey(),
+            &[],
+            1,
+            0,
+        )
+        .unwrap(),
+    ];
+    send_all(svm, &instructions, &[from]);
+    holder
+}
+
+fn claim_prize_nft_instruction(lottery_id: u32, ticket_id: u32, winner: &Keypair) -> Instruction {
+    instructions::claim_prize_nft(
+        lottery_id,
+        0,
+        ticket_id,
+        &winner.pubkey(),
+        &spl_token_2022::ID,
+    )
+}
+
+fn refund_nft_ticket_instruction(
+    lottery_id: u32,
+    ticket_id: u32,
+    owner: &Pubkey,
+    holder: &Keypair,
+) -> Instruction {
+    instructions::refund_nft_ticket(
+        lottery_id,
+        ticket_id,
+        owner,
+        &holder.pubkey(),
+        &spl_token_2022::ID,
+    )
+}
+
+#[test]
+fn test_buy_nft_ticket_mints_a_single_token() {
+    let (mut svm, lottery_id, _authority) = setup();
+    let buyer = funded_wallet(&mut svm, 10_000_000_000);
+
+    send(
+        &mut svm,
+        buy_nft_ticket_instruction(lottery_id, 1, &buyer),
+        &buyer,
+    );
+    assert_eq!(
+        token_balance(&svm, &nft_account(lottery_id, 1, &buyer.pubkey())),
+        1
+    );
+    assert_eq!(mint_supply(&svm, &nft_mint(lottery_id, 1)), 1);
+    assert_eq!(load_lottery(&svm, lottery_id).last_ticket_id, 1);
+
+    // Plain tickets can't be bought in a lottery with NFT tickets
+    let instruction = instructions::buy_ticket(lottery_id, 2, &buyer.pubkey(), None, None, vec![]);
+    assert_fails_with(&mut svm, instruction, &buyer, "WrongTicketKind");
+}
+
+#[test]
+fn test_nft_holder_claims_the_prize_and_the_nft_is_burned() {
+    let (mut svm, lottery_id, authority) = setup();
+    let buyers = [
+        funded_wallet(&mut svm, 10_000_000_000),
+        funded_wallet(&mut svm, 10_000_000_000),
+    ];
+    for (index, buyer) in buyers.iter().enumerate() {
+        let instruction = buy_nft_ticket_instruction(lottery_id, index as u32 + 1, buyer);
+        send(&mut svm, instruction, buyer);
+    }
+    draw(&mut svm, lottery_id, &authority);
+    let winner_ticket_id = load_lottery(&svm, lottery_id).prize_tiers[0].winner_ticket_id;
+    let buyer = &buyers[winner_ticket_id as usize - 1];
+    let holder = give_nft(&mut svm, lottery_id, winner_ticket_id, buyer);
+
+    // The buyer no longer holds the NFT, so it can't claim
+    let instruction = claim_prize_nft_instruction(lottery_id, winner_ticket_id, buyer);
+    try_send(&mut svm, instruction, buyer).expect_err("Only the holder can claim");
+    assert!(!load_lottery(&svm, lottery_id).prize_tiers[0].claimed);
+
+    let balance = svm.get_balance(&holder.pubkey()).unwrap();
+    let instruction = claim_prize_nft_instruction(lottery_id, winner_ticket_id, &holder);
+    send(&mut svm, instruction, &holder);
+    assert!(load_lottery(&svm, lottery_id).prize_tiers[0].claimed);
+    assert!(svm.get_balance(&holder.pubkey()).unwrap() > balance + TICKET_PRICE);
+    assert_eq!(
+        token_balance(
+            &svm,
+            &nft_account(lottery_id, winner_ticket_id, &holder.pubkey())
+        ),
+        0
+    );
+    assert_eq!(
+        mint_supply(&svm, &nft_mint(lottery_id, winner_ticket_id)),
+        0
+    );
+}
+
+#[test]
+fn test_cancelled_nft_ticket_refunds_its_holder() {
+    let (mut svm, lottery_id, authority) = setup();
+    let buyer = funded_wallet(&mut svm, 10_000_000_000);
+    send(
+        &mut svm,
+        buy_nft_ticket_instruction(lottery_id, 1, &buyer),
+        &buyer,
+    );
+    let holder = give_nft(&mut svm, lottery_id, 1, &buyer);
+
+    let instruction =
+        instructions::cancel_lottery(lottery_id, &authority.pubkey(), &authority.pubkey(), None);
+    send(&mut svm, instruction, &authority);
+    assert_eq!(
+        load_lottery(&svm, lottery_id).status,
+        LotteryStatus::Cancelled
+    );
+
+    // The buyer gave the NFT away, so the refund is the holder's
+    let instruction = refund_nft_ticket_instruction(lottery_id, 1, &buyer.pubkey(), &buyer);
+    try_send(&mut svm, instruction, &buyer).expect_err("Only the holder is refunded");
+
+    let buyer_balance = svm.get_balance(&buyer.pubkey()).unwrap();
+    let holder_balance = svm.get_balance(&holder.pubkey()).unwrap();
+    let ticket_rent = svm
+        .get_account(&pda::ticket(&pda::lottery(lottery_id), 1))
+        .unwrap()
+        .lamports;
+    let instruction = refund_nft_ticket_instruction(lottery_id, 1, &buyer.pubkey(), &holder);
+    send(&mut svm, instruction, &holder);
+    assert_eq!(
+        svm.get_balance(&holder.pubkey()).unwrap(),
+        holder_balance + TICKET_PRICE - TX_FEE
+    );
+    assert_eq!(
+        svm.get_balance(&buyer.pubkey()).unwrap(),
+        buyer_balance + ticket_rent
+    );
+    assert_eq!(mint_supply(&svm, &nft_mint(lottery_id, 1)), 0);
+    assert_eq!(load_lottery(&svm, lottery_id).tickets_refunded, 1);
+}
@@ -0,0 +1,163 @@
+#![cfg(test)]
+
+mod common;
+
+use anchor_lang::solana_program::instruction::Instruction;
+use common::*;
+use litesvm::LiteSVM;
+use lottery::{CreateLotteryParams, GameMode, TicketPDA};
+use lottery_client::{instructions, pda};
+use solana_keypair::Keypair;
+use solana_signer::Signer;
+
+/// With two picks out of three, every ticket matches at least one winning number
+const NUMBERS_PER_TICKET: u8 = 2;
+const MAX_NUMBER: u8 = 3;
+const TIER_SHARES_BPS: [u16; 2] = [7_000, 3_000];
+
+fn load_ticket(svm: &LiteSVM, lottery_id: u32, ticket_id: u32) -> TicketPDA {
+    load(svm, &pda::ticket(&pda::lottery(lottery_id), ticket_id))
+}
+
+fn buy_instruction(
+    lottery_id: u32,
+    ticket_id: u32,
+    buyer: &Keypair,
+    numbers: Vec<u8>,
+) -> Instruction {
+    instructions::buy_ticket(lottery_id, ticket_id, &buyer.pubkey(), None, None, numbers)
+}
+
+/// Opens a committed two-tier numbers lottery
+fn setup() -> (LiteSVM, u32, Keypair) {
+    let (mut svm, authority) = new_svm();
+    let params = CreateLotteryParams {
+        prize_shares_bps: TIER_SHARES_BPS.to_vec(),
+        game_mode: GameMode::Numbers,
+        numbers_per_ticket: NUMBERS_PER_TICKET,
+        max_number: MAX_NUMBER,
+        ..lottery_params()
+    };
+    let lottery_id = create_lottery(&mut svm, &authority, params);
+    (svm, lottery_id, authority)
+}
+
+#[test]
+fn test_picks_are_validated() {
+    let (mut svm, lottery_id, _authority) = setup();
+    let buyer = funded_wallet(&mut svm, 10_000_000_000);
+
+    for numbers in [
+        vec![],
+        vec![1],
+        vec![1, 2, 3],
+        vec![0, 1],
+        vec![1, 4],
+        vec![2, 2],
+    ] {
+        let instruction = buy_instruction(lottery_id, 1, &buyer, numbers);
+        assert_fails_with(&mut svm, instruction, &buyer, "InvalidNumbers");
+    }
+    assert_eq!(load_lottery(&svm, lottery_id).last_ticket_id, 0);
+
+    // Picks are stored sorted
+    send(
+        &mut svm,
+        buy_instruction(lottery_id, 1, &buyer, vec![3, 1]),
+        &buyer,
+    );
+    let ticket = load_ticket(&svm, lottery_id, 1);
+    assert_eq!(ticket.numbers[..2], [1, 3]);
+}
+
+#[test]
+fn test_registered_matches_split_their_tier() {
+    let (mut svm, lottery_id, authority) = setup();
+    // Every possible pick is sold: one ticket matches both winning numbers, the other two one
+    let mut owners = Vec::new();
+    for (ticket_id, numbers) in (1..).zip([[1, 2], [1, 3], [2, 3]]) {
+        let owner = funded_wallet(&mut svm, 10_000_000_000);
+        send(
+            &mut svm,
+            buy_instruction(lottery_id, ticket_id, &owner, numbers.to_vec()),
+            &owner,
+        );
+        owners.push(owner);
+    }
+
+    let instruction = instructions::register_match(lottery_id, 1);
+    assert_fails_with(&mut svm, instruction, &authority, "WinnerNotChosenYet");
+
+    draw(&mut svm, lottery_id, &authority);
+    let lottery = load_lottery(&svm, lottery_id);
+    let tiers: Vec<u8> = (1..=3)
+        .map(|ticket_id| {
+            lottery
+                .match_tier(&load_ticket(&svm, lottery_id, ticket_id))
+                .unwrap()
+        })
+        .collect();
+    let jackpot_ticket = tiers.iter().position(|&tier| tier == 0).unwrap() as u32 + 1;
+    let runner_ups: Vec<u32> = (1..=3).filter(|&id| id != jackpot_ticket).collect();
+    assert_eq!(tiers.iter().filter(|&&tier| tier == 1).count(), 2);
+
+    // Anyone registers matches, once each. Only one of the runner-ups is registered in time.
+    send(
+        &mut svm,
+        instructions::register_match(lottery_id, jackpot_ticket),
+        &authority,
+    );
+    send(
+        &mut svm,
+        instructions::register_match(lottery_id, runner_ups[0]),
+        &authority,
+    );
+    let instruction = instructions::register_match(lottery_id, jackpot_ticket);
+    assert_fails_with(&mut svm, instruction, &authority, "MatchAlreadyRegistered");
+
+    let jackpot_owner = &owners[jackpot_ticket as usize - 1];
+    let instruction =
+        instructions::claim_match_prize(lottery_id, jackpot_ticket, &jackpot_owner.pubkey());
+    assert_fails_with(
+        &mut svm,
+        instruction,
+        jackpot_owner,
+        "MatchRegistrationOpen",
+    );
+
+    set_time(&mut svm, lottery.match_registration_end_ts(), DRAW_SLOT + 2);
+    let instruction = instructions::register_match(lottery_id, runner_ups[1]);
+    assert_fails_with(&mut svm, instruction, &authority, "MatchRegistrationClosed");
+    let late_owner = &owners[runner_ups[1] as usize - 1];
+    let instruction =
+        instructions::claim_match_prize(lottery_id, runner_ups[1], &late_owner.pubkey());
+    assert_fails_with(&mut svm, instruction, late_owner, "MatchNotRegistered");
+
+    let pool = lottery.prize_pool_lamports;
+    assert_eq!(pool, 3 * TICKET_PRICE);
+    for (ticket_id, share_bps) in [
+        (jackpot_ticket, TIER_SHARES_BPS[0]),
+        (runner_ups[0], TIER_SHARES_BPS[1]),
+    ] {
+        let owner = &owners[ticket_id as usize - 1];
+        let balance = svm.get_balance(&owner.pubkey()).unwrap();
+        send(
+            &mut svm,
+            instructions::claim_match_prize(lottery_id, ticket_id, &owner.pubkey()),
+            owner,
+        );
+        assert_eq!(
+            svm.get_balance(&owner.pubkey()).unwrap(),
+            balance + pool * share_bps as u64 / 10_000 - TX_FEE
+        );
+        assert!(load_ticket(&svm, lottery_id, ticket_id).match_claimed);
+    }
+    let instruction =
+        instructions::claim_match_prize(lottery_id, jackpot_ticket, &jackpot_owner.pubkey());
+    assert_fails_with(&mut svm, instruction, jackpot_owner, "TicketAlreadyClaimed");
+
+    // Only the owner can claim a match
+    let instruction =
+        instructions::claim_match_prize(lottery_id, runner_ups[1], &jackpot_owner.pubkey());
+    assert_fails_with(&mut svm, instruction, jackpot_owner, "UnauthorizedAction");
+}
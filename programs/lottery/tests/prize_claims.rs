@@ -0,0 +1,193 @@
+#![cfg(test)]
+
+mod common;
+
+use anchor_lang::solana_program::instruction::Instruction;
+use common::*;
+use litesvm::LiteSVM;
+use lottery::{CreateLotteryParams, PlayerStats};
+use lottery_client::{
+    instructions::{self, WinningTicket},
+    pda,
+};
+use solana_keypair::Keypair;
+use solana_signer::Signer;
+
+/// Sent to the lottery PDA outside of ticket sales
+const STRAY_LAMPORTS: u64 = 123_456;
+
+/// Runs a single-tier lottery up to its draw: three buyers with a ticket each, and stray lamports
+/// sent to the lottery PDA. Returns the buyers, ticket `n` belongs to `buyers[n - 1]`.
+fn setup_drawn_lottery() -> (LiteSVM, u32, Vec<Keypair>) {
+    let (mut svm, lottery_id, authority, buyers) = setup_lottery(lottery_params(), 3);
+    svm.airdrop(&pda::lottery(lottery_id), STRAY_LAMPORTS)
+        .unwrap();
+    draw(&mut svm, lottery_id, &authority);
+    (svm, lottery_id, buyers)
+}
+
+fn claim_instruction(lottery_id: u32, winner_ticket_id: u32, winner: &Keypair) -> Instruction {
+    instructions::claim_prize(
+        lottery_id,
+        0,
+        WinningTicket::Pda(winner_ticket_id),
+        &winner.pubkey(),
+    )
+}
+
+#[test]
+fn test_claim_pays_the_pot_and_keeps_rent() {
+    let (mut svm, lottery_id, buyers) = setup_drawn_lottery();
+    let lottery_pda = pda::lottery(lottery_id);
+    let lottery = load_lottery(&svm, lottery_id);
+    assert_eq!(lottery.sales_lamports, 3 * TICKET_PRICE);
+    assert_eq!(lottery.prize_pool_lamports, 3 * TICKET_PRICE);
+
+    let winner_ticket_id = lottery.prize_tiers[0].winner_ticket_id;
+    let winner = &buyers[winner_ticket_id as usize - 1];
+    let treasury_start = svm.get_balance(&pda::treasury()).unwrap();
+    send(
+        &mut svm,
+        claim_instruction(lottery_id, winner_ticket_id, winner),
+        winner,
+    );
+
+    // Exactly the pot is paid out, the stray lamports and the rent stay behind
+    let lottery_account = svm.get_account(&lottery_pda).unwrap();
+    let rent = svm.minimum_balance_for_rent_exemption(lottery_account.data.len());
+    assert_eq!(lottery_account.lamports, rent + STRAY_LAMPORTS);
+    let lottery = load_lottery(&svm, lottery_id);
+    assert_eq!(lottery.prizes_paid_lamports, lottery.prize_pool_lamports);
+    assert!(lottery.prize_tiers[0].claimed);
+    let fee = lottery.fee_for(3 * TICKET_PRICE);
+    assert_eq!(
+        svm.get_balance(&pda::treasury()).unwrap(),
+        treasury_start + fee
+    );
+
+    let stats: PlayerStats = load(&svm, &pda::player_stats(&winner.pubkey()));
+    assert_eq!(stats.tickets_bought, 1);
+    assert_eq!(stats.lamports_spent, TICKET_PRICE);
+    assert_eq!(stats.lotteries_entered, 1);
+    assert_eq!(stats.wins, 1);
+    assert_eq!(stats.lamports_won, 3 * TICKET_PRICE - fee);
+}
+
+#[test]
+fn test_claim_never_dips_into_rent() {
+    let (mut svm, lottery_id, buyers) = setup_drawn_lottery();
+    let lottery_pda = pda::lottery(lottery_id);
+    let lottery = load_lottery(&svm, lottery_id);
+    let winner_ticket_id = lottery.prize_tiers[0].winner_ticket_id;
+    let winner = &buyers[winner_ticket_id as usize - 1];
+
+    // Leave the lottery one lamport short of its rent plus the pot
+    let mut lottery_account = svm.get_account(&lottery_pda).unwrap();
+    let rent = svm.minimum_balance_for_rent_exemption(lottery_account.data.len());
+    lottery_account.lamports = rent + lottery.prize_pool_lamports - 1;
+    svm.set_account(lottery_pda, lottery_account).unwrap();
+
+    let result = try_send(
+        &mut svm,
+        claim_instruction(lottery_id, winner_ticket_id, winner),
+        winner,
+    );
+    let logs = result.expect_err("Claim should not take the lottery's rent");
+    assert!(logs.iter().any(|log| log.contains("InsufficientPot")));
+    assert_eq!(
+        svm.get_balance(&lottery_pda).unwrap(),
+        rent + lottery.prize_pool_lamports - 1
+    );
+    assert!(!load_lottery(&svm, lottery_id).prize_tiers[0].claimed);
+}
+
+#[test]
+fn test_player_closes_its_stats_for_their_rent() {
+    let (mut svm, _lottery_id, buyers) = setup_drawn_lottery();
+    let player = &buyers[0];
+    let stats_pda = pda::player_stats(&player.pubkey());
+    let rent = svm.get_balance(&stats_pda).unwrap();
+
+    // Only the player can close its stats
+    let stranger = funded_wallet(&mut svm, 1_000_000_000);
+    let mut instruction = instructions::close_player_stats(&stranger.pubkey());
+    instruction.accounts[0].pubkey = stats_pda;
+    assert_fails_with(&mut svm, instruction, &stranger, "ConstraintSeeds");
+
+    let balance = svm.get_balance(&player.pubkey()).unwrap();
+    send(
+        &mut svm,
+        instructions::close_player_stats(&player.pubkey()),
+        player,
+    );
+    assert!(is_closed(&svm, &stats_pda));
+    assert_eq!(
+        svm.get_balance(&player.pubkey()).unwrap(),
+        balance + rent - TX_FEE
+    );
+
+    // Its next ticket starts the stats over
+    let params = CreateLotteryParams {
+        sale_end_ts: 2 * DRAW_TS,
+        draw_after_ts: 2 * DRAW_TS,
+        claim_deadline_ts: 3 * DRAW_TS,
+        ..lottery_params()
+    };
+    let lottery_id = create_lottery(&mut svm, &buyers[1], params);
+    buy_ticket(&mut svm, lottery_id, player);
+    let stats: PlayerStats = load(&svm, &stats_pda);
+    assert_eq!(stats.tickets_bought, 1);
+    assert_eq!(stats.lotteries_entered, 1);
+    assert_eq!(stats.wins, 0);
+}
+
+#[test]
+fn test_tiers_draw_distinct_winners_sharing_the_pot() {
+    let shares = [5_000, 3_000, 2_000];
+    let (mut svm, lottery_id, authority, buyers) = setup_lottery(
+        CreateLotteryParams {
+            prize_shares_bps: shares.to_vec(),
+            min_tickets: shares.len() as u32,
+            ..lottery_params()
+        },
+        5,
+    );
+    draw(&mut svm, lottery_id, &authority);
+
+    let lottery = load_lottery(&svm, lottery_id);
+    let pot = 5 * TICKET_PRICE;
+    assert_eq!(lottery.prize_pool_lamports, pot);
+    let mut winner_ticket_ids: Vec<u32> = lottery.prize_tiers[..shares.len()]
+        .iter()
+        .map(|tier| tier.winner_ticket_id)
+        .collect();
+    winner_ticket_ids.sort_unstable();
+    winner_ticket_ids.dedup();
+    assert_eq!(winner_ticket_ids.len(), shares.len());
+
+    let mut paid = 0;
+    for (tier, share_bps) in shares.iter().enumerate() {
+        let winner_ticket_id = lottery.prize_tiers[tier].winner_ticket_id;
+        let winner = &buyers[winner_ticket_id as usize - 1];
+        let balance = svm.get_balance(&winner.pubkey()).unwrap();
+        send(
+            &mut svm,
+            instructions::claim_prize(
+                lottery_id,
+                tier as u8,
+                WinningTicket::Pda(winner_ticket_id),
+                &winner.pubkey(),
+            ),
+            winner,
+        );
+        let prize = svm.get_balance(&winner.pubkey()).unwrap() + TX_FEE - balance;
+        assert_eq!(prize, pot * *share_bps as u64 / 10_000);
+        paid += prize;
+    }
+    assert_eq!(paid, pot);
+    let lottery = load_lottery(&svm, lottery_id);
+    assert_eq!(lottery.prizes_paid_lamports, pot);
+    assert!(lottery.prize_tiers[..shares.len()]
+        .iter()
+        .all(|tier| tier.claimed));
+}
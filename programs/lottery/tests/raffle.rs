@@ -0,0 +1,157 @@
+#![cfg(test)]
+
+mod common;
+
+use anchor_lang::{prelude::*, solana_program::instruction::Instruction};
+use anchor_spl::token_2022::spl_token_2022;
+use common::*;
+use litesvm::LiteSVM;
+use lottery::{CreateLotteryParams, LotteryStatus};
+use lottery_client::{
+    instructions::{self, TokenMint, WinningTicket},
+    pda,
+};
+use solana_keypair::Keypair;
+use solana_signer::Signer;
+
+const PRIZE_AMOUNT: u64 = 1_000_000;
+
+/// Sets up the master PDA and a committed raffle for `PRIZE_AMOUNT` of a new mint, which its
+/// authority holds but hasn't deposited yet
+fn setup_raffle(token_program: &Pubkey, transfer_fee: bool) -> (LiteSVM, u32, TokenMint, Keypair) {
+    let (mut svm, authority) = new_svm();
+    let mint = create_mint(&mut svm, &authority, token_program, transfer_fee);
+    mint_tokens(
+        &mut svm,
+        &authority,
+        &mint,
+        token_program,
+        &authority.pubkey(),
+        PRIZE_AMOUNT,
+    );
+    let params = CreateLotteryParams {
+        prize_mint: Some(mint),
+        prize_amount: PRIZE_AMOUNT,
+        ..lottery_params()
+    };
+    let lottery_id = create_lottery(&mut svm, &authority, params);
+    let prize_mint = TokenMint {
+        mint,
+        token_program: *token_program,
+    };
+    (svm, lottery_id, prize_mint, authority)
+}
+
+fn buy_ticket_instruction(lottery_id: u32, ticket_id: u32, buyer: &Keypair) -> Instruction {
+    instructions::buy_ticket(lottery_id, ticket_id, &buyer.pubkey(), None, None, vec![])
+}
+
+fn token_account(owner: &Pubkey, prize_mint: TokenMint) -> Pubkey {
+    pda::token_account(owner, &prize_mint.mint, &prize_mint.token_program)
+}
+
+#[test]
+fn test_raffle_prize_deposit_claim_and_proceeds() {
+    let (mut svm, lottery_id, prize_mint, authority) = setup_raffle(&anchor_spl::token::ID, false);
+    let buyers = [
+        funded_wallet(&mut svm, 10_000_000_000),
+        funded_wallet(&mut svm, 10_000_000_000),
+    ];
+
+    // Nothing can be sold before the prize is in the vault
+    let instruction = buy_ticket_instruction(lottery_id, 1, &buyers[0]);
+    assert_fails_with(&mut svm, instruction, &buyers[0], "PrizeNotDeposited");
+
+    let instruction = instructions::deposit_prize(lottery_id, &authority.pubkey(), prize_mint);
+    send(&mut svm, instruction, &authority);
+    let prize_vault = token_account(&pda::lottery(lottery_id), prize_mint);
+    assert_eq!(token_balance(&svm, &prize_vault), PRIZE_AMOUNT);
+    let instruction = instructions::deposit_prize(lottery_id, &authority.pubkey(), prize_mint);
+    assert_fails_with(&mut svm, instruction, &authority, "PrizeAlreadyDeposited");
+
+    for (index, buyer) in buyers.iter().enumerate() {
+        let instruction = buy_ticket_instruction(lottery_id, index as u32 + 1, buyer);
+        send(&mut svm, instruction, buyer);
+    }
+    let instruction = instructions::withdraw_proceeds(lottery_id, &authority.pubkey());
+    assert_fails_with(&mut svm, instruction, &authority, "WinnerNotChosenYet");
+    draw(&mut svm, lottery_id, &authority);
+
+    let winner_ticket_id = load_lottery(&svm, lottery_id).prize_tiers[0].winner_ticket_id;
+    let winner = &buyers[winner_ticket_id as usize - 1];
+    let loser = &buyers[2 - winner_ticket_id as usize];
+    let winning_ticket = WinningTicket::Pda(winner_ticket_id);
+    let instruction =
+        instructions::claim_raffle_prize(lottery_id, winning_ticket, &loser.pubkey(), prize_mint);
+    assert_fails_with(&mut svm, instruction, loser, "UnauthorizedAction");
+    let instruction =
+        instructions::claim_raffle_prize(lottery_id, winning_ticket, &winner.pubkey(), prize_mint);
+    send(&mut svm, instruction, winner);
+    let winner_prize_account = token_account(&winner.pubkey(), prize_mint);
+    assert_eq!(token_balance(&svm, &winner_prize_account), PRIZE_AMOUNT);
+    assert_eq!(token_balance(&svm, &prize_vault), 0);
+    assert!(load_lottery(&svm, lottery_id).prize_tiers[0].claimed);
+
+    // The ticket sales go to the authority, the master charges no fee by default
+    let balance = svm.get_balance(&authority.pubkey()).unwrap();
+    let instruction = instructions::withdraw_proceeds(lottery_id, &authority.pubkey());
+    send(&mut svm, instruction, &authority);
+    assert_eq!(
+        svm.get_balance(&authority.pubkey()).unwrap(),
+        balance + 2 * TICKET_PRICE - TX_FEE
+    );
+    assert!(load_lottery(&svm, lottery_id).proceeds_withdrawn);
+    let instruction = instructions::withdraw_proceeds(lottery_id, &authority.pubkey());
+    assert_fails_with(
+        &mut svm,
+        instruction,
+        &authority,
+        "ProceedsAlreadyWithdrawn",
+    );
+}
+
+#[test]
+fn test_cancelled_raffle_returns_its_prize() {
+    let (mut svm, lottery_id, prize_mint, authority) = setup_raffle(&anchor_spl::token::ID, false);
+    let instruction = instructions::deposit_prize(lottery_id, &authority.pubkey(), prize_mint);
+    send(&mut svm, instruction, &authority);
+    let buyer = funded_wallet(&mut svm, 10_000_000_000);
+    send(
+        &mut svm,
+        buy_ticket_instruction(lottery_id, 1, &buyer),
+        &buyer,
+    );
+
+    let authority_prize_account = token_account(&authority.pubkey(), prize_mint);
+    assert_eq!(token_balance(&svm, &authority_prize_account), 0);
+    let instruction = instructions::cancel_lottery(
+        lottery_id,
+        &authority.pubkey(),
+        &authority.pubkey(),
+        Some(prize_mint),
+    );
+    send(&mut svm, instruction, &authority);
+    let lottery = load_lottery(&svm, lottery_id);
+    assert_eq!(lottery.status, LotteryStatus::Cancelled);
+    assert!(!lottery.prize_deposited);
+    assert_eq!(token_balance(&svm, &authority_prize_account), PRIZE_AMOUNT);
+
+    let balance = svm.get_balance(&buyer.pubkey()).unwrap();
+    send(
+        &mut svm,
+        instructions::refund_ticket(lottery_id, 1, &buyer.pubkey()),
+        &authority,
+    );
+    assert!(svm.get_balance(&buyer.pubkey()).unwrap() > balance + TICKET_PRICE);
+}
+
+#[test]
+fn test_deposit_prize_rejects_token_2022_transfer_fees() {
+    let (mut svm, lottery_id, prize_mint, authority) = setup_raffle(&spl_token_2022::ID, true);
+
+    let instruction = instructions::deposit_prize(lottery_id, &authority.pubkey(), prize_mint);
+    assert_fails_with(&mut svm, instruction, &authority, "TransferFeeMint");
+    assert!(!load_lottery(&svm, lottery_id).prize_deposited);
+    let authority_prize_account = token_account(&authority.pubkey(), prize_mint);
+    assert_eq!(token_balance(&svm, &authority_prize_account), PRIZE_AMOUNT);
+}
@@ -0,0 +1,120 @@
+#![cfg(test)]
+
+mod common;
+
+use common::*;
+use litesvm::LiteSVM;
+use lottery::{CreateLotteryParams, ReferralPDA};
+use lottery_client::{instructions, pda};
+use solana_keypair::Keypair;
+use solana_signer::Signer;
+
+const REFERRAL_BPS: u16 = 500;
+
+/// Opens a committed lottery paying `referral_bps` of each referred ticket, and funds a referrer
+fn setup(referral_bps: u16) -> (LiteSVM, u32, Keypair, Keypair) {
+    let (mut svm, authority) = new_svm();
+    let params = CreateLotteryParams {
+        referral_bps,
+        ..lottery_params()
+    };
+    let lottery_id = create_lottery(&mut svm, &authority, params);
+    let referrer = funded_wallet(&mut svm, 10_000_000_000);
+    (svm, lottery_id, authority, referrer)
+}
+
+#[test]
+fn test_referred_tickets_pay_the_referrer_after_the_draw() {
+    let (mut svm, lottery_id, authority, referrer) = setup(REFERRAL_BPS);
+    send(
+        &mut svm,
+        instructions::init_referral(lottery_id, &referrer.pubkey()),
+        &referrer,
+    );
+
+    let mut buyers = Vec::new();
+    for ticket_id in 1..=3 {
+        let buyer = funded_wallet(&mut svm, 10_000_000_000);
+        // The last ticket is bought without a referral
+        let referred = (ticket_id < 3).then(|| referrer.pubkey());
+        let instruction = instructions::buy_ticket(
+            lottery_id,
+            ticket_id,
+            &buyer.pubkey(),
+            referred.as_ref(),
+            None,
+            vec![],
+        );
+        send(&mut svm, instruction, &buyer);
+        buyers.push(buyer);
+    }
+    let share = TICKET_PRICE * REFERRAL_BPS as u64 / 10_000;
+    let referral_pda = pda::referral(&pda::lottery(lottery_id), &referrer.pubkey());
+    assert_eq!(
+        load::<ReferralPDA>(&svm, &referral_pda).accrued_lamports,
+        2 * share
+    );
+    let lottery = load_lottery(&svm, lottery_id);
+    assert_eq!(lottery.referral_lamports, 2 * share);
+    assert_eq!(
+        lottery.pot_lamports().unwrap(),
+        3 * TICKET_PRICE - 2 * share
+    );
+
+    let instruction = instructions::claim_referral_rewards(lottery_id, &referrer.pubkey());
+    assert_fails_with(&mut svm, instruction, &referrer, "WinnerNotChosenYet");
+
+    draw(&mut svm, lottery_id, &authority);
+    let lottery = load_lottery(&svm, lottery_id);
+    assert_eq!(lottery.prize_pool_lamports, 3 * TICKET_PRICE - 2 * share);
+
+    let referral_rent = svm.get_balance(&referral_pda).unwrap();
+    let balance = svm.get_balance(&referrer.pubkey()).unwrap();
+    send(
+        &mut svm,
+        instructions::claim_referral_rewards(lottery_id, &referrer.pubkey()),
+        &referrer,
+    );
+    assert_eq!(
+        svm.get_balance(&referrer.pubkey()).unwrap(),
+        balance + 2 * share + referral_rent - TX_FEE
+    );
+    assert!(is_closed(&svm, &referral_pda));
+    assert_eq!(
+        load_lottery(&svm, lottery_id).referral_claimed_lamports,
+        2 * share
+    );
+}
+
+#[test]
+fn test_referrer_cannot_refer_itself() {
+    let (mut svm, lottery_id, _authority, referrer) = setup(REFERRAL_BPS);
+    send(
+        &mut svm,
+        instructions::init_referral(lottery_id, &referrer.pubkey()),
+        &referrer,
+    );
+
+    let instruction = instructions::buy_ticket(
+        lottery_id,
+        1,
+        &referrer.pubkey(),
+        Some(&referrer.pubkey()),
+        None,
+        vec![],
+    );
+    assert_fails_with(&mut svm, instruction, &referrer, "SelfReferral");
+    assert_eq!(load_lottery(&svm, lottery_id).last_ticket_id, 0);
+
+    // Buying without the referral is fine
+    buy_ticket(&mut svm, lottery_id, &referrer);
+    let referral_pda = pda::referral(&pda::lottery(lottery_id), &referrer.pubkey());
+    assert_eq!(load::<ReferralPDA>(&svm, &referral_pda).accrued_lamports, 0);
+}
+
+#[test]
+fn test_referrals_need_a_referral_share() {
+    let (mut svm, lottery_id, _authority, referrer) = setup(0);
+    let instruction = instructions::init_referral(lottery_id, &referrer.pubkey());
+    assert_fails_with(&mut svm, instruction, &referrer, "ReferralsDisabled");
+}
@@ -0,0 +1,186 @@
+#![cfg(test)]
+
+mod common;
+
+use anchor_lang::solana_program::{
+    hash::{hashv, Hash},
+    slot_hashes::SlotHashes,
+};
+use common::*;
+use litesvm::LiteSVM;
+use lottery::REVEAL_WINDOW_SLOTS;
+use lottery_client::instructions;
+use solana_keypair::Keypair;
+use solana_signer::Signer;
+
+/// Runs a single-tier lottery up to its reveal: three buyers with a ticket each, sales closed
+/// at `DRAW_SLOT`. Returns the authority and the buyers, ticket `n` belongs to `buyers[n - 1]`.
+fn setup_closed_lottery() -> (LiteSVM, u32, Keypair, Vec<Keypair>) {
+    let (mut svm, lottery_id, authority, buyers) = setup_lottery(lottery_params(), 3);
+    close_sales(&mut svm, lottery_id, &authority);
+    (svm, lottery_id, authority, buyers)
+}
+
+#[test]
+fn test_reveal_rejects_a_secret_not_matching_the_commitment() {
+    let (mut svm, lottery_id, authority, _buyers) = setup_closed_lottery();
+    set_time(&mut svm, DRAW_TS, DRAW_SLOT + 1);
+
+    let instruction =
+        instructions::reveal_and_pick_winner(lottery_id, &authority.pubkey(), [8; 32]);
+    assert_fails_with(&mut svm, instruction, &authority, "InvalidReveal");
+    assert!(!load_lottery(&svm, lottery_id).is_drawn());
+
+    send(
+        &mut svm,
+        instructions::reveal_and_pick_winner(lottery_id, &authority.pubkey(), SECRET),
+        &authority,
+    );
+    let lottery = load_lottery(&svm, lottery_id);
+    assert!(lottery.is_drawn());
+    assert_eq!(lottery.revealed_secret, SECRET);
+}
+
+#[test]
+fn test_reveal_rejected_after_the_reveal_window() {
+    let (mut svm, lottery_id, authority, _buyers) = setup_closed_lottery();
+    set_time(&mut svm, DRAW_TS, DRAW_SLOT + REVEAL_WINDOW_SLOTS + 1);
+
+    let instruction = instructions::reveal_and_pick_winner(lottery_id, &authority.pubkey(), SECRET);
+    assert_fails_with(&mut svm, instruction, &authority, "RevealWindowExpired");
+    assert!(!load_lottery(&svm, lottery_id).is_drawn());
+}
+
+#[test]
+fn test_no_ticket_is_sold_before_the_commitment() {
+    let (mut svm, authority) = new_svm();
+    let lottery_id = next_lottery_id(&svm);
+    send(
+        &mut svm,
+        instructions::create_lottery(lottery_id, &authority.pubkey(), lottery_params()),
+        &authority,
+    );
+    let buyer = funded_wallet(&mut svm, 1_000_000_000);
+    let instruction = instructions::buy_ticket(lottery_id, 1, &buyer.pubkey(), None, None, vec![]);
+    assert_fails_with(&mut svm, instruction, &buyer, "RandomnessNotCommitted");
+    assert_eq!(load_lottery(&svm, lottery_id).last_ticket_id, 0);
+
+    let commitment = hashv(&[&SECRET]).to_bytes();
+    send(
+        &mut svm,
+        instructions::commit_randomness(lottery_id, &authority.pubkey(), commitment),
+        &authority,
+    );
+    buy_ticket(&mut svm, lottery_id, &buyer);
+
+    // The commitment can't be swapped once tickets are sold
+    let instruction = instructions::commit_randomness(lottery_id, &authority.pubkey(), [9; 32]);
+    assert_fails_with(
+        &mut svm,
+        instruction,
+        &authority,
+        "RandomnessAlreadyCommitted",
+    );
+    assert_eq!(
+        load_lottery(&svm, lottery_id).randomness_commitment,
+        commitment
+    );
+}
+
+/// Winning ticket of a single-tier draw among `tickets` tickets, as the program draws it
+fn expected_winner(entropy_accumulator: &[u8; 32], slot_hash: &Hash, tickets: u32) -> u32 {
+    let seed = hashv(&[&SECRET, entropy_accumulator, &slot_hash.to_bytes()]).to_bytes();
+    let pick_seed = hashv(&[&seed, &[0]]).to_bytes();
+    (u64::from_le_bytes(pick_seed[..8].try_into().unwrap()) % tickets as u64) as u32 + 1
+}
+
+#[test]
+fn test_buyer_entropy_moves_the_draw() {
+    const TICKETS: u32 = 4;
+    let (mut svm, authority) = new_svm();
+    let slot_hash = Hash::new_unique();
+    let buyer = funded_wallet(&mut svm, 10_000_000_000);
+
+    // Pick entropy that draws another ticket than no entropy at all would
+    let plain_winner = expected_winner(&[0; 32], &slot_hash, TICKETS);
+    let (entropy, mixed_accumulator) = (0..=u8::MAX)
+        .map(|byte| {
+            let entropy = [byte; 32];
+            let accumulator = hashv(&[&[0; 32], &buyer.pubkey().to_bytes(), &entropy]).to_bytes();
+            (entropy, accumulator)
+        })
+        .find(|(_, accumulator)| expected_winner(accumulator, &slot_hash, TICKETS) != plain_winner)
+        .unwrap();
+
+    // The same sales in two lotteries, but the last ticket of the second brings entropy
+    let plain_id = create_lottery(&mut svm, &authority, lottery_params());
+    let mixed_id = create_lottery(&mut svm, &authority, lottery_params());
+    for lottery_id in [plain_id, mixed_id] {
+        for ticket_id in 1..TICKETS {
+            send(
+                &mut svm,
+                instructions::buy_ticket(
+                    lottery_id,
+                    ticket_id,
+                    &buyer.pubkey(),
+                    None,
+                    None,
+                    vec![],
+                ),
+                &buyer,
+            );
+        }
+        // `None` leaves the accumulator as it was
+        assert_eq!(load_lottery(&svm, lottery_id).entropy_accumulator, [0; 32]);
+    }
+    send(
+        &mut svm,
+        instructions::buy_ticket(plain_id, TICKETS, &buyer.pubkey(), None, None, vec![]),
+        &buyer,
+    );
+    send(
+        &mut svm,
+        instructions::buy_ticket(
+            mixed_id,
+            TICKETS,
+            &buyer.pubkey(),
+            None,
+            Some(entropy),
+            vec![],
+        ),
+        &buyer,
+    );
+    assert_eq!(load_lottery(&svm, plain_id).entropy_accumulator, [0; 32]);
+    assert_eq!(
+        load_lottery(&svm, mixed_id).entropy_accumulator,
+        mixed_accumulator
+    );
+
+    // Both draws see the same secret and slot hash
+    set_time(&mut svm, DRAW_TS, DRAW_SLOT);
+    svm.set_sysvar(&SlotHashes::new(&[(DRAW_SLOT, slot_hash)]));
+    for lottery_id in [plain_id, mixed_id] {
+        send(&mut svm, instructions::close_sales(lottery_id), &authority);
+    }
+    set_time(&mut svm, DRAW_TS, DRAW_SLOT + 1);
+    for lottery_id in [plain_id, mixed_id] {
+        send(
+            &mut svm,
+            instructions::reveal_and_pick_winner(lottery_id, &authority.pubkey(), SECRET),
+            &authority,
+        );
+    }
+
+    let plain = load_lottery(&svm, plain_id);
+    let mixed = load_lottery(&svm, mixed_id);
+    assert_eq!(plain.prize_tiers[0].winner_ticket_id, plain_winner);
+    assert_eq!(
+        mixed.prize_tiers[0].winner_ticket_id,
+        expected_winner(&mixed_accumulator, &slot_hash, TICKETS)
+    );
+    assert_ne!(
+        mixed.prize_tiers[0].winner_ticket_id,
+        plain.prize_tiers[0].winner_ticket_id
+    );
+    assert_ne!(mixed.draw_seed, plain.draw_seed);
+}
@@ -0,0 +1,197 @@
+#![cfg(test)]
+
+mod common;
+
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_spl::token_2022::spl_token_2022;
+use common::*;
+use litesvm::LiteSVM;
+use lottery::{CreateLotteryParams, JackpotPDA, LotteryStatus};
+use lottery_client::{
+    instructions::{self, TokenMint, WinningTicket},
+    pda,
+};
+use solana_keypair::Keypair;
+use solana_signer::Signer;
+
+const CLAIM_DEADLINE_TS: i64 = DRAW_TS + 1_000;
+
+fn load_jackpot(svm: &LiteSVM) -> JackpotPDA {
+    load(svm, &pda::jackpot())
+}
+
+/// Creates the next single-tier SOL lottery of `authority`, whose sale ends at `sale_end_ts`
+fn create_next_lottery(svm: &mut LiteSVM, authority: &Keypair, sale_end_ts: i64) -> u32 {
+    let params = CreateLotteryParams {
+        sale_end_ts,
+        draw_after_ts: sale_end_ts,
+        claim_deadline_ts: sale_end_ts + 1_000,
+        ..lottery_params()
+    };
+    create_lottery(svm, authority, params)
+}
+
+/// Runs lottery 1 up to its draw with a ticket for each of three buyers, ticket `n` belongs to
+/// `buyers[n - 1]`. The master admin is also the lottery's authority.
+fn setup_drawn_lottery() -> (LiteSVM, u32, Keypair, Vec<Keypair>) {
+    let (mut svm, lottery_id, authority, buyers) = setup_lottery(lottery_params(), 3);
+    draw(&mut svm, lottery_id, &authority);
+    (svm, lottery_id, authority, buyers)
+}
+
+fn claim_instruction(lottery_id: u32, winner_ticket_id: u32, winner: &Keypair) -> Instruction {
+    instructions::claim_prize(
+        lottery_id,
+        0,
+        WinningTicket::Pda(winner_ticket_id),
+        &winner.pubkey(),
+    )
+}
+
+fn rollover_instruction(
+    lottery_id: u32,
+    next_lottery_id: Option<u32>,
+    caller: &Keypair,
+) -> Instruction {
+    instructions::rollover_unclaimed(lottery_id, next_lottery_id, &caller.pubkey(), None)
+}
+
+#[test]
+fn test_unclaimed_prize_rolls_into_the_jackpot_after_the_deadline() {
+    let (mut svm, lottery_id, authority, buyers) = setup_drawn_lottery();
+    let lottery_pda = pda::lottery(lottery_id);
+    let lottery = load_lottery(&svm, lottery_id);
+    let winner_ticket_id = lottery.prize_tiers[0].winner_ticket_id;
+    let winner = &buyers[winner_ticket_id as usize - 1];
+    let cranker = funded_wallet(&mut svm, 1_000_000_000);
+
+    // The winner still has time to claim
+    let instruction = rollover_instruction(lottery_id, None, &cranker);
+    assert_fails_with(&mut svm, instruction, &cranker, "ClaimDeadlineNotPassed");
+
+    set_time(&mut svm, CLAIM_DEADLINE_TS, DRAW_SLOT + 2);
+    let instruction = claim_instruction(lottery_id, winner_ticket_id, winner);
+    assert_fails_with(&mut svm, instruction, winner, "ClaimDeadlinePassed");
+
+    let lottery_balance = svm.get_balance(&lottery_pda).unwrap();
+    send(
+        &mut svm,
+        rollover_instruction(lottery_id, None, &cranker),
+        &cranker,
+    );
+    assert_eq!(
+        load_jackpot(&svm).balance_lamports,
+        lottery.prize_pool_lamports
+    );
+    assert_eq!(
+        svm.get_balance(&lottery_pda).unwrap(),
+        lottery_balance - lottery.prize_pool_lamports
+    );
+    assert_eq!(
+        load_lottery(&svm, lottery_id).status,
+        LotteryStatus::Expired
+    );
+
+    // An expired lottery can't be rolled over twice, nor claimed
+    let instruction = rollover_instruction(lottery_id, None, &authority);
+    assert_fails_with(&mut svm, instruction, &authority, "LotteryExpired");
+    let instruction = claim_instruction(lottery_id, winner_ticket_id, winner);
+    assert_fails_with(&mut svm, instruction, winner, "LotteryExpired");
+    assert_eq!(
+        load_jackpot(&svm).balance_lamports,
+        lottery.prize_pool_lamports
+    );
+}
+
+#[test]
+fn test_unclaimed_prize_rolls_into_the_next_lottery() {
+    let (mut svm, lottery_id, authority, _buyers) = setup_drawn_lottery();
+    let next_lottery_id = create_next_lottery(&mut svm, &authority, CLAIM_DEADLINE_TS * 2);
+    let next_lottery_pda = pda::lottery(next_lottery_id);
+    let unclaimed = load_lottery(&svm, lottery_id).prize_pool_lamports;
+    set_time(&mut svm, CLAIM_DEADLINE_TS, DRAW_SLOT + 2);
+
+    // The target has to be a later lottery
+    let instruction = rollover_instruction(lottery_id, Some(lottery_id), &authority);
+    assert_fails_with(&mut svm, instruction, &authority, "InvalidRolloverTarget");
+
+    let next_balance = svm.get_balance(&next_lottery_pda).unwrap();
+    send(
+        &mut svm,
+        rollover_instruction(lottery_id, Some(next_lottery_id), &authority),
+        &authority,
+    );
+    let next_lottery = load_lottery(&svm, next_lottery_id);
+    assert_eq!(next_lottery.jackpot_lamports, unclaimed);
+    assert_eq!(next_lottery.pot_lamports().unwrap(), unclaimed);
+    assert_eq!(
+        svm.get_balance(&next_lottery_pda).unwrap(),
+        next_balance + unclaimed
+    );
+    assert_eq!(
+        load_lottery(&svm, lottery_id).status,
+        LotteryStatus::Expired
+    );
+}
+
+#[test]
+fn test_unclaimed_prize_never_rolls_into_another_currency() {
+    let (mut svm, lottery_id, authority, _buyers) = setup_drawn_lottery();
+    let next_lottery_id = create_next_lottery(&mut svm, &authority, CLAIM_DEADLINE_TS * 2);
+    let mint = create_mint(&mut svm, &authority, &spl_token_2022::ID, false);
+    let ticket_mint = TokenMint {
+        mint,
+        token_program: spl_token_2022::ID,
+    };
+    send(
+        &mut svm,
+        instructions::set_ticket_mint(next_lottery_id, &authority.pubkey(), ticket_mint),
+        &authority,
+    );
+    set_time(&mut svm, CLAIM_DEADLINE_TS, DRAW_SLOT + 2);
+
+    // Lamports would otherwise be counted as tokens of the next lottery's pot
+    let instruction = rollover_instruction(lottery_id, Some(next_lottery_id), &authority);
+    assert_fails_with(&mut svm, instruction, &authority, "InvalidRolloverTarget");
+    assert_eq!(load_lottery(&svm, next_lottery_id).jackpot_lamports, 0);
+    assert_eq!(load_lottery(&svm, lottery_id).status, LotteryStatus::Active);
+}
+
+#[test]
+fn test_admin_seeds_a_lottery_from_the_jackpot() {
+    let (mut svm, lottery_id, admin, buyers) = setup_drawn_lottery();
+    let jackpot = load_lottery(&svm, lottery_id).prize_pool_lamports;
+    set_time(&mut svm, CLAIM_DEADLINE_TS, DRAW_SLOT + 2);
+    send(
+        &mut svm,
+        rollover_instruction(lottery_id, None, &admin),
+        &admin,
+    );
+    let next_lottery_id = create_next_lottery(&mut svm, &admin, CLAIM_DEADLINE_TS * 2);
+    let next_lottery_pda = pda::lottery(next_lottery_id);
+
+    let stranger = &buyers[0];
+    let instruction = instructions::seed_from_jackpot(&stranger.pubkey(), next_lottery_id, jackpot);
+    assert_fails_with(&mut svm, instruction, stranger, "UnauthorizedAction");
+    let instruction =
+        instructions::seed_from_jackpot(&admin.pubkey(), next_lottery_id, jackpot + 1);
+    assert_fails_with(&mut svm, instruction, &admin, "InsufficientJackpot");
+
+    let next_balance = svm.get_balance(&next_lottery_pda).unwrap();
+    let seeded = jackpot / 2;
+    send(
+        &mut svm,
+        instructions::seed_from_jackpot(&admin.pubkey(), next_lottery_id, seeded),
+        &admin,
+    );
+    assert_eq!(load_jackpot(&svm).balance_lamports, jackpot - seeded);
+    assert_eq!(load_lottery(&svm, next_lottery_id).jackpot_lamports, seeded);
+    assert_eq!(
+        svm.get_balance(&next_lottery_pda).unwrap(),
+        next_balance + seeded
+    );
+
+    // A drawn lottery's pot is final
+    let instruction = instructions::seed_from_jackpot(&admin.pubkey(), lottery_id, 1);
+    assert_fails_with(&mut svm, instruction, &admin, "LotteryExpired");
+}
@@ -0,0 +1,64 @@
+#![cfg(test)]
+
+mod common;
+
+use common::*;
+use lottery::CreateLotteryParams;
+use lottery_client::instructions;
+use solana_signer::Signer;
+
+const SALE_START_TS: i64 = 100;
+const SALE_END_TS: i64 = 500;
+
+#[test]
+fn test_tickets_sell_only_within_the_sale_window() {
+    let (mut svm, authority) = new_svm();
+    let lottery_id = create_lottery(
+        &mut svm,
+        &authority,
+        CreateLotteryParams {
+            sale_start_ts: SALE_START_TS,
+            sale_end_ts: SALE_END_TS,
+            ..lottery_params()
+        },
+    );
+    let buyer = funded_wallet(&mut svm, 10_000_000_000);
+    let buy_instruction = |ticket_id| {
+        instructions::buy_ticket(lottery_id, ticket_id, &buyer.pubkey(), None, None, vec![])
+    };
+
+    set_time(&mut svm, SALE_START_TS - 1, 1);
+    assert_fails_with(&mut svm, buy_instruction(1), &buyer, "SalesNotOpen");
+    set_time(&mut svm, SALE_START_TS, 2);
+    send(&mut svm, buy_instruction(1), &buyer);
+
+    set_time(&mut svm, SALE_END_TS - 1, 3);
+    send(&mut svm, buy_instruction(2), &buyer);
+    set_time(&mut svm, SALE_END_TS, 4);
+    assert_fails_with(&mut svm, buy_instruction(3), &buyer, "SalesClosed");
+    assert_eq!(load_lottery(&svm, lottery_id).last_ticket_id, 2);
+
+    // Between the end of the sales and `draw_after_ts` nobody can buy nor draw yet
+    set_time(&mut svm, DRAW_TS - 1, DRAW_SLOT - 1);
+    assert_fails_with(&mut svm, buy_instruction(3), &buyer, "SalesClosed");
+    assert_fails_with(
+        &mut svm,
+        instructions::close_sales(lottery_id),
+        &authority,
+        "DrawTooEarly",
+    );
+    assert_fails_with(
+        &mut svm,
+        instructions::crank_draw(lottery_id, &buyer.pubkey()),
+        &buyer,
+        "DrawTooEarly",
+    );
+    let lottery = load_lottery(&svm, lottery_id);
+    assert_eq!(lottery.sales_closed_slot, None);
+    assert!(!lottery.is_drawn());
+
+    draw(&mut svm, lottery_id, &authority);
+    let lottery = load_lottery(&svm, lottery_id);
+    assert_eq!(lottery.sales_closed_slot, Some(DRAW_SLOT));
+    assert!(lottery.is_drawn());
+}
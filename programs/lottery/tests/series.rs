@@ -0,0 +1,297 @@
+#![cfg(test)]
+
+mod common;
+
+use anchor_lang::{
+    prelude::*,
+    solana_program::{
+        hash::{hashv, Hash},
+        instruction::Instruction,
+        slot_hashes::SlotHashes,
+    },
+};
+use common::*;
+use litesvm::LiteSVM;
+use lottery::{JackpotPDA, LotterySeries, LotteryStatus, SeriesParams};
+use lottery_client::{instructions, pda};
+use solana_keypair::Keypair;
+use solana_signer::Signer;
+
+const START_TS: i64 = 1_000;
+const SALE_DURATION: i64 = 3_600;
+const CARRY_OVER_BPS: u16 = 1_000;
+const MAX_FEE_BPS: u16 = 200;
+const SERIES_ID: u32 = 7;
+
+fn series_pda(authority: &Pubkey) -> Pubkey {
+    pda::series(authority, SERIES_ID)
+}
+
+fn load_series(svm: &LiteSVM, authority: &Pubkey) -> LotterySeries {
+    load(svm, &series_pda(authority))
+}
+
+fn series_params(max_fee_bps: u16) -> SeriesParams {
+    SeriesParams {
+        ticket_price_lamports: TICKET_PRICE,
+        sale_duration_secs: SALE_DURATION,
+        claim_period_secs: 86_400,
+        prize_shares_bps: vec![10_000],
+        min_tickets: 1,
+        max_tickets: 0,
+        max_tickets_per_wallet: 0,
+        referral_bps: 0,
+        carry_over_bps: CARRY_OVER_BPS,
+        crank_bounty_lamports: 0,
+        max_fee_bps,
+    }
+}
+
+fn advance_instruction(
+    authority: &Pubkey,
+    current_lottery_id: Option<u32>,
+    next_lottery_id: u32,
+    caller: &Keypair,
+) -> Instruction {
+    instructions::advance_series(
+        authority,
+        SERIES_ID,
+        current_lottery_id,
+        next_lottery_id,
+        &caller.pubkey(),
+    )
+}
+
+fn commit_instruction(lottery_id: u32, authority: &Keypair) -> Instruction {
+    instructions::commit_randomness(
+        lottery_id,
+        &authority.pubkey(),
+        hashv(&[&SECRET]).to_bytes(),
+    )
+}
+
+/// Sets up the master PDA, whose admin is the returned authority, and a funded series of the
+/// authority's that has not opened a round yet
+fn setup() -> (LiteSVM, Keypair) {
+    let (mut svm, authority) = new_svm();
+    set_time(&mut svm, START_TS, 0);
+    send(
+        &mut svm,
+        instructions::create_series(&authority.pubkey(), SERIES_ID, series_params(MAX_FEE_BPS)),
+        &authority,
+    );
+    // Pays back the rent of the rounds' accounts
+    svm.airdrop(&series_pda(&authority.pubkey()), 1_000_000_000)
+        .unwrap();
+    (svm, authority)
+}
+
+/// Opens the series' first round, commits it and sells it two tickets
+fn open_first_round(svm: &mut LiteSVM, authority: &Keypair) {
+    send(
+        svm,
+        advance_instruction(&authority.pubkey(), None, 1, authority),
+        authority,
+    );
+    send(svm, commit_instruction(1, authority), authority);
+    sell_tickets(svm, 1, 2);
+}
+
+/// Closes the sales of a round at its end and reveals the committed secret
+fn draw_round(svm: &mut LiteSVM, lottery_id: u32, authority: &Keypair) {
+    set_time(svm, START_TS + SALE_DURATION, DRAW_SLOT);
+    svm.set_sysvar(&SlotHashes::new(&[(DRAW_SLOT, Hash::new_unique())]));
+    send(svm, instructions::close_sales(lottery_id), authority);
+    set_time(svm, START_TS + SALE_DURATION, DRAW_SLOT + 1);
+    send(
+        svm,
+        instructions::reveal_and_pick_winner(lottery_id, &authority.pubkey(), SECRET),
+        authority,
+    );
+}
+
+#[test]
+fn test_create_series_rejects_a_fee_cap_above_the_maximum() {
+    let (mut svm, _authority) = setup();
+    let other_authority = funded_wallet(&mut svm, 1_000_000_000);
+
+    let instruction = instructions::create_series(
+        &other_authority.pubkey(),
+        SERIES_ID,
+        series_params(lottery::MAX_FEE_BPS + 1),
+    );
+    assert_fails_with(&mut svm, instruction, &other_authority, "FeeTooHigh");
+    assert!(svm
+        .get_account(&series_pda(&other_authority.pubkey()))
+        .is_none());
+}
+
+#[test]
+fn test_advance_series_opens_rounds_awaiting_the_authority_commitment() {
+    let (mut svm, authority) = setup();
+    let series = load_series(&svm, &authority.pubkey());
+    assert_eq!(series.ticket_price_lamports, TICKET_PRICE);
+    assert_eq!(series.max_fee_bps, MAX_FEE_BPS);
+    assert_eq!(series.current_lottery_id, 0);
+
+    let caller = funded_wallet(&mut svm, 1_000_000_000);
+    send(
+        &mut svm,
+        advance_instruction(&authority.pubkey(), None, 1, &caller),
+        &caller,
+    );
+    let round = load_lottery(&svm, 1);
+    assert_eq!(round.authority, authority.pubkey());
+    assert_eq!(round.ticket_price_lamports, TICKET_PRICE);
+    assert_eq!(round.sale_end_ts, START_TS + SALE_DURATION);
+    assert_eq!(round.carry_over_bps, CARRY_OVER_BPS);
+    assert_eq!(round.randomness_commitment, [0; 32]);
+    assert_eq!(load_series(&svm, &authority.pubkey()).current_lottery_id, 1);
+
+    // The round opens once the authority commits, and the series waits for its draw
+    let buyer = funded_wallet(&mut svm, 10_000_000_000);
+    let instruction = instructions::buy_ticket(1, 1, &buyer.pubkey(), None, None, vec![]);
+    assert_fails_with(&mut svm, instruction, &buyer, "RandomnessNotCommitted");
+    send(&mut svm, commit_instruction(1, &authority), &authority);
+    buy_ticket(&mut svm, 1, &buyer);
+    buy_ticket(&mut svm, 1, &buyer);
+    let instruction = advance_instruction(&authority.pubkey(), Some(1), 2, &caller);
+    assert_fails_with(&mut svm, instruction, &caller, "WinnerNotChosenYet");
+
+    draw_round(&mut svm, 1, &authority);
+
+    // The next round gets the carry-over but no commitment, the revealed secret stays unused
+    let caller_balance = svm.get_balance(&caller.pubkey()).unwrap();
+    send(
+        &mut svm,
+        advance_instruction(&authority.pubkey(), Some(1), 2, &caller),
+        &caller,
+    );
+    let carry_over = 2 * TICKET_PRICE * CARRY_OVER_BPS as u64 / 10_000;
+    let round = load_lottery(&svm, 2);
+    assert_eq!(round.jackpot_lamports, carry_over);
+    assert_eq!(round.randomness_commitment, [0; 32]);
+    assert_eq!(round.sale_start_ts, START_TS + SALE_DURATION);
+    assert!(load_lottery(&svm, 1).carried_over);
+    assert_eq!(load_series(&svm, &authority.pubkey()).current_lottery_id, 2);
+    // The series paid the new round's rent back, so the caller only paid the transaction fee
+    assert_eq!(
+        svm.get_balance(&caller.pubkey()).unwrap(),
+        caller_balance - TX_FEE
+    );
+    send(&mut svm, commit_instruction(2, &authority), &authority);
+    assert_eq!(
+        load_lottery(&svm, 2).randomness_commitment,
+        hashv(&[&SECRET]).to_bytes()
+    );
+}
+
+#[test]
+fn test_advance_series_waits_while_the_fee_is_above_its_cap() {
+    let (mut svm, authority) = setup();
+    send(
+        &mut svm,
+        instructions::set_fee(&authority.pubkey(), MAX_FEE_BPS + 1),
+        &authority,
+    );
+
+    let instruction = advance_instruction(&authority.pubkey(), None, 1, &authority);
+    assert_fails_with(&mut svm, instruction, &authority, "SeriesFeeTooHigh");
+    assert!(svm.get_account(&pda::lottery(1)).is_none());
+
+    send(
+        &mut svm,
+        instructions::set_fee(&authority.pubkey(), MAX_FEE_BPS),
+        &authority,
+    );
+    send(
+        &mut svm,
+        advance_instruction(&authority.pubkey(), None, 1, &authority),
+        &authority,
+    );
+    assert_eq!(load_lottery(&svm, 1).fee_bps, MAX_FEE_BPS);
+    assert_eq!(next_lottery_id(&svm), 2);
+}
+
+#[test]
+fn test_close_series_rejected_while_its_round_is_live() {
+    let (mut svm, authority) = setup();
+    open_first_round(&mut svm, &authority);
+
+    // Only the authority closes its series
+    let stranger = funded_wallet(&mut svm, 1_000_000_000);
+    let mut instruction = instructions::close_series(&authority.pubkey(), SERIES_ID, Some(1));
+    instruction.accounts[3] = AccountMeta::new(stranger.pubkey(), true);
+    assert_fails_with(&mut svm, instruction, &stranger, "UnauthorizedAction");
+
+    // The round's pot can still grow, so its carry-over isn't known yet
+    let instruction = instructions::close_series(&authority.pubkey(), SERIES_ID, Some(1));
+    assert_fails_with(&mut svm, instruction, &authority, "WinnerNotChosenYet");
+    // Leaving the round out doesn't get around it
+    let instruction = instructions::close_series(&authority.pubkey(), SERIES_ID, None);
+    assert_fails_with(&mut svm, instruction, &authority, "SeriesRoundMissing");
+    assert!(!is_closed(&svm, &series_pda(&authority.pubkey())));
+}
+
+#[test]
+fn test_close_series_after_a_drawn_round_moves_its_carry_over_to_the_jackpot() {
+    let (mut svm, authority) = setup();
+    open_first_round(&mut svm, &authority);
+    draw_round(&mut svm, 1, &authority);
+
+    let series_balance = svm.get_balance(&series_pda(&authority.pubkey())).unwrap();
+    let round_balance = svm.get_balance(&pda::lottery(1)).unwrap();
+    let balance = svm.get_balance(&authority.pubkey()).unwrap();
+    send(
+        &mut svm,
+        instructions::close_series(&authority.pubkey(), SERIES_ID, Some(1)),
+        &authority,
+    );
+
+    let carry_over = 2 * TICKET_PRICE * CARRY_OVER_BPS as u64 / 10_000;
+    let jackpot: JackpotPDA = load(&svm, &pda::jackpot());
+    assert_eq!(jackpot.balance_lamports, carry_over);
+    let round = load_lottery(&svm, 1);
+    assert!(round.carried_over);
+    assert_eq!(
+        svm.get_balance(&pda::lottery(1)).unwrap(),
+        round_balance - carry_over
+    );
+    // The authority gets the series' balance back, less the new jackpot account's rent
+    let jackpot_rent = svm.get_balance(&pda::jackpot()).unwrap() - carry_over;
+    assert!(is_closed(&svm, &series_pda(&authority.pubkey())));
+    assert_eq!(
+        svm.get_balance(&authority.pubkey()).unwrap(),
+        balance + series_balance - jackpot_rent - TX_FEE
+    );
+}
+
+#[test]
+fn test_close_series_after_a_cancelled_round_returns_its_balance() {
+    let (mut svm, authority) = setup();
+    open_first_round(&mut svm, &authority);
+    let instruction =
+        instructions::cancel_lottery(1, &authority.pubkey(), &authority.pubkey(), None);
+    send(&mut svm, instruction, &authority);
+    assert_eq!(load_lottery(&svm, 1).status, LotteryStatus::Cancelled);
+
+    let series_balance = svm.get_balance(&series_pda(&authority.pubkey())).unwrap();
+    let round_balance = svm.get_balance(&pda::lottery(1)).unwrap();
+    let balance = svm.get_balance(&authority.pubkey()).unwrap();
+    send(
+        &mut svm,
+        instructions::close_series(&authority.pubkey(), SERIES_ID, Some(1)),
+        &authority,
+    );
+
+    // Nothing is held back for a next round, the refunds keep the whole pot
+    let jackpot: JackpotPDA = load(&svm, &pda::jackpot());
+    assert_eq!(jackpot.balance_lamports, 0);
+    assert_eq!(svm.get_balance(&pda::lottery(1)).unwrap(), round_balance);
+    let jackpot_rent = svm.get_balance(&pda::jackpot()).unwrap();
+    assert!(is_closed(&svm, &series_pda(&authority.pubkey())));
+    assert_eq!(
+        svm.get_balance(&authority.pubkey()).unwrap(),
+        balance + series_balance - jackpot_rent - TX_FEE
+    );
+}
@@ -0,0 +1,227 @@
+#![cfg(test)]
+
+mod common;
+
+use anchor_lang::{prelude::*, solana_program::instruction::Instruction};
+use common::*;
+use litesvm::LiteSVM;
+use lottery::{PlayerStats, TicketLedger, TicketPDA, WalletTicketsPDA};
+use lottery_client::{
+    instructions::{self, WinningTicket},
+    pda,
+};
+use solana_keypair::Keypair;
+use solana_signer::Signer;
+
+const TICKET_COUNT: u32 = 50;
+
+/// Sets up the master PDA and an open lottery with a randomness commitment and a ticket ledger
+fn setup() -> (LiteSVM, Keypair, u32) {
+    let (mut svm, authority) = new_svm();
+    let lottery_id = create_lottery(&mut svm, &authority, lottery_params());
+    send(
+        &mut svm,
+        instructions::init_ticket_ledger(lottery_id, &authority.pubkey()),
+        &authority,
+    );
+    (svm, authority, lottery_id)
+}
+
+fn new_buyer(svm: &mut LiteSVM) -> Keypair {
+    funded_wallet(svm, 10_000_000_000)
+}
+
+fn buy_tickets_instruction(lottery_id: u32, buyer: &Keypair, count: u32) -> Instruction {
+    instructions::buy_tickets(lottery_id, &buyer.pubkey(), None, count, None)
+}
+
+fn load_ranges(svm: &LiteSVM, lottery_id: u32) -> (usize, Vec<lottery::TicketRange>) {
+    let ledger_account = svm
+        .get_account(&pda::ticket_ledger(&pda::lottery(lottery_id)))
+        .unwrap();
+    let ranges = TicketLedger::ranges(&ledger_account.data).unwrap().to_vec();
+    (ledger_account.data.len(), ranges)
+}
+
+#[test]
+fn test_bulk_purchase_saves_rent_and_compute() {
+    let (mut svm, _authority, lottery_id) = setup();
+
+    // One TicketPDA per ticket
+    let single_buyer = new_buyer(&mut svm);
+    let single_start = svm.get_balance(&single_buyer.pubkey()).unwrap();
+    let mut single_compute = 0;
+    for ticket_id in 1..=TICKET_COUNT {
+        let instruction = instructions::buy_ticket(
+            lottery_id,
+            ticket_id,
+            &single_buyer.pubkey(),
+            None,
+            None,
+            vec![],
+        );
+        single_compute += send(&mut svm, instruction, &single_buyer).compute_units_consumed;
+    }
+    let single_cost = single_start - svm.get_balance(&single_buyer.pubkey()).unwrap();
+
+    // One range in the ticket ledger
+    let bulk_buyer = new_buyer(&mut svm);
+    let bulk_start = svm.get_balance(&bulk_buyer.pubkey()).unwrap();
+    let instruction = buy_tickets_instruction(lottery_id, &bulk_buyer, TICKET_COUNT);
+    let bulk_compute = send(&mut svm, instruction, &bulk_buyer).compute_units_consumed;
+    let bulk_cost = bulk_start - svm.get_balance(&bulk_buyer.pubkey()).unwrap();
+
+    let ticket_rent = svm.minimum_balance_for_rent_exemption(8 + TicketPDA::INIT_SPACE);
+    assert!(single_cost >= TICKET_PRICE * TICKET_COUNT as u64 + ticket_rent * TICKET_COUNT as u64);
+    // The bulk buyer only pays for the tickets, its ticket counter, its stats, its range in the
+    // ledger and a single transaction fee
+    let wallet_tickets_rent =
+        svm.minimum_balance_for_rent_exemption(8 + WalletTicketsPDA::INIT_SPACE);
+    let player_stats_rent = svm.minimum_balance_for_rent_exemption(8 + PlayerStats::INIT_SPACE);
+    let range_rent = svm.minimum_balance_for_rent_exemption(TicketLedger::space(1))
+        - svm.minimum_balance_for_rent_exemption(TicketLedger::space(0));
+    assert_eq!(
+        bulk_cost,
+        TICKET_PRICE * TICKET_COUNT as u64
+            + wallet_tickets_rent
+            + player_stats_rent
+            + range_rent
+            + TX_FEE
+    );
+    assert!(bulk_compute * 10 < single_compute);
+    assert_eq!(
+        load_lottery(&svm, lottery_id).last_ticket_id,
+        2 * TICKET_COUNT
+    );
+}
+
+#[test]
+fn test_ledger_merges_consecutive_ranges() {
+    let (mut svm, _authority, lottery_id) = setup();
+    let alice = new_buyer(&mut svm);
+    let bob = new_buyer(&mut svm);
+
+    for (buyer, count) in [(&alice, 3), (&alice, 2), (&bob, 4), (&alice, 1)] {
+        send(
+            &mut svm,
+            buy_tickets_instruction(lottery_id, buyer, count),
+            buyer,
+        );
+    }
+
+    let ledger_account = svm
+        .get_account(&pda::ticket_ledger(&pda::lottery(lottery_id)))
+        .unwrap();
+    assert_eq!(ledger_account.data.len(), TicketLedger::space(3));
+    let (ledger, ranges) = TicketLedger::split(&ledger_account.data).unwrap();
+    assert_eq!(ledger.lottery, pda::lottery(lottery_id));
+    assert_eq!(ranges.len(), 3);
+    assert_eq!(TicketLedger::owner_of(ranges, 5), Some(alice.pubkey()));
+    assert_eq!(TicketLedger::owner_of(ranges, 6), Some(bob.pubkey()));
+    assert_eq!(TicketLedger::owner_of(ranges, 9), Some(bob.pubkey()));
+    assert_eq!(TicketLedger::owner_of(ranges, 10), Some(alice.pubkey()));
+    assert_eq!(TicketLedger::owner_of(ranges, 11), None);
+}
+
+#[test]
+fn test_stats_count_bulk_and_single_purchases() {
+    let (mut svm, _authority, lottery_id) = setup();
+    let alice = new_buyer(&mut svm);
+    let bob = new_buyer(&mut svm);
+
+    // Alice starts with a bulk purchase, Bob with a single ticket
+    for (buyer, count) in [(&alice, 3), (&bob, 1), (&alice, 1), (&bob, 2), (&alice, 2)] {
+        if count == 1 {
+            buy_ticket(&mut svm, lottery_id, buyer);
+        } else {
+            send(
+                &mut svm,
+                buy_tickets_instruction(lottery_id, buyer, count),
+                buyer,
+            );
+        }
+    }
+
+    for (player, tickets) in [(&alice, 6), (&bob, 3)] {
+        let stats: PlayerStats = load(&svm, &pda::player_stats(&player.pubkey()));
+        assert_eq!(stats.player, player.pubkey());
+        assert_eq!(stats.tickets_bought, tickets);
+        assert_eq!(stats.lamports_spent, tickets * TICKET_PRICE);
+        assert_eq!(stats.lotteries_entered, 1);
+    }
+}
+
+#[test]
+fn test_alternating_purchases_grow_the_ledger() {
+    let (mut svm, _authority, lottery_id) = setup();
+    let alice = new_buyer(&mut svm);
+    let bob = new_buyer(&mut svm);
+
+    // Every purchase adds a range, paid by its buyer
+    let purchases = 250;
+    let range_rent = svm.minimum_balance_for_rent_exemption(TicketLedger::space(1))
+        - svm.minimum_balance_for_rent_exemption(TicketLedger::space(0));
+    for purchase in 0..purchases {
+        let buyer = if purchase % 2 == 0 { &alice } else { &bob };
+        let balance = svm.get_balance(&buyer.pubkey()).unwrap();
+        send(
+            &mut svm,
+            buy_tickets_instruction(lottery_id, buyer, 1),
+            buyer,
+        );
+        // Each buyer sends the same instruction every other purchase
+        svm.expire_blockhash();
+        if purchase >= 2 {
+            assert_eq!(
+                svm.get_balance(&buyer.pubkey()).unwrap(),
+                balance - TICKET_PRICE - range_rent - TX_FEE
+            );
+        }
+    }
+
+    let (ledger_len, ranges) = load_ranges(&svm, lottery_id);
+    assert_eq!(ledger_len, TicketLedger::space(purchases));
+    assert_eq!(ranges.len(), purchases);
+    assert_eq!(TicketLedger::owner_of(&ranges, 249), Some(alice.pubkey()));
+    assert_eq!(TicketLedger::owner_of(&ranges, 250), Some(bob.pubkey()));
+}
+
+#[test]
+fn test_ledger_ticket_wins_and_claims() {
+    let (mut svm, authority, lottery_id) = setup();
+    let alice = new_buyer(&mut svm);
+    let bob = new_buyer(&mut svm);
+    for (buyer, count) in [(&alice, 3), (&bob, 4), (&alice, 2)] {
+        send(
+            &mut svm,
+            buy_tickets_instruction(lottery_id, buyer, count),
+            buyer,
+        );
+    }
+    draw(&mut svm, lottery_id, &authority);
+
+    let winner_ticket_id = load_lottery(&svm, lottery_id).prize_tiers[0].winner_ticket_id;
+    let (_, ranges) = load_ranges(&svm, lottery_id);
+    let owner = TicketLedger::owner_of(&ranges, winner_ticket_id).unwrap();
+    let (winner, loser) = if owner == alice.pubkey() {
+        (&alice, &bob)
+    } else {
+        (&bob, &alice)
+    };
+    assert_eq!(owner, winner.pubkey());
+
+    let claim_instruction = |winner: &Keypair| {
+        instructions::claim_prize(lottery_id, 0, WinningTicket::Ledger, &winner.pubkey())
+    };
+    assert_fails_with(
+        &mut svm,
+        claim_instruction(loser),
+        loser,
+        "UnauthorizedAction",
+    );
+
+    let balance = svm.get_balance(&winner.pubkey()).unwrap();
+    send(&mut svm, claim_instruction(winner), winner);
+    assert!(load_lottery(&svm, lottery_id).prize_tiers[0].claimed);
+    assert!(svm.get_balance(&winner.pubkey()).unwrap() > balance + 8 * TICKET_PRICE);
+}
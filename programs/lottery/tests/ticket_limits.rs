@@ -0,0 +1,60 @@
+#![cfg(test)]
+
+mod common;
+
+use common::*;
+use lottery::{CreateLotteryParams, WalletTicketsPDA};
+use lottery_client::{instructions, pda};
+use solana_signer::Signer;
+
+#[test]
+fn test_buy_ticket_stops_at_max_tickets() {
+    let (mut svm, lottery_id, _authority, _buyers) = setup_lottery(
+        CreateLotteryParams {
+            max_tickets: 2,
+            ..lottery_params()
+        },
+        2,
+    );
+
+    let late_buyer = funded_wallet(&mut svm, 1_000_000_000);
+    let instruction =
+        instructions::buy_ticket(lottery_id, 3, &late_buyer.pubkey(), None, None, vec![]);
+    assert_fails_with(&mut svm, instruction, &late_buyer, "SoldOut");
+
+    let lottery = load_lottery(&svm, lottery_id);
+    assert_eq!(lottery.last_ticket_id, 2);
+    assert_eq!(lottery.sales_lamports, 2 * TICKET_PRICE);
+    assert!(svm
+        .get_account(&pda::ticket(&pda::lottery(lottery_id), 3))
+        .is_none());
+}
+
+#[test]
+fn test_buy_ticket_stops_at_the_wallet_limit() {
+    let (mut svm, authority) = new_svm();
+    let lottery_id = create_lottery(
+        &mut svm,
+        &authority,
+        CreateLotteryParams {
+            max_tickets_per_wallet: 2,
+            ..lottery_params()
+        },
+    );
+    let buyer = funded_wallet(&mut svm, 1_000_000_000);
+    buy_ticket(&mut svm, lottery_id, &buyer);
+    buy_ticket(&mut svm, lottery_id, &buyer);
+
+    let instruction = instructions::buy_ticket(lottery_id, 3, &buyer.pubkey(), None, None, vec![]);
+    assert_fails_with(&mut svm, instruction, &buyer, "WalletTicketLimitReached");
+    let wallet_tickets: WalletTicketsPDA = load(
+        &svm,
+        &pda::wallet_tickets(&pda::lottery(lottery_id), &buyer.pubkey()),
+    );
+    assert_eq!(wallet_tickets.tickets_bought, 2);
+
+    // The limit is per wallet, the next ticket goes to someone else
+    let other_buyer = funded_wallet(&mut svm, 1_000_000_000);
+    assert_eq!(buy_ticket(&mut svm, lottery_id, &other_buyer), 3);
+    assert_eq!(load_lottery(&svm, lottery_id).last_ticket_id, 3);
+}
@@ -0,0 +1,224 @@
+#![cfg(test)]
+
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_spl::associated_token::spl_associated_token_account;
+use anchor_spl::token_2022::spl_token_2022;
+use common::*;
+use litesvm::LiteSVM;
+use lottery_client::{
+    instructions::{self, TokenMint},
+    pda,
+};
+use solana_keypair::Keypair;
+use solana_signer::Signer;
+
+const FEE_BPS: u16 = 500;
+/// Tokens each buyer starts with
+const BUYER_TOKENS: u64 = 10 * TICKET_PRICE;
+
+/// Both token programs a ticket mint may belong to
+fn token_programs() -> [Pubkey; 2] {
+    [anchor_spl::token::ID, spl_token_2022::ID]
+}
+
+/// Opens a lottery paid in a new mint of `token_program`, taking the protocol fee, and sells a
+/// ticket to each of three buyers. Ticket `n` belongs to `buyers[n - 1]`.
+fn setup_token_lottery(token_program: Pubkey) -> (LiteSVM, u32, Keypair, TokenMint, Vec<Keypair>) {
+    let (mut svm, authority) = new_svm();
+    send(
+        &mut svm,
+        instructions::set_fee(&authority.pubkey(), FEE_BPS),
+        &authority,
+    );
+    let lottery_id = create_lottery(&mut svm, &authority, lottery_params());
+    let ticket_mint = TokenMint {
+        mint: create_mint(&mut svm, &authority, &token_program, false),
+        token_program,
+    };
+    send(
+        &mut svm,
+        instructions::set_ticket_mint(lottery_id, &authority.pubkey(), ticket_mint),
+        &authority,
+    );
+
+    let buyers: Vec<Keypair> = (1..=3)
+        .map(|ticket_id| {
+            let buyer = funded_wallet(&mut svm, 1_000_000_000);
+            mint_tokens(
+                &mut svm,
+                &authority,
+                &ticket_mint.mint,
+                &token_program,
+                &buyer.pubkey(),
+                BUYER_TOKENS,
+            );
+            let instruction = instructions::buy_ticket_with_token(
+                lottery_id,
+                ticket_id,
+                &buyer.pubkey(),
+                ticket_mint,
+                None,
+            );
+            send(&mut svm, instruction, &buyer);
+            buyer
+        })
+        .collect();
+    (svm, lottery_id, authority, ticket_mint, buyers)
+}
+
+fn token_account(owner: &Pubkey, ticket_mint: &TokenMint) -> Pubkey {
+    pda::token_account(owner, &ticket_mint.mint, &ticket_mint.token_program)
+}
+
+#[test]
+fn test_set_ticket_mint_with_a_precreated_vault() {
+    let (mut svm, authority) = new_svm();
+    let lottery_id = create_lottery(&mut svm, &authority, lottery_params());
+    let token_program = anchor_spl::token::ID;
+    let mint = create_mint(&mut svm, &authority, &token_program, false);
+
+    // Anyone can open the lottery's vault before the authority sets the mint
+    let griefer = funded_wallet(&mut svm, 1_000_000_000);
+    let instruction = spl_associated_token_account::instruction::create_associated_token_account(
+        &griefer.pubkey(),
+        &pda::lottery(lottery_id),
+        &mint,
+        &token_program,
+    );
+    send(&mut svm, instruction, &griefer);
+
+    let ticket_mint = TokenMint {
+        mint,
+        token_program,
+    };
+    send(
+        &mut svm,
+        instructions::set_ticket_mint(lottery_id, &authority.pubkey(), ticket_mint),
+        &authority,
+    );
+    assert_eq!(load_lottery(&svm, lottery_id).ticket_mint, Some(mint));
+}
+
+#[test]
+fn test_set_ticket_mint_rejects_token_2022_transfer_fees() {
+    let (mut svm, authority) = new_svm();
+    let lottery_id = create_lottery(&mut svm, &authority, lottery_params());
+    let token_program = spl_token_2022::ID;
+
+    let fee_mint = TokenMint {
+        mint: create_mint(&mut svm, &authority, &token_program, true),
+        token_program,
+    };
+    let instruction = instructions::set_ticket_mint(lottery_id, &authority.pubkey(), fee_mint);
+    assert_fails_with(&mut svm, instruction, &authority, "TransferFeeMint");
+    assert_eq!(load_lottery(&svm, lottery_id).ticket_mint, None);
+
+    // Token-2022 mints without the extension are fine
+    let mint = create_mint(&mut svm, &authority, &token_program, false);
+    let ticket_mint = TokenMint {
+        mint,
+        token_program,
+    };
+    send(
+        &mut svm,
+        instructions::set_ticket_mint(lottery_id, &authority.pubkey(), ticket_mint),
+        &authority,
+    );
+    assert_eq!(load_lottery(&svm, lottery_id).ticket_mint, Some(mint));
+}
+
+#[test]
+fn test_token_tickets_fill_the_vault_and_pay_the_winner() {
+    for token_program in token_programs() {
+        let (mut svm, lottery_id, authority, ticket_mint, buyers) =
+            setup_token_lottery(token_program);
+        let vault = token_account(&pda::lottery(lottery_id), &ticket_mint);
+        let pot = 3 * TICKET_PRICE;
+        assert_eq!(token_balance(&svm, &vault), pot);
+        for buyer in &buyers {
+            assert_eq!(
+                token_balance(&svm, &token_account(&buyer.pubkey(), &ticket_mint)),
+                BUYER_TOKENS - TICKET_PRICE
+            );
+        }
+        // A lamport ticket can't be bought in a token lottery
+        let instruction =
+            instructions::buy_ticket(lottery_id, 4, &buyers[0].pubkey(), None, None, vec![]);
+        assert_fails_with(&mut svm, instruction, &buyers[0], "WrongTicketMint");
+
+        draw(&mut svm, lottery_id, &authority);
+        let lottery = load_lottery(&svm, lottery_id);
+        assert_eq!(lottery.prize_pool_lamports, pot);
+        let winner_ticket_id = lottery.prize_tiers[0].winner_ticket_id;
+        let winner = &buyers[winner_ticket_id as usize - 1];
+        send(
+            &mut svm,
+            instructions::claim_prize_with_token(
+                lottery_id,
+                0,
+                winner_ticket_id,
+                &winner.pubkey(),
+                ticket_mint,
+            ),
+            winner,
+        );
+
+        let fee = pot * FEE_BPS as u64 / 10_000;
+        assert_eq!(
+            token_balance(&svm, &token_account(&winner.pubkey(), &ticket_mint)),
+            BUYER_TOKENS - TICKET_PRICE + pot - fee
+        );
+        assert_eq!(
+            token_balance(&svm, &token_account(&pda::treasury(), &ticket_mint)),
+            fee
+        );
+        assert_eq!(token_balance(&svm, &vault), 0);
+        assert!(load_lottery(&svm, lottery_id).prize_tiers[0].claimed);
+    }
+}
+
+#[test]
+fn test_token_refunds_empty_the_vault() {
+    for token_program in token_programs() {
+        let (mut svm, lottery_id, authority, ticket_mint, buyers) =
+            setup_token_lottery(token_program);
+        send(
+            &mut svm,
+            instructions::cancel_lottery(
+                lottery_id,
+                &authority.pubkey(),
+                &authority.pubkey(),
+                None,
+            ),
+            &authority,
+        );
+
+        for (owner, ticket_id) in buyers.iter().zip(1..) {
+            send(
+                &mut svm,
+                instructions::refund_ticket_with_token(
+                    lottery_id,
+                    ticket_id,
+                    &owner.pubkey(),
+                    ticket_mint,
+                ),
+                &authority,
+            );
+            assert_eq!(
+                token_balance(&svm, &token_account(&owner.pubkey(), &ticket_mint)),
+                BUYER_TOKENS
+            );
+            assert!(is_closed(
+                &svm,
+                &pda::ticket(&pda::lottery(lottery_id), ticket_id)
+            ));
+        }
+        let vault = token_account(&pda::lottery(lottery_id), &ticket_mint);
+        assert_eq!(token_balance(&svm, &vault), 0);
+        let lottery = load_lottery(&svm, lottery_id);
+        assert_eq!(lottery.tickets_refunded, 3);
+        assert_eq!(lottery.sales_lamports, 0);
+    }
+}
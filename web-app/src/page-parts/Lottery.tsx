@@ -1,7 +1,7 @@
 import React, { useState, useEffect } from "react";
 import { useAnchorWallet, useConnection, type AnchorWallet } from "@solana/wallet-adapter-react";
 import { AnchorProvider, Program } from "@coral-xyz/anchor";
-import { PublicKey, SystemProgram } from "@solana/web3.js";
+import { PublicKey, SystemProgram, SYSVAR_SLOT_HASHES_PUBKEY } from "@solana/web3.js";
 import { hstack } from "../../styled-system/patterns";
 import Button from "../atoms/Button";
 import type { Lottery as LotteryProgram } from "../../../target/types/lottery";
@@ -18,11 +18,24 @@ const programID = new PublicKey(idl.address);
 const MASTER_SEED = "master";
 const LOTTERY_SEED = "lottery";
 const TICKET_SEED = "ticket";
+const TICKET_LEDGER_SEED = "ticket_ledger";
+const PLAYER_STATS_SEED = "player_stats";
+const TREASURY_SEED = "treasury";
+const WALLET_TICKETS_SEED = "wallet_tickets";
+const JACKPOT_SEED = "jackpot";
+
+const SALE_DURATION_SECS = 60 * 60;
+const CLAIM_PERIOD_SECS = 30 * 24 * 60 * 60;
+const DEFAULT_PRIZE_SHARES_BPS = [10_000];
 
 function getMasterAddr(programID: PublicKey): PublicKey {
   const [masterAddr] = PublicKey.findProgramAddressSync([Buffer.from(MASTER_SEED)], programID);
   return masterAddr;
 }
+function getTreasuryAddr(programID: PublicKey): PublicKey {
+  const [treasuryAddr] = PublicKey.findProgramAddressSync([Buffer.from(TREASURY_SEED)], programID);
+  return treasuryAddr;
+}
 function getLotteryKey(programID: PublicKey, lotteryId: number): PublicKey {
   const [lotteryAddr] = PublicKey.findProgramAddressSync([Buffer.from(LOTTERY_SEED), new anchor.BN(lotteryId).toArrayLike(Buffer, "le", 4)], programID);
   return lotteryAddr;
@@ -31,6 +44,52 @@ function getTicketKey(programID: PublicKey, lotteryAddr: PublicKey, ticketId: nu
   const [ticketAddr] = PublicKey.findProgramAddressSync([Buffer.from(TICKET_SEED), lotteryAddr.toBuffer(), new anchor.BN(ticketId).toArrayLike(Buffer, "le", 4)], programID);
   return ticketAddr;
 }
+function getJackpotAddr(programID: PublicKey): PublicKey {
+  const [jackpotAddr] = PublicKey.findProgramAddressSync([Buffer.from(JACKPOT_SEED)], programID);
+  return jackpotAddr;
+}
+function getWalletTicketsKey(programID: PublicKey, lotteryAddr: PublicKey, buyer: PublicKey): PublicKey {
+  const [walletTicketsAddr] = PublicKey.findProgramAddressSync([Buffer.from(WALLET_TICKETS_SEED), lotteryAddr.toBuffer(), buyer.toBuffer()], programID);
+  return walletTicketsAddr;
+}
+function getTicketLedgerKey(programID: PublicKey, lotteryAddr: PublicKey): PublicKey {
+  const [ledgerAddr] = PublicKey.findProgramAddressSync([Buffer.from(TICKET_LEDGER_SEED), lotteryAddr.toBuffer()], programID);
+  return ledgerAddr;
+}
+function getPlayerStatsKey(programID: PublicKey, player: PublicKey): PublicKey {
+  const [statsAddr] = PublicKey.findProgramAddressSync([Buffer.from(PLAYER_STATS_SEED), player.toBuffer()], programID);
+  return statsAddr;
+}
+
+// The reveal secret only lives in this browser, the program only stores sha256(secret)
+function secretStorageKey(lotteryAddr: PublicKey): string {
+  return `lottery-secret-${lotteryAddr.toBase58()}`;
+}
+async function makeRandomnessCommitment(): Promise<{ secret: Uint8Array; commitment: number[] }> {
+  const secret = crypto.getRandomValues(new Uint8Array(32));
+  const commitment = new Uint8Array(await crypto.subtle.digest("SHA-256", secret));
+  return { secret, commitment: Array.from(commitment) };
+}
+
+function formatCountdown(secs: number): string {
+  const hours = Math.floor(secs / 3600);
+  const minutes = Math.floor((secs % 3600) / 60);
+  const seconds = secs % 60;
+  return `${hours}h ${String(minutes).padStart(2, "0")}m ${String(seconds).padStart(2, "0")}s`;
+}
+function scheduleStatus(lottery: LotteryDetails, now: number): string {
+  if (lottery.cancelled) return "Cancelled, tickets can be refunded";
+  if (now < lottery.saleStartTs) return `Sales open in ${formatCountdown(lottery.saleStartTs - now)}`;
+  if (now < lottery.saleEndTs) return `Sales close in ${formatCountdown(lottery.saleEndTs - now)}`;
+  if (now < lottery.drawAfterTs) return `Draw in ${formatCountdown(lottery.drawAfterTs - now)}`;
+  return "Draw open";
+}
+
+type PrizeTierDetails = {
+  shareBps: number;
+  winnerTicketId: number | null;
+  claimed: boolean;
+};
 
 class LotteryDetails {
   id: number;
@@ -38,19 +97,63 @@ class LotteryDetails {
   authority: PublicKey;
   ticketPriceSOL: number;
   lastTicketId: number;
-  winnerTicketId: number | null;
-  claimed: boolean;
+  prizeTiers: PrizeTierDetails[];
   totalPrizeSOL: number;
-
-  constructor(params: { id: number; authority: PublicKey; ticketPriceSOL: number; lastTicketId: number; winnerTicketId: number | null; claimed: boolean; totalPrizeSOL: number }) {
+  salesClosedSlot: number | null;
+  saleStartTs: number;
+  saleEndTs: number;
+  drawAfterTs: number;
+  // Tickets and prizes are in this mint instead of SOL; buying and claiming them isn't supported here yet
+  ticketMint: PublicKey | null;
+  minTickets: number;
+  cancelled: boolean;
+  ticketsRefunded: number;
+  claimDeadlineTs: number;
+  expired: boolean;
+  // Tickets are NFTs, bought and claimed with the NFT instructions, which aren't supported here yet
+  nftTickets: boolean;
+  // Tickets pick numbers, which can't be chosen here yet
+  numbersGame: boolean;
+
+  constructor(params: {
+    id: number;
+    authority: PublicKey;
+    ticketPriceSOL: number;
+    lastTicketId: number;
+    prizeTiers: PrizeTierDetails[];
+    totalPrizeSOL: number;
+    salesClosedSlot: number | null;
+    saleStartTs: number;
+    saleEndTs: number;
+    drawAfterTs: number;
+    ticketMint: PublicKey | null;
+    minTickets: number;
+    cancelled: boolean;
+    ticketsRefunded: number;
+    claimDeadlineTs: number;
+    expired: boolean;
+    nftTickets: boolean;
+    numbersGame: boolean;
+  }) {
     this.id = params.id;
     this.address = getLotteryKey(programID, params.id);
     this.authority = params.authority;
     this.ticketPriceSOL = params.ticketPriceSOL;
     this.lastTicketId = params.lastTicketId;
-    this.winnerTicketId = params.winnerTicketId;
-    this.claimed = params.claimed;
+    this.prizeTiers = params.prizeTiers;
     this.totalPrizeSOL = params.totalPrizeSOL;
+    this.salesClosedSlot = params.salesClosedSlot;
+    this.saleStartTs = params.saleStartTs;
+    this.saleEndTs = params.saleEndTs;
+    this.drawAfterTs = params.drawAfterTs;
+    this.ticketMint = params.ticketMint;
+    this.minTickets = params.minTickets;
+    this.cancelled = params.cancelled;
+    this.ticketsRefunded = params.ticketsRefunded;
+    this.claimDeadlineTs = params.claimDeadlineTs;
+    this.expired = params.expired;
+    this.nftTickets = params.nftTickets;
+    this.numbersGame = params.numbersGame;
   }
 }
 
@@ -64,6 +167,13 @@ export const Lottery: React.FC<{ initialLotteryId: number | null }> = ({ initial
   const [lotteries, setLotteries] = useState<Record<number, LotteryDetails>>({});
   const [selectedLotteryId, setSelectedLotteryId] = useState<number | null>(initialLotteryId);
   const [ticketPrice, setTicketPrice] = useState<number>(0.1); // Default ticket price in SOL
+  const [now, setNow] = useState<number>(Math.floor(Date.now() / 1000));
+
+  // Tick the sale/draw countdowns
+  useEffect(() => {
+    const interval = setInterval(() => setNow(Math.floor(Date.now() / 1000)), 1000);
+    return () => clearInterval(interval);
+  }, []);
 
   // Auto-set program once connected
   useEffect(() => {
@@ -123,9 +233,24 @@ export const Lottery: React.FC<{ initialLotteryId: number | null }> = ({ initial
           authority: lotteryAccount.authority,
           ticketPriceSOL: ticketPriceSOL,
           lastTicketId: lotteryAccount.lastTicketId,
-          winnerTicketId: lotteryAccount.winnerTicketId,
-          claimed: lotteryAccount.claimed,
-          totalPrizeSOL: lotteryAccount.lastTicketId * ticketPriceSOL,
+          prizeTiers: lotteryAccount.prizeTiers.slice(0, lotteryAccount.prizeTierCount).map((tier) => ({
+            shareBps: tier.shareBps,
+            winnerTicketId: tier.winnerTicketId === 0 ? null : tier.winnerTicketId,
+            claimed: tier.claimed,
+          })),
+          totalPrizeSOL: (lotteryAccount.salesLamports.toNumber() + lotteryAccount.jackpotLamports.toNumber()) / anchor.web3.LAMPORTS_PER_SOL,
+          salesClosedSlot: lotteryAccount.salesClosedSlot ? lotteryAccount.salesClosedSlot.toNumber() : null,
+          saleStartTs: lotteryAccount.saleStartTs.toNumber(),
+          saleEndTs: lotteryAccount.saleEndTs.toNumber(),
+          drawAfterTs: lotteryAccount.drawAfterTs.toNumber(),
+          ticketMint: lotteryAccount.ticketMint,
+          minTickets: lotteryAccount.minTickets,
+          cancelled: "cancelled" in lotteryAccount.status,
+          ticketsRefunded: lotteryAccount.ticketsRefunded,
+          claimDeadlineTs: lotteryAccount.claimDeadlineTs.toNumber(),
+          expired: "expired" in lotteryAccount.status,
+          nftTickets: lotteryAccount.nftTickets,
+          numbersGame: "numbers" in lotteryAccount.gameMode,
         };
       } catch (error) {
         // This is expected if a lottery was not created or closed
@@ -163,6 +288,7 @@ export const Lottery: React.FC<{ initialLotteryId: number | null }> = ({ initial
         .initMaster()
         .accounts({
           masterPda: masterPda,
+          treasuryPda: getTreasuryAddr(programID),
           payer: wallet.publicKey,
           systemProgram: SystemProgram.programId,
         } as any)
@@ -185,16 +311,47 @@ export const Lottery: React.FC<{ initialLotteryId: number | null }> = ({ initial
     const lotteryAddr = getLotteryKey(programID, nextLotteryId);
     console.log({ nextLotteryId, lotteryAddr: lotteryAddr.toBase58() });
 
+    const saleStartTs = Math.floor(Date.now() / 1000);
+    const saleEndTs = saleStartTs + SALE_DURATION_SECS;
+
     try {
+      const { secret, commitment } = await makeRandomnessCommitment();
+      const commitIx = await program.methods
+        .commitRandomness(commitment)
+        .accounts({
+          lotteryPda: lotteryAddr,
+          authority: wallet.publicKey,
+        } as any)
+        .instruction();
       let txo = await program.methods
-        .createLottery(new anchor.BN(ticketPrice * anchor.web3.LAMPORTS_PER_SOL))
+        .createLottery({
+          ticketPriceLamports: new anchor.BN(ticketPrice * anchor.web3.LAMPORTS_PER_SOL),
+          saleStartTs: new anchor.BN(saleStartTs),
+          saleEndTs: new anchor.BN(saleEndTs),
+          drawAfterTs: new anchor.BN(saleEndTs),
+          claimDeadlineTs: new anchor.BN(saleEndTs + CLAIM_PERIOD_SECS),
+          prizeSharesBps: DEFAULT_PRIZE_SHARES_BPS,
+          minTickets: DEFAULT_PRIZE_SHARES_BPS.length,
+          maxTickets: 0,
+          maxTicketsPerWallet: 0,
+          nftTickets: false,
+          referralBps: 0,
+          crankBountyLamports: new anchor.BN(0),
+          gameMode: { ticketDraw: {} },
+          numbersPerTicket: 0,
+          maxNumber: 0,
+          prizeMint: null,
+          prizeAmount: new anchor.BN(0),
+        })
         .accounts({
           lotteryPda: lotteryAddr,
           masterPda: masterPdaAddress,
           authority: wallet.publicKey,
           systemProgram: SystemProgram.programId,
         } as any)
+        .postInstructions([commitIx])
         .rpc();
+      localStorage.setItem(secretStorageKey(lotteryAddr), Buffer.from(secret).toString("hex"));
       console.log("Lottery created with transaction:", txo);
       setLotteries((prev) => ({
         ...prev,
@@ -204,9 +361,20 @@ export const Lottery: React.FC<{ initialLotteryId: number | null }> = ({ initial
           authority: wallet.publicKey,
           ticketPriceSOL: ticketPrice,
           lastTicketId: 0,
-          winnerTicketId: null,
-          claimed: false,
+          prizeTiers: DEFAULT_PRIZE_SHARES_BPS.map((shareBps) => ({ shareBps, winnerTicketId: null, claimed: false })),
           totalPrizeSOL: 0,
+          salesClosedSlot: null,
+          saleStartTs,
+          saleEndTs,
+          drawAfterTs: saleEndTs,
+          ticketMint: null,
+          minTickets: DEFAULT_PRIZE_SHARES_BPS.length,
+          cancelled: false,
+          ticketsRefunded: 0,
+          claimDeadlineTs: saleEndTs + CLAIM_PERIOD_SECS,
+          expired: false,
+          nftTickets: false,
+          numbersGame: false,
         },
       }));
 
@@ -228,11 +396,17 @@ export const Lottery: React.FC<{ initialLotteryId: number | null }> = ({ initial
     const ticketPda = getTicketKey(programID, lotteryAddr, nextTicketId);
 
     try {
+      // Every buyer mixes fresh randomness into the draw
+      const entropy = Array.from(crypto.getRandomValues(new Uint8Array(32)));
       let txo = await program.methods
-        .buyTicket()
+        .buyTicket(entropy, Buffer.from([]))
         .accounts({
           ticketPda: ticketPda,
           lotteryPda: lotteryAddr,
+          walletTickets: getWalletTicketsKey(programID, lotteryAddr, wallet.publicKey),
+          referralPda: null,
+          playerStats: getPlayerStatsKey(programID, wallet.publicKey),
+          masterPda: getMasterAddr(programID),
           buyer: wallet.publicKey,
           systemProgram: SystemProgram.programId,
         } as any)
@@ -245,6 +419,105 @@ export const Lottery: React.FC<{ initialLotteryId: number | null }> = ({ initial
     }
   };
 
+  const logProgramError = (action: string, lotteryId: number, error: any) => {
+    console.error(`Error ${action}:`, error, error.logs);
+    if (error && error.logs && Array.isArray(error.logs)) {
+      console.group(`Error logs for ${action} (lottery ${lotteryId}):`);
+      error.logs.forEach((log: string, idx: number) => {
+        console.error(`Log ${idx + 1}:`, log);
+      });
+      console.groupEnd();
+    }
+  };
+
+  const closeSales = async (program: Program<LotteryProgram>, lotteryId: number) => {
+    if (!wallet) return;
+    const lotteryKey = getLotteryKey(programID, lotteryId);
+    try {
+      await program.methods
+        .closeSales()
+        .accounts({
+          lotteryPda: lotteryKey,
+        } as any)
+        .rpc();
+      console.log(`Sales closed for lottery ${lotteryId}!`);
+      fetchLotteries(program);
+    } catch (error: any) {
+      logProgramError("closing sales", lotteryId, error);
+    }
+  };
+
+  const cancelLottery = async (program: Program<LotteryProgram>, lotteryId: number) => {
+    if (!wallet) return;
+    try {
+      await program.methods
+        .cancelLottery()
+        .accounts({
+          lotteryPda: getLotteryKey(programID, lotteryId),
+          caller: wallet.publicKey,
+          prizeMint: null,
+          prizeVault: null,
+          authorityPrizeAccount: null,
+          tokenProgram: null,
+        } as any)
+        .rpc();
+      console.log(`Lottery ${lotteryId} cancelled!`);
+      fetchLotteries(program);
+    } catch (error: any) {
+      logProgramError("cancelling lottery", lotteryId, error);
+    }
+  };
+
+  const closeLottery = async (program: Program<LotteryProgram>, lotteryId: number) => {
+    if (!wallet) return;
+    const lotteryKey = getLotteryKey(programID, lotteryId);
+    try {
+      await (program.methods as any)
+        .closeLottery()
+        .accounts({
+          lotteryPda: lotteryKey,
+          ticketLedger: getTicketLedgerKey(programID, lotteryKey),
+          ticketMint: null,
+          prizeVault: null,
+          authorityTokenAccount: null,
+          jackpotPda: getJackpotAddr(programID),
+          authority: wallet.publicKey,
+          tokenProgram: null,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .rpc();
+      console.log(`Lottery ${lotteryId} closed!`);
+      fetchLotteries(program);
+    } catch (error: any) {
+      logProgramError("closing lottery", lotteryId, error);
+    }
+  };
+
+  // Without a next lottery the unclaimed prizes go to the master jackpot
+  const rolloverUnclaimed = async (program: Program<LotteryProgram>, lotteryId: number) => {
+    if (!wallet) return;
+    try {
+      await (program.methods as any)
+        .rolloverUnclaimed()
+        .accounts({
+          lotteryPda: getLotteryKey(programID, lotteryId),
+          nextLottery: null,
+          jackpotPda: getJackpotAddr(programID),
+          ticketMint: null,
+          prizeVault: null,
+          nextPrizeVault: null,
+          caller: wallet.publicKey,
+          tokenProgram: null,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .rpc();
+      console.log(`Unclaimed prizes of lottery ${lotteryId} rolled over!`);
+      fetchLotteries(program);
+    } catch (error: any) {
+      logProgramError("rolling over unclaimed prizes", lotteryId, error);
+    }
+  };
+
   const pickWinner = async (program: Program<LotteryProgram>, lotteryId: number) => {
     if (!wallet || selectedLotteryId === null) {
       console.error("Wallet not connected or no lottery selected.");
@@ -257,29 +530,51 @@ export const Lottery: React.FC<{ initialLotteryId: number | null }> = ({ initial
     }
 
     const lotteryKey = getLotteryKey(programID, lotteryId);
+    const secretHex = localStorage.getItem(secretStorageKey(lotteryKey));
+    if (!secretHex) {
+      console.error(`No reveal secret stored in this browser for lottery ${lotteryId}.`);
+      return;
+    }
     try {
-      let txo = await (program.methods as any)
-        .pickWinner()
+      let txo = await program.methods
+        .revealAndPickWinner(Array.from(Buffer.from(secretHex, "hex")))
         .accounts({
           lotteryPda: lotteryKey,
           authority: wallet.publicKey,
+          slotHashes: SYSVAR_SLOT_HASHES_PUBKEY,
         } as any)
         .rpc();
       console.log(`Winner picked for lottery ${lotteryId}!`);
       fetchLotteries(program); // Refresh lottery details
     } catch (error: any) {
-      console.error("Error picking winner:", error, error.logs);
-      if (error && error.logs && Array.isArray(error.logs)) {
-        console.group(`Error logs for picking winner (lottery ${lotteryId}):`);
-        error.logs.forEach((log: string, idx: number) => {
-          console.error(`Log ${idx + 1}:`, log);
-        });
-        console.groupEnd();
-      }
+      logProgramError("picking winner", lotteryId, error);
+    }
+  };
+
+  const crankDraw = async (program: Program<LotteryProgram>, lotteryId: number) => {
+    if (!wallet) {
+      console.error("Wallet not connected.");
+      return;
+    }
+    const lotteryKey = getLotteryKey(programID, lotteryId);
+    try {
+      await program.methods
+        .crankDraw()
+        .accounts({
+          lotteryPda: lotteryKey,
+          masterPda: getMasterAddr(programID),
+          caller: wallet.publicKey,
+          slotHashes: SYSVAR_SLOT_HASHES_PUBKEY,
+        } as any)
+        .rpc();
+      console.log(`Draw of lottery ${lotteryId} cranked!`);
+      fetchLotteries(program);
+    } catch (error: any) {
+      logProgramError("cranking the draw", lotteryId, error);
     }
   };
 
-  const claimPrize = async (program: Program<LotteryProgram>, lotteryId: number) => {
+  const claimPrize = async (program: Program<LotteryProgram>, lotteryId: number, tier: number) => {
     if (!wallet || selectedLotteryId === null) {
       console.error("Wallet not connected or no lottery selected.");
       return;
@@ -291,26 +586,42 @@ export const Lottery: React.FC<{ initialLotteryId: number | null }> = ({ initial
     }
     const lotteryKey = getLotteryKey(programID, lotteryId);
     try {
+      // Tickets bought in bulk have no TicketPDA and are looked up in the ticket ledger instead
+      const ticketKey = getTicketKey(programID, lotteryKey, lottery.prizeTiers[tier].winnerTicketId!);
+      const hasTicketPda = (await program.provider.connection.getAccountInfo(ticketKey)) !== null;
       let txo = await (program.methods as any)
-        .claimPrize()
+        .claimPrize(tier)
         .accounts({
           lotteryPda: lotteryKey,
-          winnerTicket: getTicketKey(programID, lotteryKey, lottery.winnerTicketId!),
+          winnerTicket: hasTicketPda ? ticketKey : null,
+          ticketLedger: hasTicketPda ? null : getTicketLedgerKey(programID, lotteryKey),
+          treasuryPda: getTreasuryAddr(programID),
+          playerStats: getPlayerStatsKey(programID, wallet.publicKey),
           winner: wallet.publicKey,
           systemProgram: SystemProgram.programId,
         } as any)
         .rpc();
-      console.log(`Winner claimed for lottery ${lotteryId}!`);
+      console.log(`Tier ${tier + 1} claimed for lottery ${lotteryId}!`);
       fetchLotteries(program); // Refresh lottery details
     } catch (error: any) {
-      console.error("Error claiming prize:", error, error.logs);
-      if (error && error.logs && Array.isArray(error.logs)) {
-        console.group(`Error logs for claiming prize (lottery ${lotteryId}):`);
-        error.logs.forEach((log: string, idx: number) => {
-          console.error(`Log ${idx + 1}:`, log);
-        });
-        console.groupEnd();
-      }
+      logProgramError("claiming prize", lotteryId, error);
+    }
+  };
+
+  const setPaused = async (program: Program<LotteryProgram>, paused: boolean) => {
+    if (!wallet) return;
+    try {
+      await program.methods
+        .setPaused(paused)
+        .accounts({
+          masterPda: getMasterAddr(programID),
+          admin: wallet.publicKey,
+        } as any)
+        .rpc();
+      console.log(`Program ${paused ? "paused" : "unpaused"}!`);
+      await fetchMasterData();
+    } catch (error: any) {
+      console.error("Error setting pause:", error, error.logs);
     }
   };
 
@@ -342,7 +653,14 @@ export const Lottery: React.FC<{ initialLotteryId: number | null }> = ({ initial
         {masterPdaData && (
           <>
             <p>Last Lottery ID: {masterPdaData.lastLotteryId}</p>
-            <Button onClick={async () => await createLottery(program!)}>Create Next Lottery</Button>
+            <p>Protocol Fee: {masterPdaData.feeBps / 100}%</p>
+            {masterPdaData.paused && <p>Paused: no new lotteries, ticket sales or draws</p>}
+            <Button onClick={async () => await createLottery(program!)} disabled={masterPdaData.paused}>
+              Create Next Lottery
+            </Button>
+            {wallet?.publicKey.equals(masterPdaData.admin) && (
+              <Button onClick={() => program && setPaused(program, !masterPdaData.paused)}>{masterPdaData.paused ? "Unpause Program" : "Pause Program"}</Button>
+            )}
           </>
         )}
       </div>
@@ -400,42 +718,80 @@ export const Lottery: React.FC<{ initialLotteryId: number | null }> = ({ initial
         <p className={css({ wordBreak: "break-all" })}>
           Authority: {lottery.authority.toBase58().slice(0, 4)}...{lottery.authority.toBase58().slice(-4)}
         </p>
-        <p>Ticket Price: {lottery.ticketPriceSOL} SOL</p>
-        <p>Tickets bought: {lottery.lastTicketId}</p>
+        {lottery.ticketMint ? (
+          <p className={css({ wordBreak: "break-all" })}>
+            Ticket Price: {lottery.ticketPriceSOL * anchor.web3.LAMPORTS_PER_SOL} base units of {lottery.ticketMint.toBase58()}
+          </p>
+        ) : (
+          <p>Ticket Price: {lottery.ticketPriceSOL} SOL</p>
+        )}
+        <p>
+          Tickets bought: {lottery.lastTicketId} (minimum {lottery.minTickets})
+        </p>
+        <p>{scheduleStatus(lottery, now)}</p>
 
         {(() => {
-          switch (lottery.winnerTicketId) {
-            case null:
-              return (
-                <div className={hstack({ gap: "4", marginTop: "4", minWidth: 0 })}>
-                  <Button onClick={() => program && buyTicket(program, lottery.id)} disabled={lottery.winnerTicketId !== null}>
-                    Buy Ticket
-                  </Button>
-                  <Button onClick={() => program && pickWinner(program, lottery.id)} disabled={lottery.winnerTicketId !== null || lottery.claimed || lottery.lastTicketId === 0}>
-                    Pick Winner
-                  </Button>
-                </div>
-              );
-            default:
-              return (
-                <>
-                  <p>Winner Ticket ID: {lottery.winnerTicketId}</p>
-                  {lottery.claimed ? (
+          const isAuthority = wallet?.publicKey.equals(lottery.authority);
+          if (lottery.cancelled) {
+            return (
+              isAuthority &&
+              lottery.ticketMint === null && (
+                <Button onClick={() => program && closeLottery(program, lottery.id)} disabled={lottery.ticketsRefunded < lottery.lastTicketId}>
+                  Close Lottery
+                </Button>
+              )
+            );
+          }
+          if (lottery.prizeTiers[0]?.winnerTicketId === null) {
+            return (
+              <div className={hstack({ gap: "4", marginTop: "4", minWidth: 0, flexWrap: "wrap" })}>
+                <Button onClick={() => program && buyTicket(program, lottery.id)} disabled={lottery.ticketMint !== null || lottery.nftTickets || lottery.numbersGame || lottery.salesClosedSlot !== null || now < lottery.saleStartTs || now >= lottery.saleEndTs}>
+                  Buy Ticket
+                </Button>
+                <Button onClick={() => program && closeSales(program, lottery.id)} disabled={lottery.salesClosedSlot !== null || lottery.lastTicketId === 0 || now < lottery.drawAfterTs}>
+                  Close Sales
+                </Button>
+                <Button onClick={() => program && pickWinner(program, lottery.id)} disabled={lottery.salesClosedSlot === null}>
+                  Reveal & Pick Winner
+                </Button>
+                <Button onClick={() => program && crankDraw(program, lottery.id)} disabled={lottery.salesClosedSlot === null}>
+                  Crank Draw
+                </Button>
+                <Button onClick={() => program && cancelLottery(program, lottery.id)}>Cancel Lottery</Button>
+              </div>
+            );
+          }
+          return (
+            <div className={css(col, { gap: "2" })}>
+              {lottery.prizeTiers.map((tier, index) => (
+                <div key={index}>
+                  <p>
+                    Prize {index + 1} ({tier.shareBps / 100}%): ticket ID {tier.winnerTicketId}
+                  </p>
+                  {tier.claimed ? (
                     "Claimed by winner"
+                  ) : lottery.expired ? (
+                    "Expired, rolled over"
                   ) : (
                     <div>
                       <p>Not claimed yet</p>
-                      <Button
-                        onClick={() => program && claimPrize(program, lottery.id)}
-                        disabled={lottery.winnerTicketId === null || lottery.claimed || lottery.lastTicketId === 0}
-                      >
+                      <Button onClick={() => program && claimPrize(program, lottery.id, index)} disabled={tier.claimed || lottery.ticketMint !== null || lottery.nftTickets || lottery.numbersGame || now >= lottery.claimDeadlineTs}>
                         Claim Prize
                       </Button>
                     </div>
                   )}
-                </>
-              );
-          }
+                </div>
+              ))}
+              {!lottery.expired && lottery.ticketMint === null && now >= lottery.claimDeadlineTs && lottery.prizeTiers.some((tier) => !tier.claimed) && (
+                <Button onClick={() => program && rolloverUnclaimed(program, lottery.id)}>Roll Over Unclaimed Prizes</Button>
+              )}
+              {isAuthority && lottery.ticketMint === null && (
+                <Button onClick={() => program && closeLottery(program, lottery.id)} disabled={!lottery.expired && lottery.prizeTiers.some((tier) => !tier.claimed)}>
+                  Close Lottery
+                </Button>
+              )}
+            </div>
+          );
         })()}
       </div>
     );